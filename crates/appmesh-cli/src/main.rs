@@ -31,7 +31,7 @@ enum Command {
     },
     /// Execute a command on a named port
     Port {
-        /// Port name (clipboard, input, notify, screenshot, windows)
+        /// Port name (clipboard, desktops, input, mail, notify, outputs, screenshot, windows)
         port: String,
 
         /// Command to execute on the port
@@ -41,6 +41,11 @@ enum Command {
         #[arg(trailing_var_arg = true)]
         args: Vec<String>,
     },
+    /// Stream events from a port as JSON lines
+    Watch {
+        /// Port name (e.g. windows, desktops)
+        port: String,
+    },
     /// List available ports and their commands
     Ports,
 }
@@ -129,6 +134,27 @@ fn main() {
                 }
             }
         }
+        Command::Watch { port } => {
+            let port_obj = match appmesh_core::ffi::open_port(&port) {
+                Ok(p) => p,
+                Err(e) => {
+                    eprintln!("appmesh: failed to open port '{}': {}", port, e);
+                    process::exit(1);
+                }
+            };
+
+            let events = match port_obj.subscribe() {
+                Ok(events) => events,
+                Err(e) => {
+                    eprintln!("appmesh: {} watch failed: {}", port, e.message);
+                    process::exit(1);
+                }
+            };
+
+            while let Some(event) = events.recv() {
+                println!("{}", serde_json::to_string(&event).unwrap_or_default());
+            }
+        }
         Command::Ports => {
             println!("Available ports:\n");
             for &name in appmesh_core::ffi::PORT_NAMES {
@@ -33,5 +33,22 @@ char *appmesh_port_execute(appmesh_port_t port, const char *cmd, const char *arg
 /* Free a port handle. Safe to call with NULL. */
 void appmesh_port_free(appmesh_port_t port);
 
-/* Free a string returned by appmesh_port_execute. Safe to call with NULL. */
+/* Free a string returned by appmesh_port_execute or appmesh_events_next.
+   Safe to call with NULL. */
 void appmesh_string_free(char *s);
+
+/* === Port events === */
+
+typedef void *appmesh_events_t;
+
+/* Subscribe to a port's events. Returns NULL if the port emits none.
+   The subscription stays valid after the port handle is freed. */
+appmesh_events_t appmesh_port_subscribe(appmesh_port_t port);
+
+/* Wait up to timeout_ms for the next event.
+   Returns JSON {"port","event","data"} (free with appmesh_string_free),
+   or NULL on timeout. */
+char *appmesh_events_next(appmesh_events_t events, uint64_t timeout_ms);
+
+/* Stop the subscription and free it. Safe to call with NULL. */
+void appmesh_events_free(appmesh_events_t events);
@@ -0,0 +1,234 @@
+//! Screen capture through KWin's `org.kde.KWin.ScreenShot2` D-Bus interface.
+//!
+//! Every capture method takes a write-end pipe fd: KWin replies with the image
+//! metadata (`width`, `height`, `stride`, `format`) and then streams the raw
+//! pixels into the pipe, closing it when done. The pixels are a `QImage` buffer
+//! and are converted to an [`RgbaImage`] here, ready for in-process encoding.
+//!
+//! KWin only serves callers whose desktop file lists
+//! `X-KDE-DBUS-Restricted-Interfaces=org.kde.KWin.ScreenShot2`; other callers
+//! get a `NoAuthorized` error and should fall back to spectacle.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::os::fd::{FromRawFd, OwnedFd};
+
+use image::RgbaImage;
+use zbus::zvariant;
+
+/// What to capture.
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// All outputs combined.
+    Workspace,
+    /// The output with the active window.
+    ActiveScreen,
+    /// An output by name (e.g. `DP-1`).
+    Screen(String),
+    /// The active window.
+    ActiveWindow,
+    /// A window by KWin internal ID (as returned by `windows list`).
+    Window(String),
+    /// A rectangle in global logical coordinates.
+    Area { x: i32, y: i32, width: u32, height: u32 },
+    /// Let the user click a window.
+    PickWindow,
+    /// Let the user click an output.
+    PickScreen,
+}
+
+/// Capture options understood by KWin.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub include_cursor: bool,
+    /// Window captures only: include the title bar and borders.
+    pub include_decoration: bool,
+    /// Window captures only: include the drop shadow.
+    pub include_shadow: bool,
+    /// Capture at device pixels rather than logical size on scaled outputs.
+    pub native_resolution: bool,
+}
+
+/// Output encodings for captured images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Ok(Self::Png),
+            "jpg" | "jpeg" => Ok(Self::Jpeg),
+            "webp" => Ok(Self::Webp),
+            other => Err(format!("unsupported format: {} (expected png, jpeg or webp)", other)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+            Self::Webp => "webp",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+/// Encode an image. `quality` (1-100) applies to JPEG; WebP output is lossless.
+pub fn encode(image: &RgbaImage, format: Format, quality: u8) -> Result<Vec<u8>, String> {
+    let mut out = std::io::Cursor::new(Vec::new());
+    let result = match format {
+        Format::Png => image.write_to(&mut out, image::ImageFormat::Png),
+        Format::Webp => image.write_to(&mut out, image::ImageFormat::WebP),
+        Format::Jpeg => {
+            // JPEG has no alpha channel
+            let rgb = image::DynamicImage::ImageRgba8(image.clone()).into_rgb8();
+            let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, quality.clamp(1, 100));
+            rgb.write_with_encoder(encoder)
+        }
+    };
+    result.map_err(|e| format!("{} encode failed: {}", format.name(), e))?;
+    Ok(out.into_inner())
+}
+
+/// Capture `target` through KWin and return the pixels.
+pub async fn capture(
+    connection: &zbus::Connection,
+    target: &Target,
+    options: &Options,
+) -> Result<RgbaImage, Box<dyn std::error::Error>> {
+    let proxy = zbus::Proxy::new(
+        connection,
+        "org.kde.KWin",
+        "/org/kde/KWin/ScreenShot2",
+        "org.kde.KWin.ScreenShot2",
+    )
+    .await?;
+
+    let mut opts: HashMap<&str, zvariant::Value> = HashMap::new();
+    opts.insert("include-cursor", options.include_cursor.into());
+    opts.insert("include-decoration", options.include_decoration.into());
+    opts.insert("include-shadow", options.include_shadow.into());
+    opts.insert("native-resolution", options.native_resolution.into());
+
+    let (read_end, write_end) = pipe()?;
+    // Drain the pipe while KWin writes so a large image cannot fill it and stall
+    let reader = std::thread::spawn(move || {
+        let mut data = Vec::new();
+        std::fs::File::from(read_end).read_to_end(&mut data).map(|_| data)
+    });
+
+    let fd = zvariant::Fd::from(&write_end);
+    let reply: zbus::Result<HashMap<String, zvariant::OwnedValue>> = match target {
+        Target::Workspace => proxy.call("CaptureWorkspace", &(opts, fd)).await,
+        Target::ActiveScreen => proxy.call("CaptureActiveScreen", &(opts, fd)).await,
+        Target::Screen(name) => proxy.call("CaptureScreen", &(name.as_str(), opts, fd)).await,
+        Target::ActiveWindow => proxy.call("CaptureActiveWindow", &(opts, fd)).await,
+        Target::Window(id) => proxy.call("CaptureWindow", &(id.as_str(), opts, fd)).await,
+        Target::Area { x, y, width, height } => {
+            proxy.call("CaptureArea", &(*x, *y, *width, *height, opts, fd)).await
+        }
+        Target::PickWindow => proxy.call("CaptureInteractive", &(0u32, opts, fd)).await,
+        Target::PickScreen => proxy.call("CaptureInteractive", &(1u32, opts, fd)).await,
+    };
+    // KWin holds its own copy of the fd; ours must go for the reader to see EOF
+    drop(write_end);
+    let meta = reply?;
+    let data = reader
+        .join()
+        .map_err(|_| "screenshot reader thread panicked")??;
+
+    let field = |name: &str| -> Result<u32, String> {
+        meta.get(name)
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| format!("ScreenShot2 reply has no '{}'", name))
+    };
+    if let Some(kind) = meta.get("type").and_then(|v| <&str>::try_from(v).ok()) {
+        if kind != "raw" {
+            return Err(format!("unsupported ScreenShot2 image type: {}", kind).into());
+        }
+    }
+    Ok(raw_to_rgba(
+        &data,
+        field("width")?,
+        field("height")?,
+        field("stride")?,
+        field("format")?,
+    )?)
+}
+
+fn pipe() -> std::io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+    // Safety: pipe2 fills both fds on success; each is then owned exactly once
+    unsafe {
+        if libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok((OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])))
+    }
+}
+
+// QImage::Format values KWin sends
+const FORMAT_RGB32: u32 = 4;
+const FORMAT_ARGB32: u32 = 5;
+const FORMAT_ARGB32_PREMULTIPLIED: u32 = 6;
+const FORMAT_RGBX8888: u32 = 16;
+const FORMAT_RGBA8888: u32 = 17;
+const FORMAT_RGBA8888_PREMULTIPLIED: u32 = 18;
+
+/// Convert a raw `QImage` buffer to straight-alpha RGBA.
+pub fn raw_to_rgba(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    stride: u32,
+    format: u32,
+) -> Result<RgbaImage, String> {
+    let (w, h, stride) = (width as usize, height as usize, stride as usize);
+    if stride < w * 4 || data.len() < stride * h.saturating_sub(1) + w * 4 {
+        return Err(format!(
+            "short image data: {} bytes for {}x{} (stride {})",
+            data.len(),
+            width,
+            height,
+            stride
+        ));
+    }
+
+    let mut out = Vec::with_capacity(w * h * 4);
+    for row in data.chunks(stride).take(h) {
+        for px in row[..w * 4].chunks_exact(4) {
+            // 32-bit ARGB formats are native-endian words, i.e. B, G, R, A in memory on
+            // the little-endian machines KWin runs on
+            let rgba = match format {
+                FORMAT_RGB32 => [px[2], px[1], px[0], 255],
+                FORMAT_ARGB32 => [px[2], px[1], px[0], px[3]],
+                FORMAT_ARGB32_PREMULTIPLIED => unpremultiply([px[2], px[1], px[0], px[3]]),
+                FORMAT_RGBX8888 => [px[0], px[1], px[2], 255],
+                FORMAT_RGBA8888 => [px[0], px[1], px[2], px[3]],
+                FORMAT_RGBA8888_PREMULTIPLIED => unpremultiply([px[0], px[1], px[2], px[3]]),
+                other => return Err(format!("unsupported QImage format: {}", other)),
+            };
+            out.extend_from_slice(&rgba);
+        }
+    }
+    RgbaImage::from_raw(width, height, out).ok_or_else(|| "image buffer size mismatch".to_string())
+}
+
+fn unpremultiply([r, g, b, a]: [u8; 4]) -> [u8; 4] {
+    if a == 0 || a == 255 {
+        return [r, g, b, a];
+    }
+    let scale = |c: u8| ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+    [scale(r), scale(g), scale(b), a]
+}
@@ -0,0 +1,447 @@
+//! Message composition helpers for the mail port.
+//!
+//! Pure string and byte handling — address lists, attachment content types and
+//! a plain-text view of HTML bodies — kept apart from the JMAP calls.
+
+use std::fmt;
+use std::path::Path;
+
+/// A mailbox address with an optional display name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    pub name: Option<String>,
+    pub email: String,
+}
+
+impl Address {
+    /// Parse `user@host`, `Name <user@host>` or `"Last, First" <user@host>`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (name, email) = match (text.rfind('<'), text.ends_with('>')) {
+            (Some(open), true) => {
+                let name = text[..open].trim().trim_matches('"').trim();
+                (name, text[open + 1..text.len() - 1].trim())
+            }
+            _ => ("", text),
+        };
+        let (local, domain) = email.split_once('@')?;
+        if local.is_empty() || domain.is_empty() || email.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(Self {
+            name: (!name.is_empty()).then(|| name.to_string()),
+            email: email.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) if name.contains([',', '"', '<', '>', '@']) => {
+                write!(f, "\"{}\" <{}>", name.replace('"', "'"), self.email)
+            }
+            Some(name) => write!(f, "{} <{}>", name, self.email),
+            None => f.write_str(&self.email),
+        }
+    }
+}
+
+/// Parse a comma-separated address list. Commas inside quotes or angle
+/// brackets do not split, so `"Smith, Bob" <bob@example.com>` is one entry.
+pub fn parse_addresses(list: &str) -> Result<Vec<Address>, String> {
+    let mut entries = Vec::new();
+    let (mut start, mut quoted, mut angle) = (0, false, false);
+    for (i, c) in list.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '<' if !quoted => angle = true,
+            '>' if !quoted => angle = false,
+            ',' | ';' if !quoted && !angle => {
+                entries.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    entries.push(&list[start..]);
+
+    entries
+        .into_iter()
+        .filter(|e| !e.trim().is_empty())
+        .map(|e| Address::parse(e).ok_or_else(|| format!("invalid address: {}", e.trim())))
+        .collect()
+}
+
+/// MIME type for an attachment, from its extension or else its first bytes.
+pub fn content_type(path: &Path, data: &[u8]) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let by_ext = match ext.as_deref() {
+        Some("txt" | "log") => Some("text/plain"),
+        Some("md") => Some("text/markdown"),
+        Some("csv") => Some("text/csv"),
+        Some("html" | "htm") => Some("text/html"),
+        Some("ics") => Some("text/calendar"),
+        Some("vcf") => Some("text/vcard"),
+        Some("json") => Some("application/json"),
+        Some("xml") => Some("application/xml"),
+        Some("pdf") => Some("application/pdf"),
+        Some("zip") => Some("application/zip"),
+        Some("gz" | "tgz") => Some("application/gzip"),
+        Some("tar") => Some("application/x-tar"),
+        Some("doc") => Some("application/msword"),
+        Some("docx") => Some("application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+        Some("xls") => Some("application/vnd.ms-excel"),
+        Some("xlsx") => Some("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+        Some("odt") => Some("application/vnd.oasis.opendocument.text"),
+        Some("ods") => Some("application/vnd.oasis.opendocument.spreadsheet"),
+        Some("png") => Some("image/png"),
+        Some("jpg" | "jpeg") => Some("image/jpeg"),
+        Some("gif") => Some("image/gif"),
+        Some("webp") => Some("image/webp"),
+        Some("svg") => Some("image/svg+xml"),
+        Some("mp3") => Some("audio/mpeg"),
+        Some("ogg") => Some("audio/ogg"),
+        Some("mp4") => Some("video/mp4"),
+        Some("webm") => Some("video/webm"),
+        Some("eml") => Some("message/rfc822"),
+        _ => None,
+    };
+    by_ext.unwrap_or_else(|| sniff(data))
+}
+
+fn sniff(data: &[u8]) -> &'static str {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+    ];
+    if let Some((_, mime)) = MAGIC.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return mime;
+    }
+    if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    if std::str::from_utf8(data).is_ok() {
+        return "text/plain";
+    }
+    "application/octet-stream"
+}
+
+/// Escape text for inclusion in an HTML body.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Plain-text rendering of an HTML body, for the text/plain alternative.
+pub fn html_to_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        out.push_str(&decode_entities(&rest[..open]));
+        // A '<' that does not open a tag is text
+        if !rest[open + 1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!') {
+            out.push('<');
+            rest = &rest[open + 1..];
+            continue;
+        }
+        let Some(close) = rest[open..].find('>') else {
+            rest = &rest[open..];
+            break;
+        };
+        let tag = rest[open + 1..open + close].trim().to_ascii_lowercase();
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("");
+        rest = &rest[open + close + 1..];
+
+        // Drop the contents of elements that are never displayed
+        if !tag.starts_with('/') && matches!(name, "style" | "script" | "head" | "title") {
+            let end = format!("</{}", name);
+            match rest.to_ascii_lowercase().find(&end) {
+                Some(pos) => {
+                    let after = rest[pos..].find('>').map_or(rest.len(), |p| pos + p + 1);
+                    rest = &rest[after..];
+                }
+                None => rest = "",
+            }
+            continue;
+        }
+        match name {
+            "br" => out.push('\n'),
+            "p" | "div" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "blockquote" | "pre"
+            | "ul" | "ol" | "table" => out.push('\n'),
+            "li" if !tag.starts_with('/') => out.push_str("\n- "),
+            "td" | "th" if tag.starts_with('/') => out.push('\t'),
+            _ => {}
+        }
+    }
+    out.push_str(&decode_entities(rest));
+
+    // Trim each line and collapse runs of blank lines
+    let mut text = String::new();
+    let mut blank = 0;
+    for line in out.lines().map(|l| l.trim_end()) {
+        if line.trim().is_empty() {
+            blank += 1;
+            continue;
+        }
+        if !text.is_empty() {
+            text.push_str(if blank > 0 { "\n\n" } else { "\n" });
+        }
+        blank = 0;
+        text.push_str(line.trim_start_matches([' ', '\t']));
+    }
+    text
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `YYYY-MM-DD HH:MM UTC` for a unix timestamp, as used in attribution lines.
+pub fn format_date(timestamp: i64) -> String {
+    let (year, month, day, secs) = civil(timestamp);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60
+    )
+}
+
+/// RFC 3339 `YYYY-MM-DDTHH:MM:SSZ` for a unix timestamp.
+pub fn format_rfc3339(timestamp: i64) -> String {
+    let (year, month, day, secs) = civil(timestamp);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Year, month, day and seconds into the day (UTC) for a unix timestamp.
+fn civil(timestamp: i64) -> (i64, i64, i64, i64) {
+    let (days, secs) = (timestamp.div_euclid(86400), timestamp.rem_euclid(86400));
+    // Civil date from days since 1970-01-01 (proleptic Gregorian)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day, secs)
+}
+
+/// Reply prefixes used by mail clients in various languages (compared lowercase).
+///
+/// Italian Outlook's one-letter `R:` and `I:` are left out: they cannot be told
+/// apart from subjects like `I: need help`.
+const REPLY_PREFIXES: &[&str] = &[
+    "re", "aw", "sv", "vs", "antw", "ref", "rif", "ris", "odp", "ynt", "atb", "res",
+    "απ", "σχετ", "回复", "答复", "回覆",
+];
+
+/// Forward prefixes used by mail clients in various languages (compared lowercase).
+const FORWARD_PREFIXES: &[&str] = &[
+    "fw", "fwd", "wg", "tr", "rv", "enc", "doorst", "vl", "pd", "ilt", "προώθ", "转发",
+    "轉寄",
+];
+
+/// Subject for a reply: `Re: ` plus the subject without any reply prefixes,
+/// so `AW: Re[2]: Hello` becomes `Re: Hello`.
+pub fn reply_subject(subject: &str) -> String {
+    format!("Re: {}", strip_prefixes(subject, REPLY_PREFIXES)).trim_end().to_string()
+}
+
+/// Subject for a forward: `Fwd: ` plus the subject without any forward prefixes.
+pub fn forward_subject(subject: &str) -> String {
+    format!("Fwd: {}", strip_prefixes(subject, FORWARD_PREFIXES)).trim_end().to_string()
+}
+
+/// Remove leading `prefix:` markers, including counted forms like `Re[2]:`,
+/// `Re(2):` and the full-width colon.
+fn strip_prefixes<'a>(subject: &'a str, prefixes: &[&str]) -> &'a str {
+    let mut rest = subject.trim();
+    loop {
+        let Some(colon) = rest.find([':', '：']) else {
+            return rest;
+        };
+        let word = rest[..colon].trim_end();
+        // Drop a [n] or (n) counter after the prefix
+        let word = match word.strip_suffix([']', ')']) {
+            Some(inner) => match inner.rfind(['[', '(']) {
+                Some(open) if inner[open + 1..].chars().all(|c| c.is_ascii_digit()) => &inner[..open],
+                _ => word,
+            },
+            None => word,
+        };
+        if !prefixes.contains(&word.to_lowercase().as_str()) {
+            return rest;
+        }
+        let colon_len = rest[colon..].chars().next().map_or(1, char::len_utf8);
+        rest = rest[colon + colon_len..].trim_start();
+    }
+}
+
+/// Quote text for a reply, prefixing every line with `> `.
+pub fn quote(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            if line.is_empty() {
+                ">".to_string()
+            } else if line.starts_with('>') {
+                format!(">{}", line)
+            } else {
+                format!("> {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `On <date>, <sender> wrote:` above a quoted original.
+pub fn attribution(timestamp: Option<i64>, sender: Option<&Address>) -> String {
+    let who = sender.map_or_else(|| "someone".to_string(), |a| a.to_string());
+    match timestamp {
+        Some(ts) => format!("On {}, {} wrote:", format_date(ts), who),
+        None => format!("{} wrote:", who),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_prefixes_removes_stacked_and_counted_markers() {
+        assert_eq!(reply_subject("AW: Re[2]: Hello"), "Re: Hello");
+        assert_eq!(reply_subject("RE(3): re: Budget"), "Re: Budget");
+        assert_eq!(reply_subject("回复：Meeting"), "Re: Meeting");
+        assert_eq!(reply_subject(""), "Re:");
+        assert_eq!(forward_subject("Fwd: FW: Report"), "Fwd: Report");
+        // Replies keep forward markers and forwards keep reply markers
+        assert_eq!(reply_subject("Fwd: Report"), "Re: Fwd: Report");
+        assert_eq!(forward_subject("Re: Report"), "Fwd: Re: Report");
+    }
+
+    #[test]
+    fn strip_prefixes_keeps_words_that_only_look_like_prefixes() {
+        assert_eq!(forward_subject("I: need help"), "Fwd: I: need help");
+        assert_eq!(reply_subject("R: the language"), "Re: R: the language");
+        assert_eq!(reply_subject("Agenda: Monday"), "Re: Agenda: Monday");
+        assert_eq!(reply_subject("Re[v2]: patch"), "Re: Re[v2]: patch");
+    }
+
+    #[test]
+    fn parse_addresses_splits_outside_quotes_and_brackets() {
+        let list = parse_addresses(r#""Smith, Bob" <bob@example.com>, ann@example.com; Eve <"e,ve"@example.com>"#)
+            .unwrap();
+        assert_eq!(
+            list,
+            [
+                Address { name: Some("Smith, Bob".into()), email: "bob@example.com".into() },
+                Address { name: None, email: "ann@example.com".into() },
+                Address { name: Some("Eve".into()), email: "\"e,ve\"@example.com".into() },
+            ]
+        );
+        assert_eq!(parse_addresses(" , ").unwrap(), []);
+        assert_eq!(parse_addresses("ann@example.com, nobody").unwrap_err(), "invalid address: nobody");
+    }
+
+    #[test]
+    fn address_parse_and_display() {
+        assert_eq!(
+            Address::parse("  Ann Lee <ann@example.com> "),
+            Some(Address { name: Some("Ann Lee".into()), email: "ann@example.com".into() })
+        );
+        assert_eq!(Address::parse("<ann@example.com>").unwrap().name, None);
+        assert_eq!(Address::parse("ann@example.com").unwrap().email, "ann@example.com");
+        assert_eq!(Address::parse("ann"), None);
+        assert_eq!(Address::parse("@example.com"), None);
+        assert_eq!(Address::parse("ann lee@example.com"), None);
+
+        let quoted = Address::parse(r#""Smith, Bob" <bob@example.com>"#).unwrap();
+        assert_eq!(quoted.to_string(), r#""Smith, Bob" <bob@example.com>"#);
+        assert_eq!(Address::parse("Ann <ann@example.com>").unwrap().to_string(), "Ann <ann@example.com>");
+    }
+
+    #[test]
+    fn html_to_text_renders_structure_and_entities() {
+        let html = "<html><head><title>x</title><style>p { color: red }</style></head>\
+                    <body><p>Hello&nbsp;<b>world</b> &amp; 1 &lt; 2 &#x263A;</p>\
+                    <ul><li>one</li><li>two</li></ul><br><SCRIPT>alert(1)</SCRIPT>\
+                    <table><tr><td>a</td><td>b</td></tr></table>a < b</body></html>";
+        assert_eq!(html_to_text(html), "Hello world & 1 < 2 \u{263A}\n\n- one\n- two\n\na\tb\n\na < b");
+        assert_eq!(html_to_text("<p>one</p><p></p><p></p><p>two</p>"), "one\n\ntwo");
+        assert_eq!(html_to_text("AT&T &bogus; &#65;"), "AT&T &bogus; A");
+    }
+
+    #[test]
+    fn civil_converts_unix_time() {
+        assert_eq!(civil(0), (1970, 1, 1, 0));
+        assert_eq!(civil(951_782_400), (2000, 2, 29, 0));
+        assert_eq!(civil(1_709_251_199), (2024, 2, 29, 86399));
+        assert_eq!(civil(-1), (1969, 12, 31, 86399));
+        assert_eq!(format_date(1_700_000_000), "2023-11-14 22:13 UTC");
+        assert_eq!(format_rfc3339(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+}
@@ -0,0 +1,144 @@
+//! Named JSON files under `~/.config/appmesh/`.
+//!
+//! Ports that let users save things by name (window layouts, output
+//! configurations) keep one pretty-printed `<name>.json` per entry in a
+//! subdirectory named after the kind of thing saved.
+
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// `$XDG_CONFIG_HOME/appmesh`, falling back to `~/.config/appmesh`.
+pub fn config_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(".config")
+        });
+    base.join("appmesh")
+}
+
+/// Directory holding saved entries of one kind, e.g. `layouts`.
+pub fn kind_dir(kind: &str) -> PathBuf {
+    config_dir().join(kind)
+}
+
+fn named_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains(['/', '\\', '\0'])
+    {
+        return Err(format!("invalid name: {:?}", name));
+    }
+    Ok(dir.join(format!("{}.json", name)))
+}
+
+pub fn save_named<T: Serialize>(kind: &str, name: &str, value: &T) -> Result<PathBuf, String> {
+    save_named_in(&kind_dir(kind), name, value)
+}
+
+fn save_named_in<T: Serialize>(dir: &Path, name: &str, value: &T) -> Result<PathBuf, String> {
+    let path = named_path(dir, name)?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("mkdir failed: {}", e))?;
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json + "\n").map_err(|e| format!("write failed: {}", e))?;
+    Ok(path)
+}
+
+/// `save_named` for entries holding secrets: the directory is made 0700 and
+/// the file is written 0600 to a temporary name and renamed into place, so it
+/// is never readable by others, even briefly or when replacing an older one.
+pub fn save_named_private<T: Serialize>(kind: &str, name: &str, value: &T) -> Result<PathBuf, String> {
+    save_named_private_in(&kind_dir(kind), name, value)
+}
+
+fn save_named_private_in<T: Serialize>(dir: &Path, name: &str, value: &T) -> Result<PathBuf, String> {
+    use std::io::Write;
+    use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+
+    let path = named_path(dir, name)?;
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(|e| format!("mkdir failed: {}", e))?;
+    // The mode only applies to directories it created
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+        .map_err(|e| format!("chmod failed: {}", e))?;
+
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    let tmp = dir.join(format!(".{}.json.tmp", name));
+    let _ = std::fs::remove_file(&tmp);
+    let written = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&tmp)
+        .and_then(|mut file| file.write_all((json + "\n").as_bytes()))
+        .and_then(|()| std::fs::rename(&tmp, &path));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(format!("write failed: {}", e));
+    }
+    Ok(path)
+}
+
+pub fn load_named<T: DeserializeOwned>(kind: &str, name: &str) -> Result<T, String> {
+    load_named_in(&kind_dir(kind), name)
+}
+
+fn load_named_in<T: DeserializeOwned>(dir: &Path, name: &str) -> Result<T, String> {
+    let path = named_path(dir, name)?;
+    let json = std::fs::read_to_string(&path)
+        .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    serde_json::from_str(&json).map_err(|e| format!("invalid {}: {}", path.display(), e))
+}
+
+/// Names of all saved entries of one kind, sorted.
+pub fn list_named(kind: &str) -> Vec<String> {
+    list_named_in(&kind_dir(kind))
+}
+
+fn list_named_in(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.strip_suffix(".json").map(str::to_string)
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn save_named_private_is_never_group_or_world_readable() {
+        let base = std::env::temp_dir().join(format!("appmesh-config-{}", std::process::id()));
+        let dir = base.join("secrets");
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        // An older copy saved world-readable
+        let path = save_named_in(&dir, "work", &"old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let saved = save_named_private_in(&dir, "work", &"hunter2").unwrap();
+        assert_eq!(saved, path);
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(&dir), 0o700);
+        assert_eq!(load_named_in::<String>(&dir, "work").unwrap(), "hunter2");
+        assert_eq!(list_named_in(&dir), ["work"]);
+        assert!(save_named_private_in(&dir, "../x", &"").is_err());
+
+        std::fs::remove_dir_all(base).unwrap();
+    }
+}
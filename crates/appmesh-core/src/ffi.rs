@@ -3,24 +3,34 @@ use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 
 use crate::input::InputHandle;
-use crate::port::AppMeshPort;
+use crate::port::{AppMeshPort, EventStream};
 use crate::ports::clipboard::ClipboardPort;
+use crate::ports::desktops::DesktopsPort;
 use crate::ports::input::InputPort;
 use crate::ports::mail::MailPort;
 use crate::ports::notify::NotifyPort;
+use crate::ports::outputs::OutputsPort;
 use crate::ports::screenshot::ScreenshotPort;
 use crate::ports::windows::WindowsPort;
 
 /// All available port names.
-pub const PORT_NAMES: &[&str] = &["clipboard", "input", "mail", "notify", "screenshot", "windows"];
+pub const PORT_NAMES: &[&str] = &["clipboard", "desktops", "input", "mail", "notify", "outputs", "screenshot", "windows"];
 
 /// Open a port by name. Returns the port or an error message.
 pub fn open_port(name: &str) -> Result<Box<dyn AppMeshPort>, String> {
+    // mail.<name> is the mail port defaulting to that named account
+    if let Some(account) = name.strip_prefix("mail.") {
+        return MailPort::with_account(account)
+            .map(|p| Box::new(p) as Box<dyn AppMeshPort>)
+            .map_err(|e| e.to_string());
+    }
     match name {
         "clipboard" => ClipboardPort::new().map(|p| Box::new(p) as Box<dyn AppMeshPort>),
+        "desktops" => DesktopsPort::new().map(|p| Box::new(p) as Box<dyn AppMeshPort>),
         "input" => InputPort::new().map(|p| Box::new(p) as Box<dyn AppMeshPort>),
         "mail" => MailPort::new().map(|p| Box::new(p) as Box<dyn AppMeshPort>),
         "notify" => NotifyPort::new().map(|p| Box::new(p) as Box<dyn AppMeshPort>),
+        "outputs" => OutputsPort::new().map(|p| Box::new(p) as Box<dyn AppMeshPort>),
         "screenshot" => ScreenshotPort::new().map(|p| Box::new(p) as Box<dyn AppMeshPort>),
         "windows" => WindowsPort::new().map(|p| Box::new(p) as Box<dyn AppMeshPort>),
         _ => return Err(format!("unknown port: {}", name)),
@@ -34,6 +44,7 @@ pub fn open_port(name: &str) -> Result<Box<dyn AppMeshPort>, String> {
 
 pub type AppmeshHandle = *mut InputHandle;
 pub type AppmeshPortHandle = *mut Box<dyn AppMeshPort>;
+pub type AppmeshEventsHandle = *mut EventStream;
 
 #[no_mangle]
 pub extern "C" fn appmesh_init() -> AppmeshHandle {
@@ -168,6 +179,51 @@ pub extern "C" fn appmesh_port_free(port: AppmeshPortHandle) {
     }
 }
 
+// ============================================================================
+// Port events — subscribe and poll from the caller's own loop
+// ============================================================================
+
+#[no_mangle]
+pub extern "C" fn appmesh_port_subscribe(port: AppmeshPortHandle) -> AppmeshEventsHandle {
+    if port.is_null() {
+        return std::ptr::null_mut();
+    }
+    let port = unsafe { &*port };
+
+    match port.subscribe() {
+        Ok(stream) => Box::into_raw(Box::new(stream)),
+        Err(e) => {
+            eprintln!("appmesh_port_subscribe({}) failed: {}", port.name(), e.message);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Returns the next event as JSON, or NULL if none arrived within `timeout_ms`.
+#[no_mangle]
+pub extern "C" fn appmesh_events_next(events: AppmeshEventsHandle, timeout_ms: u64) -> *mut c_char {
+    if events.is_null() {
+        return std::ptr::null_mut();
+    }
+    let events = unsafe { &*events };
+
+    let event = match events.next(std::time::Duration::from_millis(timeout_ms)) {
+        Some(event) => event,
+        None => return std::ptr::null_mut(),
+    };
+    match serde_json::to_string(&event).ok().and_then(|json| CString::new(json).ok()) {
+        Some(cs) => cs.into_raw(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn appmesh_events_free(events: AppmeshEventsHandle) {
+    if !events.is_null() {
+        unsafe { drop(Box::from_raw(events)); }
+    }
+}
+
 #[no_mangle]
 pub extern "C" fn appmesh_string_free(s: *mut c_char) {
     if !s.is_null() {
@@ -0,0 +1,586 @@
+//! Post-processing for screenshots: crop, resize, annotate and redact.
+//!
+//! Operations are written as a chain like
+//! `crop:0,0,800,600|rect:40,40,200,80,red,3|pixelate:300,20,160,30`
+//! (pipes or newlines between operations, commas between arguments) and
+//! applied left to right. Coordinates are in pixels of the image as it is at
+//! that point in the chain, so a `crop` shifts everything after it.
+
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+
+/// One image operation.
+#[derive(Debug, Clone)]
+pub enum Op {
+    Crop { x: u32, y: u32, width: u32, height: u32 },
+    /// Resize to `width`×`height`; a zero side keeps the aspect ratio.
+    Resize { width: u32, height: u32 },
+    /// Resize by a percentage of the current size.
+    Scale { percent: f32 },
+    /// Rectangle outline.
+    Rect { x: i32, y: i32, width: u32, height: u32, color: Rgba<u8>, thickness: f32 },
+    /// Filled rectangle, e.g. to black out secrets.
+    Fill { x: i32, y: i32, width: u32, height: u32, color: Rgba<u8> },
+    Arrow { from: (f32, f32), to: (f32, f32), color: Rgba<u8>, thickness: f32 },
+    /// Text with its top-left corner at `(x, y)`.
+    Text { x: f32, y: f32, size: f32, color: Rgba<u8>, text: String },
+    Blur { x: u32, y: u32, width: u32, height: u32, sigma: f32 },
+    Pixelate { x: u32, y: u32, width: u32, height: u32, block: u32 },
+}
+
+/// Operation names and their argument syntax, for help output.
+pub const OPS: &[(&str, &str)] = &[
+    ("crop", "crop:x,y,width,height"),
+    ("resize", "resize:width[,height] or resize:50%"),
+    ("rect", "rect:x,y,width,height[,color[,thickness]]"),
+    ("fill", "fill:x,y,width,height[,color] (redact)"),
+    ("arrow", "arrow:x1,y1,x2,y2[,color[,thickness]] (points at x2,y2)"),
+    ("text", "text:x,y,size,color,label (label may contain commas)"),
+    ("blur", "blur:x,y,width,height[,sigma]"),
+    ("pixelate", "pixelate:x,y,width,height[,block]"),
+];
+
+/// Parse an operation chain.
+pub fn parse_ops(chain: &str) -> Result<Vec<Op>, String> {
+    let ops: Vec<Op> = chain
+        .split(['|', '\n'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(parse_op)
+        .collect::<Result<_, _>>()?;
+    if ops.is_empty() {
+        return Err("empty operation chain".into());
+    }
+    Ok(ops)
+}
+
+fn parse_op(spec: &str) -> Result<Op, String> {
+    let (name, rest) = spec.split_once(':').unwrap_or((spec, ""));
+    let name = name.trim();
+    let syntax = OPS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, s)| *s)
+        .ok_or_else(|| format!("unknown operation: {}", name))?;
+    let bad = |why: &str| format!("{}: {} (expected {})", name, why, syntax);
+
+    // Text keeps everything after its fourth comma as the label
+    let args: Vec<&str> = if name == "text" {
+        rest.splitn(5, ',').collect()
+    } else {
+        rest.split(',').map(str::trim).collect()
+    };
+    let args: Vec<&str> = if args == [""] { Vec::new() } else { args };
+
+    let num = |i: usize| -> Result<f32, String> {
+        let raw = args.get(i).ok_or_else(|| bad("missing argument"))?;
+        raw.trim()
+            .parse::<f32>()
+            .map_err(|_| bad(&format!("invalid number '{}'", raw.trim())))
+    };
+    let uint = |i: usize| num(i).map(|v| v.max(0.0).round() as u32);
+    let int = |i: usize| num(i).map(|v| v.round() as i32);
+    let color_or = |i: usize, default: Rgba<u8>| match args.get(i) {
+        Some(c) if !c.trim().is_empty() => parse_color(c.trim()).map_err(|e| bad(&e)),
+        _ => Ok(default),
+    };
+    let num_or = |i: usize, default: f32| match args.get(i) {
+        Some(v) if !v.trim().is_empty() => num(i),
+        _ => Ok(default),
+    };
+    let needs = |n: usize| {
+        if args.len() < n {
+            Err(bad("missing argument"))
+        } else {
+            Ok(())
+        }
+    };
+
+    let red = Rgba([230, 30, 30, 255]);
+    let black = Rgba([0, 0, 0, 255]);
+    match name {
+        "crop" => {
+            needs(4)?;
+            Ok(Op::Crop { x: uint(0)?, y: uint(1)?, width: uint(2)?, height: uint(3)? })
+        }
+        "resize" => match args.first().and_then(|a| a.strip_suffix('%')) {
+            Some(percent) => {
+                let percent: f32 = percent.trim().parse().map_err(|_| bad("invalid percentage"))?;
+                if percent <= 0.0 {
+                    return Err(bad("percentage must be positive"));
+                }
+                Ok(Op::Scale { percent })
+            }
+            None => {
+                needs(1)?;
+                let width = uint(0)?;
+                let height = if args.len() > 1 { uint(1)? } else { 0 };
+                if width == 0 && height == 0 {
+                    return Err(bad("width or height must be non-zero"));
+                }
+                Ok(Op::Resize { width, height })
+            }
+        },
+        "rect" => {
+            needs(4)?;
+            Ok(Op::Rect {
+                x: int(0)?,
+                y: int(1)?,
+                width: uint(2)?,
+                height: uint(3)?,
+                color: color_or(4, red)?,
+                thickness: num_or(5, 3.0)?.max(1.0),
+            })
+        }
+        "fill" => {
+            needs(4)?;
+            Ok(Op::Fill { x: int(0)?, y: int(1)?, width: uint(2)?, height: uint(3)?, color: color_or(4, black)? })
+        }
+        "arrow" => {
+            needs(4)?;
+            Ok(Op::Arrow {
+                from: (num(0)?, num(1)?),
+                to: (num(2)?, num(3)?),
+                color: color_or(4, red)?,
+                thickness: num_or(5, 4.0)?.max(1.0),
+            })
+        }
+        "text" => {
+            needs(5)?;
+            let text = args[4].trim_start().to_string();
+            if text.is_empty() {
+                return Err(bad("empty label"));
+            }
+            Ok(Op::Text { x: num(0)?, y: num(1)?, size: num(2)?.max(4.0), color: color_or(3, red)?, text })
+        }
+        "blur" => {
+            needs(4)?;
+            Ok(Op::Blur { x: uint(0)?, y: uint(1)?, width: uint(2)?, height: uint(3)?, sigma: num_or(4, 8.0)?.max(0.5) })
+        }
+        "pixelate" => {
+            needs(4)?;
+            Ok(Op::Pixelate {
+                x: uint(0)?,
+                y: uint(1)?,
+                width: uint(2)?,
+                height: uint(3)?,
+                block: (num_or(4, 12.0)?.round() as u32).max(2),
+            })
+        }
+        _ => Err(format!("unknown operation: {}", name)),
+    }
+}
+
+/// Parse a color name or `#rgb`, `#rrggbb`, `#rrggbbaa`.
+pub fn parse_color(value: &str) -> Result<Rgba<u8>, String> {
+    let named = match value.to_ascii_lowercase().as_str() {
+        "red" => Some([230, 30, 30]),
+        "green" => Some([30, 170, 60]),
+        "blue" => Some([40, 100, 230]),
+        "yellow" => Some([250, 210, 0]),
+        "orange" => Some([250, 140, 0]),
+        "magenta" | "pink" => Some([230, 40, 180]),
+        "cyan" => Some([0, 190, 220]),
+        "white" => Some([255, 255, 255]),
+        "black" => Some([0, 0, 0]),
+        "gray" | "grey" => Some([128, 128, 128]),
+        _ => None,
+    };
+    if let Some([r, g, b]) = named {
+        return Ok(Rgba([r, g, b, 255]));
+    }
+
+    let hex = value
+        .strip_prefix('#')
+        .ok_or_else(|| format!("invalid color '{}'", value))?;
+    let digits: Vec<u8> = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("invalid color '{}'", value))?;
+    match digits.as_slice() {
+        [r, g, b] => Ok(Rgba([r * 17, g * 17, b * 17, 255])),
+        [r1, r2, g1, g2, b1, b2] => Ok(Rgba([r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2, 255])),
+        [r1, r2, g1, g2, b1, b2, a1, a2] => {
+            Ok(Rgba([r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2, a1 * 16 + a2]))
+        }
+        _ => Err(format!("invalid color '{}'", value)),
+    }
+}
+
+/// Apply `ops` in order. `font` is only needed (and only loaded by callers)
+/// when the chain contains text.
+pub fn apply(mut image: RgbaImage, ops: &[Op], font: Option<&FontVec>) -> Result<RgbaImage, String> {
+    for op in ops {
+        image = match op {
+            Op::Crop { x, y, width, height } => {
+                let region = clip(&image, *x, *y, *width, *height)
+                    .ok_or_else(|| format!("crop {},{} is outside the image", x, y))?;
+                image::imageops::crop_imm(&image, region.0, region.1, region.2, region.3).to_image()
+            }
+            Op::Resize { width, height } => {
+                let (w, h) = fit_size(image.dimensions(), *width, *height);
+                image::imageops::resize(&image, w, h, image::imageops::FilterType::Lanczos3)
+            }
+            Op::Scale { percent } => {
+                let w = ((image.width() as f32 * percent / 100.0).round() as u32).max(1);
+                let h = ((image.height() as f32 * percent / 100.0).round() as u32).max(1);
+                image::imageops::resize(&image, w, h, image::imageops::FilterType::Lanczos3)
+            }
+            Op::Rect { x, y, width, height, color, thickness } => {
+                let (x0, y0) = (*x as f32, *y as f32);
+                let (x1, y1) = (x0 + *width as f32, y0 + *height as f32);
+                for (a, b) in [((x0, y0), (x1, y0)), ((x1, y0), (x1, y1)), ((x1, y1), (x0, y1)), ((x0, y1), (x0, y0))] {
+                    draw_segment(&mut image, a, b, *thickness, *color);
+                }
+                image
+            }
+            Op::Fill { x, y, width, height, color } => {
+                let x0 = (*x).max(0) as u32;
+                let y0 = (*y).max(0) as u32;
+                let x1 = (*x as i64 + *width as i64).clamp(0, image.width() as i64) as u32;
+                let y1 = (*y as i64 + *height as i64).clamp(0, image.height() as i64) as u32;
+                for py in y0..y1 {
+                    for px in x0..x1 {
+                        blend(&mut image, px as i64, py as i64, *color, 1.0);
+                    }
+                }
+                image
+            }
+            Op::Arrow { from, to, color, thickness } => {
+                draw_arrow(&mut image, *from, *to, *thickness, *color);
+                image
+            }
+            Op::Text { x, y, size, color, text } => {
+                let font = font.ok_or("text needs a font")?;
+                draw_text(&mut image, font, *x, *y, *size, *color, text);
+                image
+            }
+            Op::Blur { x, y, width, height, sigma } => {
+                let (x, y, w, h) = clip(&image, *x, *y, *width, *height)
+                    .ok_or_else(|| format!("blur {},{} is outside the image", x, y))?;
+                let region = image::imageops::crop_imm(&image, x, y, w, h).to_image();
+                let blurred = image::imageops::blur(&region, *sigma);
+                image::imageops::replace(&mut image, &blurred, x as i64, y as i64);
+                image
+            }
+            Op::Pixelate { x, y, width, height, block } => {
+                let (x, y, w, h) = clip(&image, *x, *y, *width, *height)
+                    .ok_or_else(|| format!("pixelate {},{} is outside the image", x, y))?;
+                pixelate(&mut image, x, y, w, h, *block);
+                image
+            }
+        };
+    }
+    Ok(image)
+}
+
+/// Whether a chain draws text, and so needs a font.
+pub fn needs_font(ops: &[Op]) -> bool {
+    ops.iter().any(|op| matches!(op, Op::Text { .. }))
+}
+
+/// Load a sans-serif font via fontconfig, falling back to common paths.
+pub fn load_font() -> Result<FontVec, String> {
+    let mut candidates = Vec::new();
+    if let Ok(out) = std::process::Command::new("fc-match")
+        .args(["-f", "%{file}", "sans-serif:bold"])
+        .output()
+    {
+        let path = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        if !path.is_empty() {
+            candidates.push(path);
+        }
+    }
+    candidates.extend(
+        [
+            "/usr/share/fonts/noto/NotoSans-Bold.ttf",
+            "/usr/share/fonts/TTF/DejaVuSans-Bold.ttf",
+            "/usr/share/fonts/truetype/dejavu/DejaVuSans-Bold.ttf",
+            "/usr/share/fonts/TTF/DejaVuSans.ttf",
+            "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+        ]
+        .map(String::from),
+    );
+    candidates
+        .iter()
+        .find_map(|path| {
+            let data = std::fs::read(path).ok()?;
+            FontVec::try_from_vec(data).ok()
+        })
+        .ok_or_else(|| "no usable font found (install fontconfig or DejaVu fonts)".to_string())
+}
+
+/// Intersect a rectangle with the image; `None` if nothing is left.
+fn clip(image: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+    let w = width.min(image.width().saturating_sub(x));
+    let h = height.min(image.height().saturating_sub(y));
+    (w > 0 && h > 0).then_some((x, y, w, h))
+}
+
+/// Target size for a resize where a zero side follows the aspect ratio.
+fn fit_size((w, h): (u32, u32), width: u32, height: u32) -> (u32, u32) {
+    let ratio = w as f64 / h.max(1) as f64;
+    match (width, height) {
+        (0, h2) => (((h2 as f64 * ratio).round() as u32).max(1), h2),
+        (w2, 0) => (w2, ((w2 as f64 / ratio).round() as u32).max(1)),
+        sized => sized,
+    }
+}
+
+/// Alpha-blend `color` onto a pixel with extra `coverage` (0-1).
+fn blend(image: &mut RgbaImage, x: i64, y: i64, color: Rgba<u8>, coverage: f32) {
+    if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+        return;
+    }
+    let alpha = coverage.clamp(0.0, 1.0) * color[3] as f32 / 255.0;
+    if alpha <= 0.0 {
+        return;
+    }
+    let px = image.get_pixel_mut(x as u32, y as u32);
+    for c in 0..3 {
+        px[c] = (color[c] as f32 * alpha + px[c] as f32 * (1.0 - alpha)).round() as u8;
+    }
+    px[3] = (255.0 * alpha + px[3] as f32 * (1.0 - alpha)).round() as u8;
+}
+
+/// Anti-aliased thick line with round caps.
+fn draw_segment(image: &mut RgbaImage, a: (f32, f32), b: (f32, f32), thickness: f32, color: Rgba<u8>) {
+    let r = thickness / 2.0;
+    let (min_x, max_x) = (a.0.min(b.0) - r - 1.0, a.0.max(b.0) + r + 1.0);
+    let (min_y, max_y) = (a.1.min(b.1) - r - 1.0, a.1.max(b.1) + r + 1.0);
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = dx * dx + dy * dy;
+    for py in min_y.floor() as i64..=max_y.ceil() as i64 {
+        for px in min_x.floor() as i64..=max_x.ceil() as i64 {
+            let (cx, cy) = (px as f32 + 0.5, py as f32 + 0.5);
+            let t = if len_sq > 0.0 {
+                (((cx - a.0) * dx + (cy - a.1) * dy) / len_sq).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let (nx, ny) = (a.0 + t * dx, a.1 + t * dy);
+            let dist = ((cx - nx).powi(2) + (cy - ny).powi(2)).sqrt();
+            blend(image, px, py, color, r + 0.5 - dist);
+        }
+    }
+}
+
+fn draw_arrow(image: &mut RgbaImage, from: (f32, f32), to: (f32, f32), thickness: f32, color: Rgba<u8>) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let len = (dx * dx + dy * dy).sqrt();
+    if len < 1.0 {
+        return;
+    }
+    let (ux, uy) = (dx / len, dy / len);
+    let head = (thickness * 4.0 + 8.0).min(len);
+    let half_width = head * 0.5;
+
+    // Stop the shaft inside the head so its round cap does not poke out of the tip
+    let base = (to.0 - ux * head, to.1 - uy * head);
+    draw_segment(image, from, (base.0 + ux * head * 0.5, base.1 + uy * head * 0.5), thickness, color);
+
+    let left = (base.0 - uy * half_width, base.1 + ux * half_width);
+    let right = (base.0 + uy * half_width, base.1 - ux * half_width);
+    fill_triangle(image, to, left, right, color);
+}
+
+fn fill_triangle(image: &mut RgbaImage, a: (f32, f32), b: (f32, f32), c: (f32, f32), color: Rgba<u8>) {
+    let edge = |p: (f32, f32), q: (f32, f32), x: f32, y: f32| (q.0 - p.0) * (y - p.1) - (q.1 - p.1) * (x - p.0);
+    let area = edge(a, b, c.0, c.1);
+    if area.abs() < f32::EPSILON {
+        return;
+    }
+    let min_x = a.0.min(b.0).min(c.0).floor() as i64;
+    let max_x = a.0.max(b.0).max(c.0).ceil() as i64;
+    let min_y = a.1.min(b.1).min(c.1).floor() as i64;
+    let max_y = a.1.max(b.1).max(c.1).ceil() as i64;
+
+    // 4x4 supersampling for smooth edges
+    const SAMPLES: i32 = 4;
+    for py in min_y..=max_y {
+        for px in min_x..=max_x {
+            let mut inside = 0;
+            for sy in 0..SAMPLES {
+                for sx in 0..SAMPLES {
+                    let x = px as f32 + (sx as f32 + 0.5) / SAMPLES as f32;
+                    let y = py as f32 + (sy as f32 + 0.5) / SAMPLES as f32;
+                    let w0 = edge(b, c, x, y) * area.signum();
+                    let w1 = edge(c, a, x, y) * area.signum();
+                    let w2 = edge(a, b, x, y) * area.signum();
+                    if w0 >= 0.0 && w1 >= 0.0 && w2 >= 0.0 {
+                        inside += 1;
+                    }
+                }
+            }
+            if inside > 0 {
+                blend(image, px, py, color, inside as f32 / (SAMPLES * SAMPLES) as f32);
+            }
+        }
+    }
+}
+
+fn draw_text(image: &mut RgbaImage, font: &FontVec, x: f32, y: f32, size: f32, color: Rgba<u8>, text: &str) {
+    let scale = PxScale::from(size);
+    let scaled = font.as_scaled(scale);
+    let mut baseline = y + scaled.ascent();
+    let mut caret = x;
+    let mut previous = None;
+    for c in text.chars() {
+        if c == '\n' {
+            baseline += scaled.height() + scaled.line_gap();
+            caret = x;
+            previous = None;
+            continue;
+        }
+        let id = scaled.glyph_id(c);
+        if let Some(prev) = previous {
+            caret += scaled.kern(prev, id);
+        }
+        let glyph = id.with_scale_and_position(scale, ab_glyph::point(caret, baseline));
+        if let Some(outlined) = font.outline_glyph(glyph) {
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                blend(
+                    image,
+                    bounds.min.x as i64 + gx as i64,
+                    bounds.min.y as i64 + gy as i64,
+                    color,
+                    coverage,
+                );
+            });
+        }
+        caret += scaled.h_advance(id);
+        previous = Some(id);
+    }
+}
+
+/// Replace each `block`×`block` cell of the region with its average color.
+fn pixelate(image: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, block: u32) {
+    for cy in (y..y + height).step_by(block as usize) {
+        for cx in (x..x + width).step_by(block as usize) {
+            let cw = block.min(x + width - cx);
+            let ch = block.min(y + height - cy);
+            let mut sum = [0u64; 4];
+            for py in cy..cy + ch {
+                for px in cx..cx + cw {
+                    for (s, v) in sum.iter_mut().zip(image.get_pixel(px, py).0) {
+                        *s += v as u64;
+                    }
+                }
+            }
+            let n = (cw * ch) as u64;
+            let avg = Rgba(sum.map(|s| (s / n) as u8));
+            for py in cy..cy + ch {
+                for px in cx..cx + cw {
+                    image.put_pixel(px, py, avg);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ops_reads_a_chain() {
+        let ops = parse_ops("crop:0,0,800,600 | rect:40,40,200,80,blue,2\nresize:50%").unwrap();
+        assert_eq!(ops.len(), 3);
+        assert!(matches!(ops[0], Op::Crop { x: 0, y: 0, width: 800, height: 600 }));
+        assert!(matches!(
+            ops[1],
+            Op::Rect { x: 40, y: 40, width: 200, height: 80, color: Rgba([40, 100, 230, 255]), thickness }
+                if thickness == 2.0
+        ));
+        assert!(matches!(ops[2], Op::Scale { percent } if percent == 50.0));
+    }
+
+    #[test]
+    fn parse_ops_defaults_and_text_labels() {
+        let ops = parse_ops("fill:1,2,3,4|resize:640|text:10,20,16,#fff,Hello, world").unwrap();
+        assert!(matches!(ops[0], Op::Fill { color: Rgba([0, 0, 0, 255]), .. }));
+        assert!(matches!(ops[1], Op::Resize { width: 640, height: 0 }));
+        match &ops[2] {
+            Op::Text { size, color, text, .. } => {
+                assert_eq!(*size, 16.0);
+                assert_eq!(*color, Rgba([255, 255, 255, 255]));
+                assert_eq!(text, "Hello, world");
+            }
+            other => panic!("unexpected op {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_ops_rejects_bad_input() {
+        assert_eq!(parse_ops(" | ").unwrap_err(), "empty operation chain");
+        assert_eq!(parse_ops("spin:90").unwrap_err(), "unknown operation: spin");
+        assert!(parse_ops("crop:0,0,10").unwrap_err().contains("missing argument"));
+        assert!(parse_ops("crop:0,0,10,x").unwrap_err().contains("invalid number 'x'"));
+        assert!(parse_ops("resize:0,0").is_err());
+        assert!(parse_ops("resize:-5%").is_err());
+        assert!(parse_ops("rect:0,0,1,1,#12").unwrap_err().contains("invalid color"));
+        assert!(parse_ops("text:0,0,12,red, ").unwrap_err().contains("empty label"));
+    }
+
+    #[test]
+    fn parse_color_forms() {
+        assert_eq!(parse_color("Grey"), Ok(Rgba([128, 128, 128, 255])));
+        assert_eq!(parse_color("#f00"), Ok(Rgba([255, 0, 0, 255])));
+        assert_eq!(parse_color("#102030"), Ok(Rgba([16, 32, 48, 255])));
+        assert_eq!(parse_color("#10203080"), Ok(Rgba([16, 32, 48, 128])));
+        assert!(parse_color("102030").is_err());
+        assert!(parse_color("#xyz").is_err());
+    }
+
+    /// An 8×8 image where every pixel has a distinct color.
+    fn gradient() -> RgbaImage {
+        RgbaImage::from_fn(8, 8, |x, y| Rgba([(x * 30) as u8, (y * 30) as u8, 100, 255]))
+    }
+
+    #[test]
+    fn apply_crop_gives_requested_size() {
+        let out = apply(gradient(), &parse_ops("crop:2,3,4,2").unwrap(), None).unwrap();
+        assert_eq!(out.dimensions(), (4, 2));
+        assert_eq!(*out.get_pixel(0, 0), *gradient().get_pixel(2, 3));
+
+        let clipped = apply(gradient(), &parse_ops("crop:6,6,10,10").unwrap(), None).unwrap();
+        assert_eq!(clipped.dimensions(), (2, 2));
+    }
+
+    #[test]
+    fn apply_fill_and_pixelate_hide_the_region() {
+        let original = gradient();
+        let filled = apply(original.clone(), &parse_ops("fill:0,0,4,4,red").unwrap(), None).unwrap();
+        for (x, y, p) in filled.enumerate_pixels() {
+            if x < 4 && y < 4 {
+                assert_eq!(*p, Rgba([230, 30, 30, 255]));
+            } else {
+                assert_eq!(p, original.get_pixel(x, y));
+            }
+        }
+
+        let pixelated = apply(original.clone(), &parse_ops("pixelate:0,0,4,4,4").unwrap(), None).unwrap();
+        let cell = *pixelated.get_pixel(0, 0);
+        for y in 0..4 {
+            for x in 0..4 {
+                assert_eq!(*pixelated.get_pixel(x, y), cell);
+            }
+        }
+        assert!(original.enumerate_pixels().all(|(_, _, p)| *p != cell));
+        assert_eq!(pixelated.get_pixel(5, 5), original.get_pixel(5, 5));
+    }
+
+    #[test]
+    fn apply_out_of_bounds_regions() {
+        let err = apply(gradient(), &parse_ops("crop:8,0,2,2").unwrap(), None).unwrap_err();
+        assert!(err.contains("outside the image"), "{}", err);
+        assert!(apply(gradient(), &parse_ops("pixelate:20,20,4,4").unwrap(), None).is_err());
+        assert!(apply(gradient(), &parse_ops("blur:0,9,4,4").unwrap(), None).is_err());
+
+        let ops = parse_ops("fill:-4,-4,100,100,black|rect:50,50,10,10,red,3|arrow:-20,-20,100,100,red,4").unwrap();
+        let out = apply(gradient(), &ops, None).unwrap();
+        assert_eq!(out.dimensions(), (8, 8));
+    }
+}
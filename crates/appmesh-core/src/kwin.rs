@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::oneshot;
+
+/// Object path the return channel is served at on the port's own connection.
+const RETURN_PATH: &str = "/org/appmesh/KWinScript";
+/// Interface KWin scripts `callDBus` back into.
+const RETURN_INTERFACE: &str = "org.appmesh.KWinScript";
+
+type Pending = Arc<Mutex<HashMap<String, oneshot::Sender<Result<String, String>>>>>;
+type Listeners = Arc<Mutex<HashMap<String, Box<dyn Fn(serde_json::Value) + Send>>>>;
+
+/// D-Bus object that receives script results, keyed by per-invocation token.
+struct ReturnChannel {
+    pending: Pending,
+    listeners: Listeners,
+}
+
+#[zbus::interface(name = "org.appmesh.KWinScript")]
+impl ReturnChannel {
+    /// Called by a script with its JSON-encoded return value.
+    fn reply(&self, token: String, json: String) {
+        self.complete(&token, Ok(json));
+    }
+
+    /// Called by a script when its body threw.
+    fn fail(&self, token: String, message: String) {
+        self.complete(&token, Err(message));
+    }
+
+    /// Called by a watch script each time it has something to report.
+    fn emit(&self, token: String, json: String) {
+        let Ok(value) = serde_json::from_str(&json) else {
+            return;
+        };
+        if let Ok(listeners) = self.listeners.lock() {
+            if let Some(listener) = listeners.get(&token) {
+                listener(value);
+            }
+        }
+    }
+}
+
+impl ReturnChannel {
+    fn complete(&self, token: &str, result: Result<String, String>) {
+        let sender = self.pending.lock().ok().and_then(|mut p| p.remove(token));
+        if let Some(tx) = sender {
+            let _ = tx.send(result);
+        }
+    }
+}
+
+/// A loaded KWin script. Scripts left running by [`KWinScripts::watch`] are
+/// stopped by passing this to `unwatch`.
+pub struct Watch {
+    token: String,
+    script_id: i32,
+    file: String,
+}
+
+/// Runs KWin scripts and collects their results over D-Bus.
+///
+/// KWin scripts cannot return values through `loadScript`/`run`, so each script
+/// is wrapped in a prelude that `callDBus`es its return value (as JSON) back to a
+/// `org.appmesh.KWinScript` object served on our own connection. A random token
+/// correlates the callback with the invocation that is waiting for it.
+pub struct KWinScripts {
+    connection: zbus::Connection,
+    pending: Pending,
+    listeners: Listeners,
+}
+
+impl KWinScripts {
+    /// Register the return channel object on `connection`.
+    pub async fn new(connection: &zbus::Connection) -> Result<Self, Box<dyn std::error::Error>> {
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let listeners: Listeners = Arc::new(Mutex::new(HashMap::new()));
+        connection
+            .object_server()
+            .at(
+                RETURN_PATH,
+                ReturnChannel {
+                    pending: pending.clone(),
+                    listeners: listeners.clone(),
+                },
+            )
+            .await?;
+        Ok(Self {
+            connection: connection.clone(),
+            pending,
+            listeners,
+        })
+    }
+
+    /// Run a script body and return the value it `return`s, decoded from JSON.
+    ///
+    /// The body runs inside a function, so `return <value>;` hands a result back;
+    /// falling off the end yields `null`. Exceptions surface as errors.
+    pub async fn run(
+        &self,
+        body: &str,
+        timeout: Duration,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let (script, value) = self.start(body, Self::new_token(), timeout).await?;
+        self.stop(&script).await;
+        Ok(value)
+    }
+
+    /// Start a long-running script that reports through `emit(value)`.
+    ///
+    /// The body typically connects handlers to workspace signals; it is
+    /// considered started once it returns. Each `emit` call is delivered to
+    /// `on_event` until the script is stopped with [`unwatch`](Self::unwatch).
+    pub async fn watch(
+        &self,
+        body: &str,
+        timeout: Duration,
+        on_event: impl Fn(serde_json::Value) + Send + 'static,
+    ) -> Result<Watch, Box<dyn std::error::Error>> {
+        let token = Self::new_token();
+        self.listeners
+            .lock()
+            .map_err(|e| format!("lock poisoned: {}", e))?
+            .insert(token.clone(), Box::new(on_event));
+
+        match self.start(body, token.clone(), timeout).await {
+            Ok((script, _)) => Ok(script),
+            Err(e) => {
+                self.remove_listener(&token);
+                Err(e)
+            }
+        }
+    }
+
+    /// Stop a script started with [`watch`](Self::watch).
+    pub async fn unwatch(&self, watch: Watch) {
+        self.stop(&watch).await;
+        self.remove_listener(&watch.token);
+    }
+
+    fn remove_listener(&self, token: &str) {
+        if let Ok(mut listeners) = self.listeners.lock() {
+            listeners.remove(token);
+        }
+    }
+
+    /// Load and run a wrapped script, then wait for its Reply/Fail callback.
+    /// On success the script is still loaded; on failure it has been stopped.
+    async fn start(
+        &self,
+        body: &str,
+        token: String,
+        timeout: Duration,
+    ) -> Result<(Watch, serde_json::Value), Box<dyn std::error::Error>> {
+        let unique_name = self
+            .connection
+            .unique_name()
+            .ok_or("D-Bus connection has no unique name")?
+            .to_string();
+        let source = Self::wrap(body, &token, &unique_name);
+
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .map_err(|e| format!("lock poisoned: {}", e))?
+            .insert(token.clone(), tx);
+
+        // Unique file and plugin name per run — KWin caches scripts by both
+        let file = Self::script_path(&token);
+        let script_id = match self.load(&source, &file, &token).await {
+            Ok(id) => id,
+            Err(e) => {
+                self.forget(&token);
+                let _ = std::fs::remove_file(&file);
+                return Err(e);
+            }
+        };
+        let script = Watch { token, script_id, file };
+
+        let outcome = self.run_and_wait(&script, timeout, rx).await;
+        self.forget(&script.token);
+        match outcome {
+            Ok(value) => Ok((script, value)),
+            Err(e) => {
+                self.stop(&script).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn run_and_wait(
+        &self,
+        script: &Watch,
+        timeout: Duration,
+        rx: oneshot::Receiver<Result<String, String>>,
+    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let proxy = self.script_proxy(script.script_id).await?;
+        let _: () = proxy.call("run", &()).await?;
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(Ok(json))) => Ok(serde_json::from_str(&json)?),
+            Ok(Ok(Err(message))) => Err(format!("KWin script error: {}", message).into()),
+            Ok(Err(_)) => Err("KWin script return channel closed".into()),
+            Err(_) => Err(format!("KWin script timed out after {}ms", timeout.as_millis()).into()),
+        }
+    }
+
+    /// Script file for a run: in `$XDG_RUNTIME_DIR` (private to the user)
+    /// when set, else the temp directory.
+    fn script_path(token: &str) -> String {
+        let dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .filter(|v| !v.is_empty())
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        dir.join(format!("appmesh_kwin_{}.js", token)).to_string_lossy().into_owned()
+    }
+
+    async fn load(&self, script: &str, file: &str, token: &str) -> Result<i32, Box<dyn std::error::Error>> {
+        // Never follow or truncate something already at the path
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(file)?
+            .write_all(script.as_bytes())?;
+
+        let scripting = zbus::Proxy::new(
+            &self.connection,
+            "org.kde.KWin",
+            "/Scripting",
+            "org.kde.kwin.Scripting",
+        )
+        .await?;
+        let script_id: i32 = scripting.call("loadScript", &(file, token)).await?;
+        if script_id < 0 {
+            return Err(format!("loadScript failed (id {})", script_id).into());
+        }
+        Ok(script_id)
+    }
+
+    async fn script_proxy(&self, script_id: i32) -> Result<zbus::Proxy<'static>, Box<dyn std::error::Error>> {
+        Ok(zbus::Proxy::new(
+            &self.connection,
+            "org.kde.KWin",
+            format!("/Scripting/Script{}", script_id),
+            "org.kde.kwin.Script",
+        )
+        .await?)
+    }
+
+    /// Stop (and thereby unload) a script and remove its file.
+    async fn stop(&self, script: &Watch) {
+        if let Ok(proxy) = self.script_proxy(script.script_id).await {
+            let _: Result<(), _> = proxy.call("stop", &()).await;
+        }
+        let _ = std::fs::remove_file(&script.file);
+    }
+
+    /// Drop the pending entry if the script never called back.
+    fn forget(&self, token: &str) {
+        if let Ok(mut p) = self.pending.lock() {
+            p.remove(token);
+        }
+    }
+
+    /// Wrap a script body with the return-channel prelude.
+    fn wrap(body: &str, token: &str, unique_name: &str) -> String {
+        let service = serde_json::to_string(unique_name).unwrap_or_default();
+        let token = serde_json::to_string(token).unwrap_or_default();
+        format!(
+            r#"(function() {{
+    const __service = {service};
+    const __token = {token};
+    const emit = function(value) {{
+        callDBus(__service, "{path}", "{iface}", "Emit", __token, JSON.stringify(value));
+    }};
+    try {{
+        const __result = (function() {{
+{body}
+        }})();
+        callDBus(__service, "{path}", "{iface}", "Reply", __token,
+            JSON.stringify(__result === undefined ? null : __result));
+    }} catch (e) {{
+        callDBus(__service, "{path}", "{iface}", "Fail", __token, String(e));
+    }}
+}})();
+"#,
+            service = service,
+            token = token,
+            body = body,
+            path = RETURN_PATH,
+            iface = RETURN_INTERFACE,
+        )
+    }
+
+    fn new_token() -> String {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        format!(
+            "appmesh_{}_{}_{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            nanos
+        )
+    }
+}
@@ -0,0 +1,224 @@
+//! Saved window layouts for `windows layout_save` / `layout_restore`.
+//!
+//! A layout is a pretty-printed JSON file under `~/.config/appmesh/layouts/`
+//! holding one entry per window: how to recognise it (class + title), where it
+//! goes (geometry, output, desktops, activities) and its state flags. Field
+//! names follow `windows list` so entries can be copied between the two.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Layout {
+    pub name: String,
+    /// Unix timestamp of the save.
+    pub saved_at: u64,
+    pub windows: Vec<LayoutWindow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayoutWindow {
+    pub resource_class: String,
+    /// Caption at save time; used to tell apart windows of the same class.
+    #[serde(default)]
+    pub caption: String,
+    /// Desktop file used to relaunch the app when no window matches.
+    #[serde(default)]
+    pub desktop_file_name: String,
+    pub geometry: Geometry,
+    #[serde(default)]
+    pub output: String,
+    #[serde(default)]
+    pub desktops: Vec<String>,
+    #[serde(default)]
+    pub activities: Vec<String>,
+    #[serde(default)]
+    pub minimized: bool,
+    #[serde(default)]
+    pub maximized: bool,
+    #[serde(default)]
+    pub full_screen: bool,
+    #[serde(default)]
+    pub keep_above: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Geometry {
+    pub x: i64,
+    pub y: i64,
+    pub width: i64,
+    pub height: i64,
+}
+
+impl Layout {
+    /// Build a layout from `windows list` rows, keeping normal windows only.
+    pub fn from_windows(name: &str, windows: &[serde_json::Value]) -> Self {
+        let saved_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let windows = windows
+            .iter()
+            .filter(|w| w.get("normalWindow").and_then(|v| v.as_bool()).unwrap_or(true))
+            .filter_map(|w| serde_json::from_value(w.clone()).ok())
+            .collect();
+        Self {
+            name: name.to_string(),
+            saved_at,
+            windows,
+        }
+    }
+
+    /// Pair layout entries with open windows.
+    ///
+    /// Entries match windows of the same resource class; among those, an exact
+    /// caption match wins, then the first unclaimed window. Returns
+    /// `(entry index, window internalId)` pairs and the indices of entries
+    /// nothing matched.
+    pub fn assign(&self, windows: &[serde_json::Value]) -> (Vec<(usize, String)>, Vec<usize>) {
+        let field = |w: &serde_json::Value, name: &str| {
+            w.get(name).and_then(|v| v.as_str()).unwrap_or("").to_string()
+        };
+        let mut claimed = vec![false; windows.len()];
+        let mut matched = Vec::new();
+        let mut unmatched = Vec::new();
+
+        // Exact caption matches first so they are not stolen by looser ones
+        let mut pending = Vec::new();
+        for (i, entry) in self.windows.iter().enumerate() {
+            let exact = windows.iter().enumerate().position(|(j, w)| {
+                !claimed[j]
+                    && field(w, "resourceClass") == entry.resource_class
+                    && field(w, "caption") == entry.caption
+            });
+            match exact {
+                Some(j) => {
+                    claimed[j] = true;
+                    matched.push((i, field(&windows[j], "internalId")));
+                }
+                None => pending.push(i),
+            }
+        }
+        for i in pending {
+            let entry = &self.windows[i];
+            let any = windows
+                .iter()
+                .enumerate()
+                .position(|(j, w)| !claimed[j] && field(w, "resourceClass") == entry.resource_class);
+            match any {
+                Some(j) => {
+                    claimed[j] = true;
+                    matched.push((i, field(&windows[j], "internalId")));
+                }
+                None => unmatched.push(i),
+            }
+        }
+
+        matched.sort_by_key(|(i, _)| *i);
+        (matched, unmatched)
+    }
+}
+
+/// Subdirectory of the appmesh config dir that layouts are saved in.
+const KIND: &str = "layouts";
+
+pub fn save(layout: &Layout) -> Result<PathBuf, String> {
+    config::save_named(KIND, &layout.name, layout)
+}
+
+pub fn load(name: &str) -> Result<Layout, String> {
+    config::load_named(KIND, name)
+}
+
+/// Names of all saved layouts, sorted.
+pub fn list() -> Vec<String> {
+    config::list_named(KIND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn layout(entries: &[(&str, &str)]) -> Layout {
+        let windows = entries
+            .iter()
+            .map(|(class, caption)| {
+                serde_json::from_value(json!({
+                    "resourceClass": class,
+                    "caption": caption,
+                    "geometry": { "x": 0, "y": 0, "width": 800, "height": 600 }
+                }))
+                .unwrap()
+            })
+            .collect();
+        Layout { name: "test".into(), saved_at: 0, windows }
+    }
+
+    fn window(id: &str, class: &str, caption: &str) -> serde_json::Value {
+        json!({ "internalId": id, "resourceClass": class, "caption": caption })
+    }
+
+    #[test]
+    fn exact_caption_beats_an_earlier_window_of_the_class() {
+        let layout = layout(&[("konsole", "build"), ("konsole", "logs")]);
+        let windows = [
+            window("{a}", "konsole", "logs"),
+            window("{b}", "konsole", "build"),
+        ];
+        let (matched, unmatched) = layout.assign(&windows);
+        assert_eq!(matched, [(0, "{b}".to_string()), (1, "{a}".to_string())]);
+        assert!(unmatched.is_empty());
+    }
+
+    #[test]
+    fn caption_matches_are_not_stolen_by_class_fallbacks() {
+        // Entry 0 has no exact match; it must not take entry 1's window
+        let layout = layout(&[("konsole", "old title"), ("konsole", "logs")]);
+        let windows = [
+            window("{logs}", "konsole", "logs"),
+            window("{other}", "konsole", "shell"),
+        ];
+        let (matched, _) = layout.assign(&windows);
+        assert_eq!(matched, [(0, "{other}".to_string()), (1, "{logs}".to_string())]);
+    }
+
+    #[test]
+    fn each_window_is_claimed_once_and_leftovers_are_unmatched() {
+        let layout = layout(&[
+            ("firefox", "a"),
+            ("firefox", "b"),
+            ("firefox", "c"),
+            ("dolphin", "home"),
+        ]);
+        let windows = [
+            window("{f1}", "firefox", "x"),
+            window("{f2}", "firefox", "y"),
+            window("{k}", "kate", "home"),
+        ];
+        let (matched, unmatched) = layout.assign(&windows);
+        assert_eq!(matched, [(0, "{f1}".to_string()), (1, "{f2}".to_string())]);
+        // Same caption but another class is no match
+        assert_eq!(unmatched, [2, 3]);
+    }
+
+    #[test]
+    fn from_windows_keeps_normal_windows_only() {
+        let rows = [
+            json!({ "resourceClass": "kate", "caption": "notes", "normalWindow": true,
+                    "geometry": { "x": 1, "y": 2, "width": 3, "height": 4 } }),
+            json!({ "resourceClass": "plasmashell", "caption": "panel", "normalWindow": false,
+                    "geometry": { "x": 0, "y": 0, "width": 10, "height": 10 } }),
+            json!({ "resourceClass": "broken" }),
+        ];
+        let layout = Layout::from_windows("desk", &rows);
+        assert_eq!(layout.windows.len(), 1);
+        assert_eq!(layout.windows[0].resource_class, "kate");
+        assert_eq!(layout.windows[0].geometry.width, 3);
+    }
+}
@@ -2,5 +2,14 @@ pub mod keymap;
 pub mod eis;
 pub mod input;
 pub mod ffi;
+pub mod capture;
+pub mod compose;
+pub mod config;
+pub mod imaging;
+pub mod kwin;
+pub mod layout;
+pub mod mailcache;
 pub mod port;
 pub mod ports;
+pub mod transform;
+pub mod vision;
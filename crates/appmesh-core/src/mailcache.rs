@@ -0,0 +1,845 @@
+//! Local SQLite store of one mail account for offline use.
+//!
+//! Holds mailboxes, email metadata and the bodies fetched so far, plus the
+//! JMAP state strings they are current as of, so the mail port can bring it
+//! up to date with Mailbox/changes and Email/changes and answer queries from
+//! it. One database per account under `$XDG_CACHE_HOME/appmesh/mail/`.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS state (
+        kind TEXT PRIMARY KEY,
+        value TEXT NOT NULL,
+        synced_at INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS mailbox (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        role TEXT NOT NULL,
+        total INTEGER NOT NULL,
+        unread INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS email (
+        id TEXT PRIMARY KEY,
+        thread_id TEXT NOT NULL,
+        subject TEXT NOT NULL,
+        sender TEXT NOT NULL,
+        recipients TEXT NOT NULL,
+        received_at INTEGER NOT NULL,
+        sent_at INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        preview TEXT NOT NULL,
+        has_attachment INTEGER NOT NULL,
+        body TEXT
+    );
+    CREATE TABLE IF NOT EXISTS email_mailbox (
+        email_id TEXT NOT NULL REFERENCES email(id) ON DELETE CASCADE,
+        mailbox_id TEXT NOT NULL,
+        PRIMARY KEY (email_id, mailbox_id)
+    );
+    CREATE TABLE IF NOT EXISTS email_keyword (
+        email_id TEXT NOT NULL REFERENCES email(id) ON DELETE CASCADE,
+        keyword TEXT NOT NULL,
+        PRIMARY KEY (email_id, keyword)
+    );
+    CREATE INDEX IF NOT EXISTS email_received ON email(received_at);
+    CREATE INDEX IF NOT EXISTS email_mailbox_mailbox ON email_mailbox(mailbox_id);
+";
+
+/// `$XDG_CACHE_HOME/appmesh/mail`, falling back to `~/.cache/appmesh/mail`.
+pub fn cache_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            PathBuf::from(std::env::var_os("HOME").unwrap_or_default()).join(".cache")
+        });
+    base.join("appmesh").join("mail")
+}
+
+/// Database file for an account key such as `user@host`.
+pub fn cache_path(account: &str) -> PathBuf {
+    let name: String = account
+        .chars()
+        .map(|c| if c.is_alphanumeric() || "@.-_".contains(c) { c } else { '_' })
+        .collect();
+    cache_dir().join(format!("{}.sqlite", name.trim_start_matches('.')))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedMailbox {
+    pub id: String,
+    pub name: String,
+    /// Lowercase role (`inbox`, `sent`, ...), empty if none.
+    pub role: String,
+    pub total: i64,
+    pub unread: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedEmail {
+    pub id: String,
+    pub thread_id: String,
+    pub subject: String,
+    /// From and To as display lists (`Name <addr>, addr`).
+    pub from: String,
+    pub to: String,
+    pub received_at: i64,
+    pub sent_at: i64,
+    pub size: i64,
+    pub preview: String,
+    pub has_attachment: bool,
+    pub mailbox_ids: Vec<String>,
+    pub keywords: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Received,
+    Sent,
+    Size,
+    From,
+    To,
+    Subject,
+}
+
+impl SortKey {
+    fn column(self) -> &'static str {
+        match self {
+            SortKey::Received => "received_at",
+            SortKey::Sent => "sent_at",
+            SortKey::Size => "size",
+            SortKey::From => "sender",
+            SortKey::To => "recipients",
+            SortKey::Subject => "subject",
+        }
+    }
+}
+
+/// An Email/query evaluated locally. Text matches are case-insensitive
+/// substrings; sizes and dates follow JMAP (`min_size` ≤ size < `max_size`,
+/// `after` ≤ received < `before`).
+#[derive(Debug, Clone, Default)]
+pub struct LocalQuery {
+    pub in_mailbox: Option<String>,
+    pub not_in: Vec<String>,
+    /// Subject, addresses, preview and any cached body.
+    pub text: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub subject: Option<String>,
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+    pub has_attachment: Option<bool>,
+    pub keyword: Option<String>,
+    pub not_keyword: Option<String>,
+    pub min_size: Option<i64>,
+    pub max_size: Option<i64>,
+    /// Keys with ascending flag; empty means newest first.
+    pub sort: Vec<(SortKey, bool)>,
+    pub position: usize,
+    pub limit: usize,
+}
+
+/// Counts for a sync report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheStats {
+    pub mailboxes: i64,
+    pub emails: i64,
+    pub bodies: i64,
+}
+
+pub struct MailCache {
+    conn: Connection,
+    path: PathBuf,
+}
+
+/// Unix time in seconds, as stored in `synced_at`.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn sql_err(e: rusqlite::Error) -> String {
+    format!("mail cache: {}", e)
+}
+
+/// `%text%` for LIKE, with the wildcards in `text` escaped.
+fn like(text: &str) -> Value {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    Value::Text(format!("%{}%", escaped))
+}
+
+/// Insert or update one email with its mailboxes and keywords.
+fn store_email(conn: &Connection, e: &CachedEmail) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO email (id, thread_id, subject, sender, recipients, received_at,
+                            sent_at, size, preview, has_attachment)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT(id) DO UPDATE SET thread_id = ?2, subject = ?3, sender = ?4,
+             recipients = ?5, received_at = ?6, sent_at = ?7, size = ?8, preview = ?9,
+             has_attachment = ?10",
+        params![
+            e.id,
+            e.thread_id,
+            e.subject,
+            e.from,
+            e.to,
+            e.received_at,
+            e.sent_at,
+            e.size,
+            e.preview,
+            e.has_attachment
+        ],
+    )
+    .map_err(sql_err)?;
+    conn.execute("DELETE FROM email_mailbox WHERE email_id = ?1", [&e.id])
+        .map_err(sql_err)?;
+    for mailbox_id in &e.mailbox_ids {
+        conn.execute(
+            "INSERT OR IGNORE INTO email_mailbox (email_id, mailbox_id) VALUES (?1, ?2)",
+            [&e.id, mailbox_id],
+        )
+        .map_err(sql_err)?;
+    }
+    conn.execute("DELETE FROM email_keyword WHERE email_id = ?1", [&e.id])
+        .map_err(sql_err)?;
+    for keyword in &e.keywords {
+        conn.execute(
+            "INSERT OR IGNORE INTO email_keyword (email_id, keyword) VALUES (?1, ?2)",
+            [&e.id, keyword],
+        )
+        .map_err(sql_err)?;
+    }
+    Ok(())
+}
+
+impl MailCache {
+    /// Open or create the database at `path`. It holds message contents, so
+    /// the directory is 0700 and the database 0600; SQLite gives the -wal
+    /// and -shm files the database's mode, and older ones are tightened here.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+
+        if let Some(dir) = path.parent() {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)
+                .map_err(|e| format!("mkdir failed: {}", e))?;
+            std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+                .map_err(|e| format!("chmod failed: {}", e))?;
+        }
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o600)
+            .open(path)
+            .map_err(|e| format!("cannot create {}: {}", path.display(), e))?;
+        for suffix in ["", "-wal", "-shm"] {
+            let file = PathBuf::from(format!("{}{}", path.display(), suffix));
+            match std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o600)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(format!("chmod failed: {}", e))
+                }
+                _ => {}
+            }
+        }
+        let conn = Connection::open(path).map_err(sql_err)?;
+        Self::with_connection(conn, path)
+    }
+
+    fn with_connection(conn: Connection, path: &Path) -> Result<Self, String> {
+        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")
+            .map_err(sql_err)?;
+        conn.execute_batch(SCHEMA).map_err(sql_err)?;
+        Ok(Self {
+            conn,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The state string for `kind` (`Email`, `Mailbox`) and when it was stored.
+    pub fn state(&self, kind: &str) -> Option<(String, i64)> {
+        self.conn
+            .query_row(
+                "SELECT value, synced_at FROM state WHERE kind = ?1",
+                [kind],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .ok()
+            .flatten()
+    }
+
+    pub fn set_state(&self, kind: &str, value: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT INTO state (kind, value, synced_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(kind) DO UPDATE SET value = ?2, synced_at = ?3",
+                params![kind, value, now()],
+            )
+            .map(|_| ())
+            .map_err(sql_err)
+    }
+
+    /// Forget everything, e.g. when the server can no longer give changes
+    /// since our state.
+    pub fn clear(&mut self) -> Result<(), String> {
+        self.conn
+            .execute_batch("DELETE FROM email; DELETE FROM mailbox; DELETE FROM state;")
+            .map_err(sql_err)
+    }
+
+    /// Make the next lookup sync first, after a change made through the port.
+    pub fn mark_stale(&self) -> Result<(), String> {
+        self.conn
+            .execute("UPDATE state SET synced_at = 0", [])
+            .map(|_| ())
+            .map_err(sql_err)
+    }
+
+    /// Replace all mailboxes, as after a full Mailbox/get.
+    pub fn replace_mailboxes(&mut self, mailboxes: &[CachedMailbox]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        tx.execute("DELETE FROM mailbox", []).map_err(sql_err)?;
+        for mb in mailboxes {
+            tx.execute(
+                "INSERT INTO mailbox (id, name, role, total, unread) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![mb.id, mb.name, mb.role, mb.total, mb.unread],
+            )
+            .map_err(sql_err)?;
+        }
+        tx.commit().map_err(sql_err)
+    }
+
+    /// Apply Mailbox/changes: store created and updated, drop destroyed.
+    pub fn update_mailboxes(
+        &mut self,
+        changed: &[CachedMailbox],
+        destroyed: &[String],
+    ) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        for mb in changed {
+            tx.execute(
+                "INSERT INTO mailbox (id, name, role, total, unread) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(id) DO UPDATE SET name = ?2, role = ?3, total = ?4, unread = ?5",
+                params![mb.id, mb.name, mb.role, mb.total, mb.unread],
+            )
+            .map_err(sql_err)?;
+        }
+        for id in destroyed {
+            tx.execute("DELETE FROM mailbox WHERE id = ?1", [id])
+                .map_err(sql_err)?;
+        }
+        tx.commit().map_err(sql_err)
+    }
+
+    pub fn mailboxes(&self) -> Result<Vec<CachedMailbox>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, name, role, total, unread FROM mailbox ORDER BY name")
+            .map_err(sql_err)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(CachedMailbox {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    role: row.get(2)?,
+                    total: row.get(3)?,
+                    unread: row.get(4)?,
+                })
+            })
+            .map_err(sql_err)?;
+        rows.collect::<Result<_, _>>().map_err(sql_err)
+    }
+
+    /// Apply Email/changes (or an initial load): store created and updated
+    /// metadata, keeping any cached body, and drop destroyed emails.
+    pub fn update_emails(&mut self, changed: &[CachedEmail], destroyed: &[String]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        for e in changed {
+            store_email(&tx, e)?;
+        }
+        for id in destroyed {
+            tx.execute("DELETE FROM email WHERE id = ?1", [id])
+                .map_err(sql_err)?;
+        }
+        tx.commit().map_err(sql_err)
+    }
+
+    /// Replace all emails and the Email state, as after a full reload. Done
+    /// in one transaction so a failed reload keeps the old rows.
+    pub fn replace_emails(&mut self, emails: &[CachedEmail], state: &str) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        tx.execute("DELETE FROM email", []).map_err(sql_err)?;
+        for e in emails {
+            store_email(&tx, e)?;
+        }
+        tx.execute(
+            "INSERT INTO state (kind, value, synced_at) VALUES ('Email', ?1, ?2)
+             ON CONFLICT(kind) DO UPDATE SET value = ?1, synced_at = ?2",
+            params![state, now()],
+        )
+        .map_err(sql_err)?;
+        tx.commit().map_err(sql_err)
+    }
+
+    /// Store a fetched body. Bodies never change, so this is done once per
+    /// email; it is a no-op for emails not in the cache.
+    pub fn set_body(&self, id: &str, body: &str) -> Result<(), String> {
+        self.conn
+            .execute("UPDATE email SET body = ?2 WHERE id = ?1", [id, body])
+            .map(|_| ())
+            .map_err(sql_err)
+    }
+
+    /// Whether the cache holds every email of `mailbox`, or of the whole
+    /// account when `None`, going by the mailbox totals from the last sync.
+    /// Every email is in some mailbox, so complete mailboxes make a complete
+    /// account.
+    pub fn complete(&self, mailbox: Option<&str>) -> Result<bool, String> {
+        let short: i64 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM mailbox mb
+                 WHERE (?1 IS NULL OR mb.id = ?1)
+                   AND mb.total > (SELECT COUNT(*) FROM email_mailbox m WHERE m.mailbox_id = mb.id)",
+                [mailbox],
+                |row| row.get(0),
+            )
+            .map_err(sql_err)?;
+        Ok(short == 0)
+    }
+
+    /// An email with its body, if both are cached.
+    pub fn email_with_body(&self, id: &str) -> Result<Option<(CachedEmail, String)>, String> {
+        let body: Option<String> = self
+            .conn
+            .query_row("SELECT body FROM email WHERE id = ?1", [id], |row| row.get(0))
+            .optional()
+            .map_err(sql_err)?
+            .flatten();
+        let Some(body) = body else {
+            return Ok(None);
+        };
+        let email = self.load_emails(&[id.to_string()])?.pop();
+        Ok(email.map(|e| (e, body)))
+    }
+
+    /// One page of matches and the total number of matches.
+    pub fn query(&self, q: &LocalQuery) -> Result<(Vec<CachedEmail>, usize), String> {
+        let mut clauses: Vec<String> = Vec::new();
+        let mut values: Vec<Value> = Vec::new();
+        // Bind a value and return its placeholder
+        let mut param = |value: Value| {
+            values.push(value);
+            format!("?{}", values.len())
+        };
+
+        if let Some(id) = &q.in_mailbox {
+            clauses.push(format!(
+                "EXISTS (SELECT 1 FROM email_mailbox m WHERE m.email_id = e.id AND m.mailbox_id = {})",
+                param(Value::Text(id.clone()))
+            ));
+        }
+        for id in &q.not_in {
+            clauses.push(format!(
+                "NOT EXISTS (SELECT 1 FROM email_mailbox m WHERE m.email_id = e.id AND m.mailbox_id = {})",
+                param(Value::Text(id.clone()))
+            ));
+        }
+        if let Some(text) = &q.text {
+            let p = param(like(text));
+            clauses.push(
+                ["e.subject", "e.sender", "e.recipients", "e.preview", "e.body"]
+                    .iter()
+                    .map(|column| format!("{} LIKE {} ESCAPE '\\'", column, p))
+                    .collect::<Vec<_>>()
+                    .join(" OR "),
+            );
+            let last = clauses.last_mut().unwrap();
+            *last = format!("({})", last);
+        }
+        for (column, value) in [("sender", &q.from), ("recipients", &q.to), ("subject", &q.subject)] {
+            if let Some(value) = value {
+                clauses.push(format!("e.{} LIKE {} ESCAPE '\\'", column, param(like(value))));
+            }
+        }
+        if let Some(ts) = q.before {
+            clauses.push(format!("e.received_at < {}", param(Value::Integer(ts))));
+        }
+        if let Some(ts) = q.after {
+            clauses.push(format!("e.received_at >= {}", param(Value::Integer(ts))));
+        }
+        if let Some(has) = q.has_attachment {
+            clauses.push(format!("e.has_attachment = {}", param(Value::Integer(has as i64))));
+        }
+        if let Some(keyword) = &q.keyword {
+            clauses.push(format!(
+                "EXISTS (SELECT 1 FROM email_keyword k WHERE k.email_id = e.id AND k.keyword = {})",
+                param(Value::Text(keyword.clone()))
+            ));
+        }
+        if let Some(keyword) = &q.not_keyword {
+            clauses.push(format!(
+                "NOT EXISTS (SELECT 1 FROM email_keyword k WHERE k.email_id = e.id AND k.keyword = {})",
+                param(Value::Text(keyword.clone()))
+            ));
+        }
+        if let Some(size) = q.min_size {
+            clauses.push(format!("e.size >= {}", param(Value::Integer(size))));
+        }
+        if let Some(size) = q.max_size {
+            clauses.push(format!("e.size < {}", param(Value::Integer(size))));
+        }
+
+        let filter = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", clauses.join(" AND "))
+        };
+        let total: i64 = self
+            .conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM email e{}", filter),
+                params_from_iter(values.iter()),
+                |row| row.get(0),
+            )
+            .map_err(sql_err)?;
+
+        let mut order: Vec<String> = q
+            .sort
+            .iter()
+            .map(|(key, asc)| {
+                let collate = match key {
+                    SortKey::From | SortKey::To | SortKey::Subject => " COLLATE NOCASE",
+                    _ => "",
+                };
+                format!("e.{}{} {}", key.column(), collate, if *asc { "ASC" } else { "DESC" })
+            })
+            .collect();
+        if order.is_empty() {
+            order.push("e.received_at DESC".into());
+        }
+        order.push("e.id".into());
+
+        let sql = format!(
+            "SELECT e.id FROM email e{} ORDER BY {} LIMIT {} OFFSET {}",
+            filter,
+            order.join(", "),
+            q.limit,
+            q.position
+        );
+        let mut stmt = self.conn.prepare(&sql).map_err(sql_err)?;
+        let ids = stmt
+            .query_map(params_from_iter(values.iter()), |row| row.get::<_, String>(0))
+            .map_err(sql_err)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(sql_err)?;
+
+        Ok((self.load_emails(&ids)?, total as usize))
+    }
+
+    /// Emails by ID, in the order given; unknown IDs are skipped.
+    fn load_emails(&self, ids: &[String]) -> Result<Vec<CachedEmail>, String> {
+        let mut email_stmt = self
+            .conn
+            .prepare(
+                "SELECT id, thread_id, subject, sender, recipients, received_at, sent_at, size,
+                        preview, has_attachment
+                 FROM email WHERE id = ?1",
+            )
+            .map_err(sql_err)?;
+        let mut mailbox_stmt = self
+            .conn
+            .prepare("SELECT mailbox_id FROM email_mailbox WHERE email_id = ?1 ORDER BY mailbox_id")
+            .map_err(sql_err)?;
+        let mut keyword_stmt = self
+            .conn
+            .prepare("SELECT keyword FROM email_keyword WHERE email_id = ?1 ORDER BY keyword")
+            .map_err(sql_err)?;
+
+        let mut emails = Vec::with_capacity(ids.len());
+        for id in ids {
+            let email = email_stmt
+                .query_row([id], |row| {
+                    Ok(CachedEmail {
+                        id: row.get(0)?,
+                        thread_id: row.get(1)?,
+                        subject: row.get(2)?,
+                        from: row.get(3)?,
+                        to: row.get(4)?,
+                        received_at: row.get(5)?,
+                        sent_at: row.get(6)?,
+                        size: row.get(7)?,
+                        preview: row.get(8)?,
+                        has_attachment: row.get(9)?,
+                        mailbox_ids: Vec::new(),
+                        keywords: Vec::new(),
+                    })
+                })
+                .optional()
+                .map_err(sql_err)?;
+            let Some(mut email) = email else { continue };
+            email.mailbox_ids = mailbox_stmt
+                .query_map([id], |row| row.get(0))
+                .map_err(sql_err)?
+                .collect::<Result<_, _>>()
+                .map_err(sql_err)?;
+            email.keywords = keyword_stmt
+                .query_map([id], |row| row.get(0))
+                .map_err(sql_err)?
+                .collect::<Result<_, _>>()
+                .map_err(sql_err)?;
+            emails.push(email);
+        }
+        Ok(emails)
+    }
+
+    pub fn stats(&self) -> Result<CacheStats, String> {
+        self.conn
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM mailbox), (SELECT COUNT(*) FROM email),
+                        (SELECT COUNT(*) FROM email WHERE body IS NOT NULL)",
+                [],
+                |row| {
+                    Ok(CacheStats {
+                        mailboxes: row.get(0)?,
+                        emails: row.get(1)?,
+                        bodies: row.get(2)?,
+                    })
+                },
+            )
+            .map_err(sql_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache() -> MailCache {
+        MailCache::with_connection(Connection::open_in_memory().unwrap(), Path::new(":memory:")).unwrap()
+    }
+
+    fn email(id: &str, subject: &str, received_at: i64, size: i64, mailbox: &str) -> CachedEmail {
+        CachedEmail {
+            id: id.into(),
+            thread_id: format!("t-{}", id),
+            subject: subject.into(),
+            from: "Bob <bob@example.com>".into(),
+            to: "ann@example.com".into(),
+            received_at,
+            sent_at: received_at,
+            size,
+            preview: String::new(),
+            has_attachment: false,
+            mailbox_ids: vec![mailbox.into()],
+            keywords: Vec::new(),
+        }
+    }
+
+    fn mailbox(id: &str, total: i64) -> CachedMailbox {
+        CachedMailbox { id: id.into(), name: id.into(), role: String::new(), total, unread: 0 }
+    }
+
+    fn ids(cache: &MailCache, q: &LocalQuery) -> (Vec<String>, usize) {
+        let (emails, total) = cache.query(q).unwrap();
+        (emails.into_iter().map(|e| e.id).collect(), total)
+    }
+
+    fn query() -> LocalQuery {
+        LocalQuery { limit: 50, ..LocalQuery::default() }
+    }
+
+    fn rows(cache: &MailCache, table: &str) -> i64 {
+        cache
+            .conn
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn open_keeps_the_cache_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("appmesh-mailcache-{}", std::process::id()));
+        let path = dir.join("mail").join("cache.db");
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        // A database left world-readable by an older version
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, "").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let mut cache = MailCache::open(&path).unwrap();
+        cache.update_emails(&[email("m1", "Secret", 100, 10, "inbox")], &[]).unwrap();
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+        for suffix in ["", "-wal", "-shm"] {
+            let file = PathBuf::from(format!("{}{}", path.display(), suffix));
+            assert_eq!(mode(&file), 0o600, "{}", file.display());
+        }
+
+        drop(cache);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn update_emails_keeps_cached_bodies() {
+        let mut cache = cache();
+        cache.update_emails(&[email("m1", "Draft", 100, 10, "inbox")], &[]).unwrap();
+        assert_eq!(cache.email_with_body("m1").unwrap(), None);
+        cache.set_body("m1", "hello").unwrap();
+
+        let mut updated = email("m1", "Final", 100, 10, "archive");
+        updated.keywords = vec!["$seen".into()];
+        cache.update_emails(&[updated.clone()], &[]).unwrap();
+
+        let (stored, body) = cache.email_with_body("m1").unwrap().unwrap();
+        assert_eq!(stored, updated);
+        assert_eq!(body, "hello");
+        assert_eq!(cache.stats().unwrap(), CacheStats { mailboxes: 0, emails: 1, bodies: 1 });
+    }
+
+    #[test]
+    fn destroyed_emails_take_their_mailboxes_and_keywords() {
+        let mut cache = cache();
+        let mut flagged = email("m1", "One", 100, 10, "inbox");
+        flagged.mailbox_ids.push("work".into());
+        flagged.keywords = vec!["$seen".into(), "$flagged".into()];
+        cache.update_emails(&[flagged, email("m2", "Two", 200, 10, "inbox")], &[]).unwrap();
+        assert_eq!((rows(&cache, "email_mailbox"), rows(&cache, "email_keyword")), (3, 2));
+
+        cache.update_emails(&[], &["m1".into()]).unwrap();
+        assert_eq!(rows(&cache, "email"), 1);
+        assert_eq!((rows(&cache, "email_mailbox"), rows(&cache, "email_keyword")), (1, 0));
+    }
+
+    #[test]
+    fn replace_emails_swaps_rows_and_state_together() {
+        let mut cache = cache();
+        cache.update_emails(&[email("m1", "Old", 100, 10, "inbox")], &[]).unwrap();
+        cache.set_state("Email", "s1").unwrap();
+
+        cache.replace_emails(&[email("m2", "New", 200, 10, "inbox")], "s2").unwrap();
+        assert_eq!(ids(&cache, &query()).0, ["m2"]);
+        assert_eq!(cache.state("Email").map(|(state, _)| state).as_deref(), Some("s2"));
+        assert_eq!(rows(&cache, "email_mailbox"), 1);
+    }
+
+    #[test]
+    fn query_filters_sorts_and_pages() {
+        let mut cache = cache();
+        let mut seen = email("m2", "beta", 200, 30, "inbox");
+        seen.keywords = vec!["$seen".into()];
+        seen.has_attachment = true;
+        cache
+            .update_emails(
+                &[
+                    email("m1", "Alpha", 100, 20, "inbox"),
+                    seen,
+                    email("m3", "gamma", 300, 10, "inbox"),
+                    email("m4", "Delta", 400, 40, "archive"),
+                ],
+                &[],
+            )
+            .unwrap();
+
+        // Newest first by default, with the total of all matches
+        assert_eq!(ids(&cache, &query()), (vec!["m4".into(), "m3".into(), "m2".into(), "m1".into()], 4));
+        let inbox = LocalQuery { in_mailbox: Some("inbox".into()), ..query() };
+        assert_eq!(ids(&cache, &inbox).1, 3);
+        let not_inbox = LocalQuery { not_in: vec!["inbox".into()], ..query() };
+        assert_eq!(ids(&cache, &not_inbox).0, ["m4"]);
+        let unseen = LocalQuery { not_keyword: Some("$seen".into()), ..inbox.clone() };
+        assert_eq!(ids(&cache, &unseen).0, ["m3", "m1"]);
+        let attached = LocalQuery { has_attachment: Some(true), ..query() };
+        assert_eq!(ids(&cache, &attached).0, ["m2"]);
+        let text = LocalQuery { text: Some("ALP".into()), ..query() };
+        assert_eq!(ids(&cache, &text).0, ["m1"]);
+
+        // Subjects sort case-insensitively; a page keeps the full total
+        let by_subject = LocalQuery { sort: vec![(SortKey::Subject, true)], ..query() };
+        assert_eq!(ids(&cache, &by_subject).0, ["m1", "m2", "m4", "m3"]);
+        let page = LocalQuery { position: 1, limit: 2, ..by_subject };
+        assert_eq!(ids(&cache, &page), (vec!["m2".into(), "m4".into()], 4));
+        let by_size = LocalQuery { sort: vec![(SortKey::Size, false)], limit: 1, ..query() };
+        assert_eq!(ids(&cache, &by_size).0, ["m4"]);
+    }
+
+    #[test]
+    fn size_and_date_bounds_follow_jmap() {
+        let mut cache = cache();
+        cache
+            .update_emails(
+                &[
+                    email("m1", "a", 100, 10, "inbox"),
+                    email("m2", "b", 200, 20, "inbox"),
+                    email("m3", "c", 300, 30, "inbox"),
+                ],
+                &[],
+            )
+            .unwrap();
+
+        // min_size <= size < max_size
+        let sizes = LocalQuery { min_size: Some(20), max_size: Some(30), ..query() };
+        assert_eq!(ids(&cache, &sizes).0, ["m2"]);
+        // after <= receivedAt < before
+        let dates = LocalQuery { after: Some(200), before: Some(300), ..query() };
+        assert_eq!(ids(&cache, &dates).0, ["m2"]);
+        let empty = LocalQuery { after: Some(300), before: Some(300), ..query() };
+        assert_eq!(ids(&cache, &empty), (vec![], 0));
+    }
+
+    #[test]
+    fn like_matches_wildcards_literally() {
+        assert_eq!(like(r"50%_off\"), Value::Text(r"%50\%\_off\\%".into()));
+
+        let mut cache = cache();
+        cache
+            .update_emails(
+                &[
+                    email("m1", "100% sure", 100, 10, "inbox"),
+                    email("m2", "100 percent", 200, 10, "inbox"),
+                    email("m3", "snake_case", 300, 10, "inbox"),
+                    email("m4", "snakeXcase", 400, 10, "inbox"),
+                ],
+                &[],
+            )
+            .unwrap();
+        let subject = |text: &str| ids(&cache, &LocalQuery { subject: Some(text.into()), ..query() }).0;
+        assert_eq!(subject("0%"), ["m1"]);
+        assert_eq!(subject("e_c"), ["m3"]);
+        assert_eq!(subject("100"), ["m2", "m1"]);
+    }
+
+    #[test]
+    fn complete_compares_cached_emails_with_mailbox_totals() {
+        let mut cache = cache();
+        cache.replace_mailboxes(&[mailbox("inbox", 2), mailbox("archive", 1)]).unwrap();
+        cache.update_emails(&[email("m1", "a", 100, 10, "inbox"), email("m2", "b", 200, 10, "inbox")], &[]).unwrap();
+
+        assert!(cache.complete(Some("inbox")).unwrap());
+        assert!(!cache.complete(Some("archive")).unwrap());
+        assert!(!cache.complete(None).unwrap());
+
+        cache.update_emails(&[email("m3", "c", 300, 10, "archive")], &[]).unwrap();
+        assert!(cache.complete(None).unwrap());
+    }
+}
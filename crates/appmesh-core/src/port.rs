@@ -1,4 +1,7 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
@@ -24,12 +27,33 @@ pub struct ParamDef {
 pub enum PortValue {
     String(String),
     Int(i64),
+    Float(f64),
     Bool(bool),
     List(Vec<PortValue>),
     Map(HashMap<String, PortValue>),
     Null,
 }
 
+impl From<serde_json::Value> for PortValue {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => PortValue::Null,
+            serde_json::Value::Bool(b) => PortValue::Bool(b),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(i) => PortValue::Int(i),
+                None => PortValue::Float(n.as_f64().unwrap_or(0.0)),
+            },
+            serde_json::Value::String(s) => PortValue::String(s),
+            serde_json::Value::Array(list) => {
+                PortValue::List(list.into_iter().map(PortValue::from).collect())
+            }
+            serde_json::Value::Object(map) => {
+                PortValue::Map(map.into_iter().map(|(k, v)| (k, PortValue::from(v))).collect())
+            }
+        }
+    }
+}
+
 /// Error from a port command.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortError {
@@ -45,9 +69,141 @@ impl std::fmt::Display for PortError {
 
 impl std::error::Error for PortError {}
 
+impl PortError {
+    /// A command failure with the generic error code.
+    pub fn msg(message: impl std::fmt::Display) -> Self {
+        PortError { code: -1, message: message.to_string() }
+    }
+}
+
 /// Result type for port commands.
 pub type PortResult = Result<PortValue, PortError>;
 
+/// A required command argument.
+pub fn required_arg(args: &HashMap<String, String>, name: &str) -> Result<String, PortError> {
+    args.get(name)
+        .cloned()
+        .ok_or_else(|| PortError::msg(format!("missing '{}' argument", name)))
+}
+
+/// An optional flag argument: `true` or `1` turns it on.
+pub fn bool_arg(args: &HashMap<String, String>, name: &str, default: bool) -> bool {
+    args.get(name)
+        .map(|s| s == "true" || s == "1")
+        .unwrap_or(default)
+}
+
+/// Event emitted asynchronously by a port (window opened, action invoked, ...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortEvent {
+    pub port: String,
+    pub event: String,
+    pub data: PortValue,
+}
+
+/// Receiving end of a port subscription. Dropping it stops the port's watcher.
+pub struct EventStream {
+    rx: mpsc::Receiver<PortEvent>,
+    closed: Arc<AtomicBool>,
+}
+
+impl EventStream {
+    /// Block until the next event. Returns `None` once the watcher has exited.
+    pub fn recv(&self) -> Option<PortEvent> {
+        self.rx.recv().ok()
+    }
+
+    /// Wait up to `timeout` for the next event. Returns `None` on timeout or
+    /// once the watcher has exited.
+    pub fn next(&self, timeout: Duration) -> Option<PortEvent> {
+        self.rx.recv_timeout(timeout).ok()
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Sending end of a port subscription, owned by the port's watcher.
+#[derive(Clone)]
+pub struct EventSink {
+    port: String,
+    tx: mpsc::Sender<PortEvent>,
+    closed: Arc<AtomicBool>,
+}
+
+impl EventSink {
+    /// Emit an event to the subscriber.
+    pub fn emit(&self, event: &str, data: PortValue) {
+        let _ = self.tx.send(PortEvent {
+            port: self.port.clone(),
+            event: event.into(),
+            data,
+        });
+    }
+
+    /// Whether the subscriber has dropped its stream.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+
+    /// Resolve once the subscriber has dropped its stream.
+    pub async fn closed(&self) {
+        while !self.is_closed() {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    }
+}
+
+/// Run an event watcher on its own thread and tokio runtime.
+///
+/// Watchers need a runtime that is always being driven (D-Bus signals and
+/// callbacks arrive at any time), which the ports' own block_on runtimes are not.
+/// The watcher must call `ready` once it is listening, passing any setup error;
+/// it should then run until `EventSink::closed` resolves.
+pub fn spawn_watcher<F, Fut>(port: &str, watcher: F) -> Result<EventStream, PortError>
+where
+    F: FnOnce(EventSink, mpsc::Sender<Result<(), String>>) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = ()>,
+{
+    let (tx, rx) = mpsc::channel();
+    let closed = Arc::new(AtomicBool::new(false));
+    let sink = EventSink {
+        port: port.to_string(),
+        tx,
+        closed: closed.clone(),
+    };
+    let (ready_tx, ready_rx) = mpsc::channel();
+
+    std::thread::Builder::new()
+        .name(format!("appmesh-{}-events", port))
+        .spawn(move || {
+            let rt = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                Ok(rt) => rt,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e.to_string()));
+                    return;
+                }
+            };
+            rt.block_on(watcher(sink, ready_tx));
+        })
+        .map_err(PortError::msg)?;
+
+    match ready_rx.recv_timeout(Duration::from_secs(10)) {
+        Ok(Ok(())) => Ok(EventStream { rx, closed }),
+        Ok(Err(message)) => Err(PortError { code: -1, message }),
+        Err(_) => {
+            closed.store(true, Ordering::Relaxed);
+            Err(PortError {
+                code: -1,
+                message: format!("{} event watcher failed to start", port),
+            })
+        }
+    }
+}
+
 /// The ARexx-style port trait — every scriptable subsystem implements this.
 pub trait AppMeshPort: Send {
     /// Port name (e.g. "input", "windows", "clipboard").
@@ -58,4 +214,12 @@ pub trait AppMeshPort: Send {
 
     /// Execute a command with the given arguments.
     fn execute(&self, cmd: &str, args: &HashMap<String, String>) -> PortResult;
+
+    /// Subscribe to events emitted by this port.
+    fn subscribe(&self) -> Result<EventStream, PortError> {
+        Err(PortError {
+            code: -1,
+            message: format!("port '{}' does not emit events", self.name()),
+        })
+    }
 }
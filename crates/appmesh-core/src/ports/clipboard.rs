@@ -1,8 +1,9 @@
 use std::collections::HashMap;
 
 use crate::port::*;
+use crate::transform;
 
-/// Clipboard port — Klipper get/set via D-Bus.
+/// Clipboard port — Klipper get/set/transform via D-Bus.
 pub struct ClipboardPort {
     rt: tokio::runtime::Runtime,
     connection: zbus::Connection,
@@ -26,14 +27,14 @@ impl ClipboardPort {
                 "org.kde.klipper.klipper",
             )
             .await
-            .map_err(|e| PortError { code: -1, message: e.to_string() })?;
+            .map_err(PortError::msg)?;
 
             let reply: zbus::Message = if let Some(text) = args {
                 proxy.call_method(method, &(text,)).await
             } else {
                 proxy.call_method(method, &()).await
             }
-            .map_err(|e| PortError { code: -1, message: e.to_string() })?;
+            .map_err(PortError::msg)?;
 
             let body = reply.body();
             match body.deserialize::<String>() {
@@ -66,6 +67,19 @@ impl AppMeshPort for ClipboardPort {
                     ParamDef { name: "text".into(), description: "Text to copy to clipboard".into(), required: true },
                 ],
             },
+            CommandDef {
+                name: "transform".into(),
+                description: "Transform clipboard contents through a chain of named transforms".into(),
+                params: vec![
+                    ParamDef { name: "ops".into(), description: "Transform chain, e.g. trim,lower,strip_tracking".into(), required: true },
+                    ParamDef { name: "dry_run".into(), description: "Return the result without setting the clipboard (default: false)".into(), required: false },
+                ],
+            },
+            CommandDef {
+                name: "transforms".into(),
+                description: "List available clipboard transforms".into(),
+                params: vec![],
+            },
         ]
     }
 
@@ -76,13 +90,33 @@ impl AppMeshPort for ClipboardPort {
                 Ok(PortValue::String(contents))
             }
             "set" => {
-                let text = args.get("text").ok_or_else(|| PortError {
-                    code: -1,
-                    message: "missing 'text' argument".into(),
-                })?;
-                self.call_klipper("setClipboardContents", Some(text))?;
+                let text = required_arg(args, "text")?;
+                self.call_klipper("setClipboardContents", Some(&text))?;
                 Ok(PortValue::String(format!("clipboard set ({} chars)", text.len())))
             }
+            "transform" => {
+                let ops = required_arg(args, "ops")?;
+                let dry_run = bool_arg(args, "dry_run", false);
+
+                let contents = self.call_klipper("getClipboardContents", None)?;
+                let result = transform::apply_chain(&contents, &ops).map_err(PortError::msg)?;
+                if !dry_run {
+                    self.call_klipper("setClipboardContents", Some(&result))?;
+                }
+                Ok(PortValue::String(result))
+            }
+            "transforms" => {
+                let list = transform::TRANSFORMS
+                    .iter()
+                    .map(|t| {
+                        let mut map = HashMap::new();
+                        map.insert("name".into(), PortValue::String(t.name.into()));
+                        map.insert("description".into(), PortValue::String(t.description.into()));
+                        PortValue::Map(map)
+                    })
+                    .collect();
+                Ok(PortValue::List(list))
+            }
             other => Err(PortError {
                 code: -1,
                 message: format!("unknown command: {}", other),
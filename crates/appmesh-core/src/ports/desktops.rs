@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+use std::sync::mpsc;
+
+use futures_util::StreamExt;
+
+use crate::port::*;
+
+const KWIN_SERVICE: &str = "org.kde.KWin";
+const DESKTOPS_PATH: &str = "/VirtualDesktopManager";
+const DESKTOPS_INTERFACE: &str = "org.kde.KWin.VirtualDesktopManager";
+
+const ACTIVITIES_SERVICE: &str = "org.kde.ActivityManager";
+const ACTIVITIES_PATH: &str = "/ActivityManager/Activities";
+const ACTIVITIES_INTERFACE: &str = "org.kde.ActivityManager.Activities";
+
+/// Desktops port — KWin virtual desktops and Plasma activities via D-Bus.
+pub struct DesktopsPort {
+    rt: tokio::runtime::Runtime,
+    connection: zbus::Connection,
+}
+
+/// A virtual desktop as KWin reports it: `(position, id, name)`.
+type DesktopData = (u32, String, String);
+
+/// An activity as kactivitymanagerd reports it: `(id, name, description, icon, state)`.
+type ActivityInfo = (String, String, String, String, i32);
+
+impl DesktopsPort {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let connection = rt.block_on(zbus::Connection::session())?;
+        Ok(Self { rt, connection })
+    }
+
+    fn desktops(&self) -> Result<Vec<DesktopData>, PortError> {
+        self.rt.block_on(async {
+            let proxy = desktops_proxy(&self.connection).await.map_err(PortError::msg)?;
+            read_desktops(&proxy).await.map_err(PortError::msg)
+        })
+    }
+
+    fn current_desktop_id(&self) -> Result<String, PortError> {
+        self.rt.block_on(async {
+            let proxy = desktops_proxy(&self.connection).await.map_err(PortError::msg)?;
+            proxy.get_property::<String>("current").await.map_err(PortError::msg)
+        })
+    }
+
+    /// Resolve a desktop by ID, name or 1-based number.
+    fn find_desktop(&self, selector: &str) -> Result<DesktopData, PortError> {
+        let desktops = self.desktops()?;
+        let number: Option<u32> = selector.trim().parse().ok();
+        desktops
+            .iter()
+            .find(|(_, id, _)| id == selector)
+            .or_else(|| desktops.iter().find(|(_, _, name)| name == selector))
+            .or_else(|| number.and_then(|n| desktops.iter().find(|(pos, _, _)| pos + 1 == n)))
+            .cloned()
+            .ok_or_else(|| PortError::msg(format!("no such desktop: {}", selector)))
+    }
+
+    fn list_desktops(&self) -> Result<PortValue, PortError> {
+        let current = self.current_desktop_id()?;
+        Ok(PortValue::List(
+            self.desktops()?
+                .iter()
+                .map(|d| desktop_value(d, &current))
+                .collect(),
+        ))
+    }
+
+    fn current_desktop(&self) -> Result<PortValue, PortError> {
+        let current = self.current_desktop_id()?;
+        let desktop = self.find_desktop(&current)?;
+        Ok(desktop_value(&desktop, &current))
+    }
+
+    fn switch_desktop(&self, selector: &str) -> Result<PortValue, PortError> {
+        let (_, id, name) = self.find_desktop(selector)?;
+        self.rt.block_on(async {
+            let proxy = desktops_proxy(&self.connection).await.map_err(PortError::msg)?;
+            proxy.set_property("current", id.as_str()).await.map_err(PortError::msg)
+        })?;
+        Ok(PortValue::String(format!("switched to desktop: {}", name)))
+    }
+
+    fn create_desktop(&self, name: &str, position: Option<u32>) -> Result<PortValue, PortError> {
+        let before = self.desktops()?;
+        let position = position.unwrap_or(before.len() as u32);
+        self.call_desktops("createDesktop", &(position, name))?;
+
+        // createDesktop returns nothing, so find the new desktop by its ID
+        let current = self.current_desktop_id()?;
+        self.desktops()?
+            .iter()
+            .find(|(_, id, _)| !before.iter().any(|(_, old, _)| old == id))
+            .map(|d| desktop_value(d, &current))
+            .ok_or_else(|| PortError::msg("desktop was not created"))
+    }
+
+    fn remove_desktop(&self, selector: &str) -> Result<PortValue, PortError> {
+        let (_, id, name) = self.find_desktop(selector)?;
+        self.call_desktops("removeDesktop", &(id.as_str(),))?;
+        Ok(PortValue::String(format!("removed desktop: {}", name)))
+    }
+
+    fn rename_desktop(&self, selector: &str, name: &str) -> Result<PortValue, PortError> {
+        let (_, id, old) = self.find_desktop(selector)?;
+        self.call_desktops("setDesktopName", &(id.as_str(), name))?;
+        Ok(PortValue::String(format!("renamed desktop: {} -> {}", old, name)))
+    }
+
+    fn call_desktops<B>(&self, method: &str, body: &B) -> Result<(), PortError>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        self.rt.block_on(async {
+            let proxy = desktops_proxy(&self.connection).await.map_err(PortError::msg)?;
+            proxy.call::<_, _, ()>(method, body).await.map_err(PortError::msg)
+        })
+    }
+
+    fn activities(&self) -> Result<Vec<ActivityInfo>, PortError> {
+        self.rt.block_on(async {
+            let proxy = activities_proxy(&self.connection).await.map_err(PortError::msg)?;
+            proxy
+                .call::<_, _, Vec<ActivityInfo>>("ListActivitiesWithInformation", &())
+                .await
+                .map_err(PortError::msg)
+        })
+    }
+
+    fn current_activity_id(&self) -> Result<String, PortError> {
+        self.rt.block_on(async {
+            let proxy = activities_proxy(&self.connection).await.map_err(PortError::msg)?;
+            proxy.call::<_, _, String>("CurrentActivity", &()).await.map_err(PortError::msg)
+        })
+    }
+
+    /// Resolve an activity by ID or name.
+    fn find_activity(&self, selector: &str) -> Result<ActivityInfo, PortError> {
+        let activities = self.activities()?;
+        activities
+            .iter()
+            .find(|a| a.0 == selector)
+            .or_else(|| activities.iter().find(|a| a.1 == selector))
+            .cloned()
+            .ok_or_else(|| PortError::msg(format!("no such activity: {}", selector)))
+    }
+
+    fn list_activities(&self) -> Result<PortValue, PortError> {
+        let current = self.current_activity_id()?;
+        Ok(PortValue::List(
+            self.activities()?
+                .iter()
+                .map(|a| activity_value(a, &current))
+                .collect(),
+        ))
+    }
+
+    fn current_activity(&self) -> Result<PortValue, PortError> {
+        let current = self.current_activity_id()?;
+        let activity = self.find_activity(&current)?;
+        Ok(activity_value(&activity, &current))
+    }
+
+    /// Call an activity manager method taking the activity ID, returning its name.
+    fn activity_call<R>(&self, selector: &str, method: &str) -> Result<(String, R), PortError>
+    where
+        R: for<'d> zbus::zvariant::DynamicDeserialize<'d>,
+    {
+        let (id, name, ..) = self.find_activity(selector)?;
+        let reply = self.rt.block_on(async {
+            let proxy = activities_proxy(&self.connection).await.map_err(PortError::msg)?;
+            proxy.call::<_, _, R>(method, &(id.as_str(),)).await.map_err(PortError::msg)
+        })?;
+        Ok((name, reply))
+    }
+
+    fn switch_activity(&self, selector: &str) -> Result<PortValue, PortError> {
+        let (name, switched) = self.activity_call::<bool>(selector, "SetCurrentActivity")?;
+        if !switched {
+            return Err(PortError::msg(format!("could not switch to activity: {}", name)));
+        }
+        Ok(PortValue::String(format!("switched to activity: {}", name)))
+    }
+
+    fn start_activity(&self, selector: &str) -> Result<PortValue, PortError> {
+        let (name, ()) = self.activity_call(selector, "StartActivity")?;
+        Ok(PortValue::String(format!("started activity: {}", name)))
+    }
+
+    fn stop_activity(&self, selector: &str) -> Result<PortValue, PortError> {
+        let (name, ()) = self.activity_call(selector, "StopActivity")?;
+        Ok(PortValue::String(format!("stopped activity: {}", name)))
+    }
+}
+
+// Safety: DesktopsPort is only used from one thread at a time via Mutex or single-threaded FFI
+unsafe impl Send for DesktopsPort {}
+
+impl AppMeshPort for DesktopsPort {
+    fn name(&self) -> &str {
+        "desktops"
+    }
+
+    fn commands(&self) -> Vec<CommandDef> {
+        vec![
+            CommandDef {
+                name: "list".into(),
+                description: "List virtual desktops (id, name, number, current)".into(),
+                params: vec![],
+            },
+            CommandDef {
+                name: "current".into(),
+                description: "Get the current virtual desktop".into(),
+                params: vec![],
+            },
+            CommandDef {
+                name: "switch".into(),
+                description: "Switch to a virtual desktop".into(),
+                params: vec![desktop_param()],
+            },
+            CommandDef {
+                name: "create".into(),
+                description: "Create a virtual desktop".into(),
+                params: vec![
+                    ParamDef { name: "name".into(), description: "Desktop name".into(), required: true },
+                    ParamDef { name: "position".into(), description: "1-based position (default: last)".into(), required: false },
+                ],
+            },
+            CommandDef {
+                name: "remove".into(),
+                description: "Remove a virtual desktop".into(),
+                params: vec![desktop_param()],
+            },
+            CommandDef {
+                name: "rename".into(),
+                description: "Rename a virtual desktop".into(),
+                params: vec![
+                    desktop_param(),
+                    ParamDef { name: "name".into(), description: "New name".into(), required: true },
+                ],
+            },
+            CommandDef {
+                name: "activity_list".into(),
+                description: "List activities (id, name, description, icon, state, current)".into(),
+                params: vec![],
+            },
+            CommandDef {
+                name: "activity_current".into(),
+                description: "Get the current activity".into(),
+                params: vec![],
+            },
+            CommandDef {
+                name: "activity_switch".into(),
+                description: "Switch to an activity".into(),
+                params: vec![activity_param()],
+            },
+            CommandDef {
+                name: "activity_start".into(),
+                description: "Start a stopped activity".into(),
+                params: vec![activity_param()],
+            },
+            CommandDef {
+                name: "activity_stop".into(),
+                description: "Stop a running activity".into(),
+                params: vec![activity_param()],
+            },
+        ]
+    }
+
+    fn execute(&self, cmd: &str, args: &HashMap<String, String>) -> PortResult {
+        match cmd {
+            "list" => self.list_desktops(),
+            "current" => self.current_desktop(),
+            "switch" => self.switch_desktop(&required_arg(args, "desktop")?),
+            "create" => {
+                let position = match args.get("position") {
+                    Some(p) => match p.trim().parse::<u32>() {
+                        Ok(n) if n >= 1 => Some(n - 1),
+                        _ => return Err(PortError::msg(format!("invalid 'position': {}", p))),
+                    },
+                    None => None,
+                };
+                self.create_desktop(&required_arg(args, "name")?, position)
+            }
+            "remove" => self.remove_desktop(&required_arg(args, "desktop")?),
+            "rename" => self.rename_desktop(&required_arg(args, "desktop")?, &required_arg(args, "name")?),
+            "activity_list" => self.list_activities(),
+            "activity_current" => self.current_activity(),
+            "activity_switch" => self.switch_activity(&required_arg(args, "activity")?),
+            "activity_start" => self.start_activity(&required_arg(args, "activity")?),
+            "activity_stop" => self.stop_activity(&required_arg(args, "activity")?),
+            other => Err(PortError::msg(format!("unknown command: {}", other))),
+        }
+    }
+
+    fn subscribe(&self) -> Result<EventStream, PortError> {
+        spawn_watcher("desktops", watch_desktops)
+    }
+}
+
+/// Forward desktop and activity signals as port events until unsubscribed.
+///
+/// Events: `desktop_changed`, `desktop_created`, `desktop_removed`,
+/// `desktop_updated`, `activity_changed`, `activity_added`,
+/// `activity_removed` and `activity_state_changed`.
+async fn watch_desktops(sink: EventSink, ready: mpsc::Sender<Result<(), String>>) {
+    let setup = async {
+        let connection = zbus::Connection::session().await?;
+        let desktops = desktops_proxy(&connection).await?;
+        let activities = activities_proxy(&connection).await?;
+        let desktop_signals = desktops.receive_all_signals().await?;
+        let activity_signals = activities.receive_all_signals().await?;
+        Ok::<_, zbus::Error>((connection, desktops, desktop_signals, activity_signals))
+    };
+
+    let (_connection, desktops, mut desktop_signals, mut activity_signals) = match setup.await {
+        Ok(running) => {
+            let _ = ready.send(Ok(()));
+            running
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e.to_string()));
+            return;
+        }
+    };
+
+    loop {
+        tokio::select! {
+            Some(msg) = desktop_signals.next() => {
+                if let Some((event, data)) = desktop_event(&desktops, &msg).await {
+                    sink.emit(event, data);
+                }
+            }
+            Some(msg) = activity_signals.next() => {
+                if let Some((event, data)) = activity_event(&msg) {
+                    sink.emit(event, data);
+                }
+            }
+            _ = sink.closed() => break,
+        }
+    }
+}
+
+async fn desktop_event(
+    proxy: &zbus::Proxy<'_>,
+    msg: &zbus::Message,
+) -> Option<(&'static str, PortValue)> {
+    let header = msg.header();
+    let body = msg.body();
+    match header.member()?.as_str() {
+        "currentChanged" => {
+            let id: String = body.deserialize().ok()?;
+            // The signal only carries the ID; look up the rest for subscribers
+            let desktop = read_desktops(proxy)
+                .await
+                .ok()
+                .and_then(|all| all.into_iter().find(|(_, d, _)| *d == id))
+                .unwrap_or((0, id.clone(), String::new()));
+            Some(("desktop_changed", desktop_value(&desktop, &id)))
+        }
+        "desktopCreated" => {
+            let (_, desktop): (String, DesktopData) = body.deserialize().ok()?;
+            Some(("desktop_created", desktop_value(&desktop, "")))
+        }
+        "desktopDataChanged" => {
+            let (_, desktop): (String, DesktopData) = body.deserialize().ok()?;
+            Some(("desktop_updated", desktop_value(&desktop, "")))
+        }
+        "desktopRemoved" => {
+            let id: String = body.deserialize().ok()?;
+            Some(("desktop_removed", id_value(id)))
+        }
+        _ => None,
+    }
+}
+
+fn activity_event(msg: &zbus::Message) -> Option<(&'static str, PortValue)> {
+    let header = msg.header();
+    let body = msg.body();
+    let event = match header.member()?.as_str() {
+        "CurrentActivityChanged" => "activity_changed",
+        "ActivityAdded" => "activity_added",
+        "ActivityRemoved" => "activity_removed",
+        "ActivityStateChanged" => {
+            let (id, state): (String, i32) = body.deserialize().ok()?;
+            let mut map = HashMap::new();
+            map.insert("id".into(), PortValue::String(id));
+            map.insert("state".into(), PortValue::String(activity_state(state).into()));
+            return Some(("activity_state_changed", PortValue::Map(map)));
+        }
+        _ => return None,
+    };
+    let id: String = body.deserialize().ok()?;
+    Some((event, id_value(id)))
+}
+
+async fn desktops_proxy(connection: &zbus::Connection) -> zbus::Result<zbus::Proxy<'static>> {
+    // Properties are read fresh on every call; KWin does not announce all changes
+    zbus::proxy::Builder::new(connection)
+        .destination(KWIN_SERVICE)?
+        .path(DESKTOPS_PATH)?
+        .interface(DESKTOPS_INTERFACE)?
+        .cache_properties(zbus::proxy::CacheProperties::No)
+        .build()
+        .await
+}
+
+async fn activities_proxy(connection: &zbus::Connection) -> zbus::Result<zbus::Proxy<'static>> {
+    zbus::proxy::Builder::new(connection)
+        .destination(ACTIVITIES_SERVICE)?
+        .path(ACTIVITIES_PATH)?
+        .interface(ACTIVITIES_INTERFACE)?
+        .cache_properties(zbus::proxy::CacheProperties::No)
+        .build()
+        .await
+}
+
+/// Read the `desktops` property, sorted by position.
+async fn read_desktops(proxy: &zbus::Proxy<'_>) -> zbus::Result<Vec<DesktopData>> {
+    let mut desktops: Vec<DesktopData> = proxy.get_property("desktops").await?;
+    desktops.sort_by_key(|(pos, _, _)| *pos);
+    Ok(desktops)
+}
+
+fn desktop_value((position, id, name): &DesktopData, current: &str) -> PortValue {
+    let mut map = HashMap::new();
+    map.insert("id".into(), PortValue::String(id.clone()));
+    map.insert("name".into(), PortValue::String(name.clone()));
+    map.insert("number".into(), PortValue::Int(*position as i64 + 1));
+    map.insert("current".into(), PortValue::Bool(id == current));
+    PortValue::Map(map)
+}
+
+fn activity_value((id, name, description, icon, state): &ActivityInfo, current: &str) -> PortValue {
+    let mut map = HashMap::new();
+    map.insert("id".into(), PortValue::String(id.clone()));
+    map.insert("name".into(), PortValue::String(name.clone()));
+    map.insert("description".into(), PortValue::String(description.clone()));
+    map.insert("icon".into(), PortValue::String(icon.clone()));
+    map.insert("state".into(), PortValue::String(activity_state(*state).into()));
+    map.insert("current".into(), PortValue::Bool(id == current));
+    PortValue::Map(map)
+}
+
+fn id_value(id: String) -> PortValue {
+    let mut map = HashMap::new();
+    map.insert("id".into(), PortValue::String(id));
+    PortValue::Map(map)
+}
+
+/// Name for a kactivitymanagerd `Info::State` value.
+fn activity_state(state: i32) -> &'static str {
+    match state {
+        2 => "running",
+        3 => "starting",
+        4 => "stopped",
+        5 => "stopping",
+        _ => "unknown",
+    }
+}
+
+fn desktop_param() -> ParamDef {
+    ParamDef { name: "desktop".into(), description: "Desktop ID, name or 1-based number".into(), required: true }
+}
+
+fn activity_param() -> ParamDef {
+    ParamDef { name: "activity".into(), description: "Activity ID or name".into(), required: true }
+}
@@ -57,25 +57,13 @@ impl AppMeshPort for InputPort {
 
         match cmd {
             "type_text" => {
-                let text = args.get("text").ok_or_else(|| PortError {
-                    code: -1,
-                    message: "missing 'text' argument".into(),
-                })?;
-                handle.type_text(text, delay_us).map_err(|e| PortError {
-                    code: -1,
-                    message: e.to_string(),
-                })?;
+                let text = &required_arg(args, "text")?;
+                handle.type_text(text, delay_us).map_err(PortError::msg)?;
                 Ok(PortValue::String(format!("typed {} characters", text.len())))
             }
             "send_key" => {
-                let combo = args.get("combo").ok_or_else(|| PortError {
-                    code: -1,
-                    message: "missing 'combo' argument".into(),
-                })?;
-                handle.send_key(combo, delay_us).map_err(|e| PortError {
-                    code: -1,
-                    message: e.to_string(),
-                })?;
+                let combo = &required_arg(args, "combo")?;
+                handle.send_key(combo, delay_us).map_err(PortError::msg)?;
                 Ok(PortValue::String(format!("sent key combo: {}", combo)))
             }
             other => Err(PortError {
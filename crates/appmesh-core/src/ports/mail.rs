@@ -1,60 +1,385 @@
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
 
 use jmap_client::client::Client;
+use jmap_client::core::error::MethodErrorType;
 use jmap_client::core::query;
-use jmap_client::core::response::{EmailGetResponse, EmailSetResponse, IdentityGetResponse};
+use jmap_client::core::response::{
+    EmailGetResponse, EmailSetResponse, IdentityGetResponse, MailboxGetResponse,
+};
 use jmap_client::core::set::SetObject;
 use jmap_client::email;
 use jmap_client::email::EmailBodyPart;
+use jmap_client::email_submission::{self, UndoStatus};
 use jmap_client::mailbox;
+use jmap_client::thread;
+use jmap_client::TypeState;
 
+use crate::compose::{self, Address};
+use crate::config;
+use crate::mailcache::{self, CachedEmail, CachedMailbox, LocalQuery, MailCache, SortKey};
 use crate::port::*;
 
+/// Properties fetched for each row of a query or search result.
+const SUMMARY_PROPERTIES: [email::Property; 7] = [
+    email::Property::Id,
+    email::Property::ThreadId,
+    email::Property::Subject,
+    email::Property::From,
+    email::Property::To,
+    email::Property::ReceivedAt,
+    email::Property::Preview,
+];
+
+/// One page of an Email/query: filter, sort order and window.
+struct EmailQuery {
+    conditions: Vec<email::query::Filter>,
+    sort: Vec<query::Comparator<email::query::Comparator>>,
+    position: Option<i32>,
+    anchor: Option<String>,
+    anchor_offset: Option<i32>,
+    limit: usize,
+    /// One row per thread, with its message count and participants.
+    collapse_threads: bool,
+}
+
+/// Rows of a query page plus where it sits in the full result.
+struct EmailPage {
+    emails: Vec<PortValue>,
+    total: Option<usize>,
+    position: usize,
+}
+
+const NOT_CONNECTED: &str =
+    "not connected — call 'connect' first or set JMAP_URL/JMAP_USER/JMAP_PASS env vars";
+
+/// Config kind holding one `<name>.json` login per named account.
+const ACCOUNTS_KIND: &str = "mail";
+
+/// Account used when neither the port name (`mail.<name>`) nor `account` picks one.
+const DEFAULT_ACCOUNT: &str = "default";
+
+/// Properties fetched for emails reported by the push watcher.
+const WATCH_PROPERTIES: [email::Property; 9] = [
+    email::Property::Id,
+    email::Property::ThreadId,
+    email::Property::Subject,
+    email::Property::From,
+    email::Property::To,
+    email::Property::ReceivedAt,
+    email::Property::Preview,
+    email::Property::MailboxIds,
+    email::Property::Keywords,
+];
+
+/// Properties kept in the offline cache for each email.
+const CACHE_PROPERTIES: [email::Property; 12] = [
+    email::Property::Id,
+    email::Property::ThreadId,
+    email::Property::MailboxIds,
+    email::Property::Keywords,
+    email::Property::Size,
+    email::Property::ReceivedAt,
+    email::Property::SentAt,
+    email::Property::Subject,
+    email::Property::From,
+    email::Property::To,
+    email::Property::Preview,
+    email::Property::HasAttachment,
+];
+
+/// Properties kept in the offline cache for each mailbox.
+const CACHE_MAILBOX_PROPERTIES: [mailbox::Property; 5] = [
+    mailbox::Property::Id,
+    mailbox::Property::Name,
+    mailbox::Property::Role,
+    mailbox::Property::TotalEmails,
+    mailbox::Property::UnreadEmails,
+];
+
+/// Emails loaded by a first sync unless `limit` says otherwise, newest first.
+const SYNC_LIMIT: usize = 5000;
+
+/// Emails per request while loading the cache.
+const SYNC_BATCH: usize = 256;
+
+/// Lookups answered from the cache sync first once it is this old (seconds).
+const SYNC_INTERVAL: i64 = 60;
+
+/// Most changes fetched per Email/changes or Mailbox/changes call.
+const CHANGES_LIMIT: usize = 256;
+
+/// Wait before reopening a dropped push connection.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Keywords set on drafts; they are ours, so already seen.
+const DRAFT_KEYWORDS: &[&str] = &["$draft", "$seen"];
+
+/// A sender identity on the server.
+struct Identity {
+    id: String,
+    name: String,
+    email: String,
+}
+
+/// A message to create with Email/set.
+struct Outgoing {
+    from: Address,
+    to: Vec<Address>,
+    cc: Vec<Address>,
+    bcc: Vec<Address>,
+    reply_to: Vec<Address>,
+    subject: String,
+    text: String,
+    /// HTML alternative; with it the message is multipart/alternative.
+    html: Option<String>,
+    attachments: Vec<Attachment>,
+    /// Extra header fields as `(name, value)`.
+    headers: Vec<(String, String)>,
+    in_reply_to: Vec<String>,
+    references: Vec<String>,
+}
+
+/// Delayed release of a submission (RFC 4865 FUTURERELEASE). Until then
+/// the server holds the message and the submission can be cancelled.
+enum Hold {
+    /// HOLDFOR: seconds from submission.
+    For(u64),
+    /// HOLDUNTIL: a unix timestamp.
+    Until(i64),
+}
+
+/// A blob on the server to attach to an outgoing message.
+struct Attachment {
+    blob_id: String,
+    name: String,
+    content_type: String,
+}
+
+/// What one sync of the offline cache changed.
+#[derive(Default)]
+struct SyncCounts {
+    /// Emails were loaded from scratch rather than from changes.
+    full: bool,
+    mailboxes: usize,
+    created: usize,
+    updated: usize,
+    destroyed: usize,
+}
+
+/// Server and credentials of an account, as saved in
+/// `~/.config/appmesh/mail/<name>.json`. The push watcher makes its own
+/// connection from these on its own runtime.
+#[derive(Clone, Serialize, Deserialize)]
+struct Login {
+    url: String,
+    user: String,
+    pass: String,
+    /// JMAP accountId to work in instead of the session's primary mail
+    /// account, e.g. a shared mailbox.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    account_id: Option<String>,
+}
+
 /// JMAP mail port — full email via any JMAP server (Stalwart, Fastmail, etc.).
+///
+/// Each named account has its own connection and caches. Commands pick one
+/// with `account`; the port opened as `mail.<name>` defaults to `<name>`.
 pub struct MailPort {
-    rt: tokio::runtime::Runtime,
+    rt: Arc<tokio::runtime::Runtime>,
+    default_account: String,
+    /// Accounts used so far, by name.
+    accounts: Mutex<HashMap<String, Arc<MailAccount>>>,
+}
+
+/// One account of the mail port: its login, connection and caches.
+struct MailAccount {
+    rt: Arc<tokio::runtime::Runtime>,
+    name: String,
     client: Mutex<Option<Client>>,
+    login: Mutex<Option<Login>>,
+    mailboxes: Mutex<Option<MailboxCache>>,
+    /// Offline store, once `sync` has created one for the account.
+    /// Lock after `client` when both are needed.
+    cache: Mutex<Option<MailCache>>,
+}
+
+/// What name and role lookups need to know about a mailbox.
+struct MailboxInfo {
+    id: String,
+    name: String,
+    /// Lowercase role (`inbox`, `sent`, ...), empty if the mailbox has none.
+    role: String,
+}
+
+/// Mailboxes of the connected account as of Mailbox `state`.
+///
+/// Lookups trust the cache without a round trip. It is dropped whenever a
+/// response reports a different Mailbox state, and reloaded when a name is
+/// not found in it.
+struct MailboxCache {
+    state: String,
+    mailboxes: Vec<MailboxInfo>,
 }
 
 impl MailPort {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_account(DEFAULT_ACCOUNT)
+    }
+
+    /// The port opened as `mail.<name>`: commands without `account` use `name`.
+    pub fn with_account(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()?;
 
-        // Try connecting from env vars, but don't fail if missing
-        let client = match (
-            std::env::var("JMAP_URL"),
-            std::env::var("JMAP_USER"),
-            std::env::var("JMAP_PASS"),
-        ) {
-            (Ok(url), Ok(user), Ok(pass)) => {
-                match rt.block_on(Self::connect_inner(&url, &user, &pass)) {
-                    Ok(c) => Some(c),
-                    Err(e) => {
-                        eprintln!("mail port: auto-connect failed: {}", e);
-                        None
-                    }
-                }
-            }
-            _ => None,
+        let port = Self {
+            rt: Arc::new(rt),
+            default_account: name.to_string(),
+            accounts: Mutex::new(HashMap::new()),
         };
+        // Connect the default account up front, as before named accounts
+        port.account(name)?;
+        Ok(port)
+    }
+
+    /// The named account, opened (and connected, if it has a login) on first use.
+    fn account(&self, name: &str) -> Result<Arc<MailAccount>, PortError> {
+        let mut accounts = self.accounts.lock().map_err(PortError::msg)?;
+        if let Some(account) = accounts.get(name) {
+            return Ok(account.clone());
+        }
+        let login = MailAccount::configured_login(name)?;
+        let account = Arc::new(MailAccount::open(self.rt.clone(), name, login));
+        accounts.insert(name.to_string(), account.clone());
+        Ok(account)
+    }
+
+    /// Configured accounts and any others used so far, with their logins
+    /// (without passwords) and whether they are connected.
+    fn cmd_accounts(&self) -> PortResult {
+        let mut names = config::list_named(ACCOUNTS_KIND);
+        let opened: Vec<Arc<MailAccount>> = self
+            .accounts
+            .lock()
+            .map_err(PortError::msg)?
+            .values()
+            .cloned()
+            .collect();
+        for account in &opened {
+            if !names.contains(&account.name) {
+                names.push(account.name.clone());
+            }
+        }
+        if !names.iter().any(|n| n == DEFAULT_ACCOUNT) && MailAccount::env_login().is_some() {
+            names.push(DEFAULT_ACCOUNT.to_string());
+        }
+        names.sort();
+
+        let list = names
+            .into_iter()
+            .map(|name| {
+                let account = opened.iter().find(|a| a.name == name);
+                let login = match account {
+                    Some(account) => account.login.lock().ok().and_then(|l| l.clone()),
+                    None => MailAccount::configured_login(&name).ok().flatten(),
+                };
+                let connected = account
+                    .and_then(|a| a.client.lock().ok().map(|c| c.is_some()))
+                    .unwrap_or(false);
+
+                let mut map = HashMap::new();
+                map.insert(
+                    "default".into(),
+                    PortValue::Bool(name == self.default_account),
+                );
+                map.insert("name".into(), PortValue::String(name));
+                if let Some(login) = login {
+                    map.insert("url".into(), PortValue::String(login.url));
+                    map.insert("user".into(), PortValue::String(login.user));
+                    map.insert(
+                        "account_id".into(),
+                        login.account_id.map_or(PortValue::Null, PortValue::String),
+                    );
+                }
+                map.insert("connected".into(), PortValue::Bool(connected));
+                PortValue::Map(map)
+            })
+            .collect();
+        Ok(PortValue::List(list))
+    }
+}
 
-        Ok(Self {
+impl MailAccount {
+    /// Try connecting with the account's login, but don't fail without one
+    /// or when the server is unreachable. The offline cache opens either
+    /// way: offline is when it matters.
+    fn open(rt: Arc<tokio::runtime::Runtime>, name: &str, login: Option<Login>) -> Self {
+        let cache = login.as_ref().and_then(|l| Self::open_cache(l, false));
+        let client = login.as_ref().and_then(|l| {
+            rt.block_on(Self::connect_inner(l))
+                .map_err(|e| eprintln!("mail port: auto-connect of '{}' failed: {}", name, e))
+                .ok()
+        });
+
+        Self {
             rt,
+            name: name.to_string(),
             client: Mutex::new(client),
+            login: Mutex::new(login),
+            mailboxes: Mutex::new(None),
+            cache: Mutex::new(cache),
+        }
+    }
+
+    /// The saved login for `name`; for the default account, the
+    /// JMAP_URL/JMAP_USER/JMAP_PASS env vars if nothing is saved.
+    fn configured_login(name: &str) -> Result<Option<Login>, PortError> {
+        if config::list_named(ACCOUNTS_KIND).iter().any(|n| n == name) {
+            return config::load_named(ACCOUNTS_KIND, name)
+                .map(Some)
+                .map_err(PortError::msg);
+        }
+        Ok(if name == DEFAULT_ACCOUNT {
+            Self::env_login()
+        } else {
+            None
         })
     }
 
-    async fn connect_inner(
-        url: &str,
-        user: &str,
-        pass: &str,
-    ) -> Result<Client, Box<dyn std::error::Error>> {
-        // Extract hostname for follow_redirects (Stalwart 307s /.well-known/jmap → /jmap/session)
-        let host = url
-            .strip_prefix("https://")
+    fn env_login() -> Option<Login> {
+        Some(Login {
+            url: std::env::var("JMAP_URL").ok()?,
+            user: std::env::var("JMAP_USER").ok()?,
+            pass: std::env::var("JMAP_PASS").ok()?,
+            account_id: std::env::var("JMAP_ACCOUNT_ID").ok(),
+        })
+    }
+
+    async fn connect_inner(login: &Login) -> Result<Client, Box<dyn std::error::Error>> {
+        // Hostname for follow_redirects (Stalwart 307s /.well-known/jmap → /jmap/session)
+        let host = Self::url_host(&login.url);
+        let mut client = Client::new()
+            .credentials((login.user.as_str(), login.pass.as_str()))
+            .follow_redirects([host])
+            .connect(&login.url)
+            .await?;
+        if let Some(account_id) = &login.account_id {
+            if client.session().account(account_id).is_none() {
+                return Err(format!("account ID not in session: {}", account_id).into());
+            }
+            // Requests are built for the client's default account
+            client.set_default_account_id(account_id.as_str());
+        }
+        Ok(client)
+    }
+
+    fn url_host(url: &str) -> String {
+        url.strip_prefix("https://")
             .or_else(|| url.strip_prefix("http://"))
             .unwrap_or(url)
             .split('/')
@@ -63,98 +388,121 @@ impl MailPort {
             .split(':')
             .next()
             .unwrap_or("")
-            .to_string();
-        let client = Client::new()
-            .credentials((user, pass))
-            .follow_redirects([host])
-            .connect(url)
-            .await?;
-        Ok(client)
+            .to_string()
+    }
+
+    /// The account's offline cache: opened if a sync has created it, or
+    /// created when `create` is set.
+    fn open_cache(login: &Login, create: bool) -> Option<MailCache> {
+        let mut key = format!("{}@{}", login.user, Self::url_host(&login.url));
+        if let Some(account_id) = &login.account_id {
+            key = format!("{}-{}", key, account_id);
+        }
+        let path = mailcache::cache_path(&key);
+        if !create && !path.exists() {
+            return None;
+        }
+        match MailCache::open(&path) {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                eprintln!("mail port: cannot open cache {}: {}", path.display(), e);
+                None
+            }
+        }
     }
 
     fn require_client(&self) -> Result<std::sync::MutexGuard<'_, Option<Client>>, PortError> {
-        let guard = self.client.lock().map_err(|e| PortError {
-            code: -1,
-            message: format!("lock poisoned: {}", e),
-        })?;
+        let guard = self
+            .client
+            .lock()
+            .map_err(|e| PortError::msg(format!("lock poisoned: {}", e)))?;
         if guard.is_none() {
-            return Err(PortError {
-                code: -1,
-                message: "not connected — call 'connect' first or set JMAP_URL/JMAP_USER/JMAP_PASS env vars".into(),
-            });
+            return Err(PortError::msg(self.not_connected()));
         }
         Ok(guard)
     }
 
-    fn port_err(msg: impl std::fmt::Display) -> PortError {
-        PortError {
-            code: -1,
-            message: msg.to_string(),
+    fn not_connected(&self) -> String {
+        if self.name == DEFAULT_ACCOUNT {
+            NOT_CONNECTED.into()
+        } else {
+            format!(
+                "account '{}' not connected — call 'connect' first or save it to {}",
+                self.name,
+                config::kind_dir(ACCOUNTS_KIND)
+                    .join(format!("{}.json", self.name))
+                    .display()
+            )
         }
     }
 
     // --- Phase 1: Read-only commands ---
 
     fn cmd_connect(&self, args: &HashMap<String, String>) -> PortResult {
-        let url = args
-            .get("url")
-            .cloned()
-            .or_else(|| std::env::var("JMAP_URL").ok())
-            .ok_or_else(|| PortError {
-                code: -1,
-                message: "missing 'url' argument and JMAP_URL not set".into(),
-            })?;
-        let user = args
-            .get("user")
-            .cloned()
-            .or_else(|| std::env::var("JMAP_USER").ok())
-            .ok_or_else(|| PortError {
-                code: -1,
-                message: "missing 'user' argument and JMAP_USER not set".into(),
-            })?;
-        let pass = args
-            .get("pass")
-            .cloned()
-            .or_else(|| std::env::var("JMAP_PASS").ok())
-            .ok_or_else(|| PortError {
-                code: -1,
-                message: "missing 'pass' argument and JMAP_PASS not set".into(),
-            })?;
+        // Missing arguments fall back to the account's saved login (or env vars)
+        let configured = self.login.lock().map_err(PortError::msg)?.clone();
+        let arg = |name: &str, saved: Option<&String>| {
+            args.get(name).or(saved).cloned().ok_or_else(|| {
+                PortError::msg(format!(
+                    "missing '{}' argument and none configured for account '{}'",
+                    name, self.name
+                ))
+            })
+        };
+        let login = Login {
+            url: arg("url", configured.as_ref().map(|l| &l.url))?,
+            user: arg("user", configured.as_ref().map(|l| &l.user))?,
+            pass: arg("pass", configured.as_ref().map(|l| &l.pass))?,
+            account_id: args
+                .get("account_id")
+                .cloned()
+                .or_else(|| configured.and_then(|l| l.account_id)),
+        };
 
         let client = self
             .rt
-            .block_on(Self::connect_inner(&url, &user, &pass))
-            .map_err(|e| Self::port_err(format!("connect failed: {}", e)))?;
+            .block_on(Self::connect_inner(&login))
+            .map_err(|e| PortError::msg(format!("connect failed: {}", e)))?;
 
         let account_id = client.default_account_id().to_string();
 
-        // Persist credentials so subsequent stateless port opens auto-connect
-        std::env::set_var("JMAP_URL", &url);
-        std::env::set_var("JMAP_USER", &user);
-        std::env::set_var("JMAP_PASS", &pass);
+        // Persist credentials so later port opens connect this account
+        let saved = if bool_arg(args, "save", false) {
+            // It holds the password
+            let path = config::save_named_private(ACCOUNTS_KIND, &self.name, &login)
+                .map_err(PortError::msg)?;
+            format!(", saved to {}", path.display())
+        } else {
+            String::new()
+        };
 
-        let mut guard = self.client.lock().map_err(|e| Self::port_err(e))?;
+        let mut guard = self.client.lock().map_err(PortError::msg)?;
         *guard = Some(client);
+        // Mailbox IDs belong to the previous account
+        *self.mailboxes.lock().map_err(PortError::msg)? = None;
+        *self.cache.lock().map_err(PortError::msg)? = Self::open_cache(&login, false);
+        let (url, user) = (login.url.clone(), login.user.clone());
+        *self.login.lock().map_err(PortError::msg)? = Some(login);
 
         Ok(PortValue::String(format!(
-            "connected to {} as {} (account: {})",
-            url, user, account_id
+            "connected to {} as {} (account: {}){}",
+            url, user, account_id, saved
         )))
     }
 
     fn cmd_status(&self) -> PortResult {
-        let guard = self.client.lock().map_err(|e| Self::port_err(e))?;
+        let guard = self.client.lock().map_err(PortError::msg)?;
 
         match guard.as_ref() {
             Some(client) => {
                 let url = client.session_url();
                 let account_id = client.default_account_id();
                 Ok(PortValue::String(format!(
-                    "connected — session: {}, account: {}",
-                    url, account_id
+                    "{}: connected — session: {}, account: {}",
+                    self.name, url, account_id
                 )))
             }
-            None => Ok(PortValue::String("disconnected".into())),
+            None => Ok(PortValue::String(format!("{}: disconnected", self.name))),
         }
     }
 
@@ -162,20 +510,12 @@ impl MailPort {
         let guard = self.require_client()?;
         let client = guard.as_ref().unwrap();
 
-        let response = self
-            .rt
-            .block_on(client.mailbox_query(
-                None::<mailbox::query::Filter>,
-                None::<Vec<query::Comparator<mailbox::query::Comparator>>>,
-            ))
-            .map_err(|e| Self::port_err(format!("mailbox_query failed: {}", e)))?;
+        // Always fetched fresh: the counts are the point of this command
+        let list = self.load_mailboxes(client)?;
 
-        let mut mailboxes = Vec::new();
-        for id in response.ids() {
-            if let Ok(Some(mb)) = self
-                .rt
-                .block_on(client.mailbox_get(id, None::<Vec<mailbox::Property>>))
-            {
+        let mailboxes = list
+            .iter()
+            .map(|mb| {
                 let mut map = HashMap::new();
                 map.insert("id".into(), PortValue::String(mb.id().unwrap_or("").into()));
                 map.insert(
@@ -188,61 +528,42 @@ impl MailPort {
                 );
                 map.insert("total".into(), PortValue::Int(mb.total_emails() as i64));
                 map.insert("unread".into(), PortValue::Int(mb.unread_emails() as i64));
-                mailboxes.push(PortValue::Map(map));
-            }
-        }
+                PortValue::Map(map)
+            })
+            .collect();
 
         Ok(PortValue::List(mailboxes))
     }
 
     fn cmd_query(&self, args: &HashMap<String, String>) -> PortResult {
-        let guard = self.require_client()?;
-        let client = guard.as_ref().unwrap();
-
-        let limit: usize = args
-            .get("limit")
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(20);
-
         let mailbox_name = args.get("mailbox").map(|s| s.as_str()).unwrap_or("Inbox");
+        if let Some(page) = self.local_page(args, Some(mailbox_name).filter(|m| *m != "*"), None)? {
+            return Ok(page);
+        }
 
-        // Find the mailbox ID by name or role
-        let mailbox_id = self.find_mailbox_id(client, mailbox_name)?;
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
 
-        let filter = email::query::Filter::in_mailbox(&mailbox_id);
-        let sort = vec![email::query::Comparator::received_at()];
+        let mut email_query = self.email_query(client, args)?;
 
-        let response = self
-            .rt
-            .block_on(client.email_query(Some(filter), Some(sort)))
-            .map_err(|e| Self::port_err(format!("email_query failed: {}", e)))?;
-
-        let ids = response.ids();
-        let count = ids.len().min(limit);
-        let mut emails = Vec::new();
-
-        let properties = [
-            email::Property::Id,
-            email::Property::Subject,
-            email::Property::From,
-            email::Property::ReceivedAt,
-            email::Property::Preview,
-        ];
-
-        for id in &ids[..count] {
-            if let Ok(Some(msg)) = self
-                .rt
-                .block_on(client.email_get(id, Some(properties.clone())))
-            {
-                emails.push(self.email_to_summary(&msg));
-            }
+        // "*" searches every mailbox, like search
+        if mailbox_name != "*" {
+            // Find the mailbox ID by name or role
+            let mailbox_id = self.find_mailbox_id(client, mailbox_name)?;
+            email_query
+                .conditions
+                .push(email::query::Filter::in_mailbox(&mailbox_id));
         }
 
-        Ok(PortValue::List(emails))
+        let page = self.query_summaries(client, email_query)?;
+        Ok(Self::page_value(page))
     }
 
     fn cmd_read(&self, args: &HashMap<String, String>) -> PortResult {
-        let id = args.get("id").ok_or_else(|| Self::port_err("missing 'id' argument"))?;
+        let id = &required_arg(args, "id")?;
+        if let Some(cached) = self.local_read(id)? {
+            return Ok(cached);
+        }
 
         let guard = self.require_client()?;
         let client = guard.as_ref().unwrap();
@@ -270,8 +591,8 @@ impl MailPort {
                     .await
                     .map(|mut r| r.take_list().pop())
             })
-            .map_err(|e| Self::port_err(format!("email_get failed: {}", e)))?
-            .ok_or_else(|| Self::port_err(format!("email not found: {}", id)))?;
+            .map_err(|e| PortError::msg(format!("email_get failed: {}", e)))?
+            .ok_or_else(|| PortError::msg(format!("email not found: {}", id)))?;
 
         let mut map = HashMap::new();
         map.insert("id".into(), PortValue::String(msg.id().unwrap_or("").into()));
@@ -279,8 +600,8 @@ impl MailPort {
             "subject".into(),
             PortValue::String(msg.subject().unwrap_or("").into()),
         );
-        map.insert("from".into(), self.addresses_to_value(msg.from()));
-        map.insert("to".into(), self.addresses_to_value(msg.to()));
+        map.insert("from".into(), Self::addresses_to_value(msg.from()));
+        map.insert("to".into(), Self::addresses_to_value(msg.to()));
         map.insert(
             "date".into(),
             PortValue::String(
@@ -296,6 +617,9 @@ impl MailPort {
 
         // Extract body text from text_body parts + body_values
         let body = self.extract_body_text(&msg);
+        if let Ok(Some(cache)) = self.cache.lock().as_deref() {
+            let _ = cache.set_body(id, &body);
+        }
         map.insert("body".into(), PortValue::String(body));
 
         let keywords: Vec<PortValue> = msg
@@ -308,15 +632,72 @@ impl MailPort {
         Ok(PortValue::Map(map))
     }
 
+    /// The conversation an email belongs to, oldest first. Email/get for its
+    /// thread ID, Thread/get and Email/get of the members in one request.
+    fn cmd_thread(&self, args: &HashMap<String, String>) -> PortResult {
+        let id = &required_arg(args, "id")?;
+
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
+
+        let (thread, emails) = self
+            .rt
+            .block_on(async {
+                let mut request = client.build();
+                let get_request = request.get_email().ids([id.as_str()]);
+                get_request.properties([email::Property::ThreadId]);
+                let thread_ids = get_request.result_reference(email::Property::ThreadId);
+                let get_threads = request.get_thread().ids_ref(thread_ids);
+                let member_ids = get_threads.result_reference(thread::Property::EmailIds);
+                request
+                    .get_email()
+                    .ids_ref(member_ids)
+                    .properties(SUMMARY_PROPERTIES);
+
+                let mut responses = request.send().await?.unwrap_method_responses();
+                let emails = responses
+                    .pop()
+                    .map(|r| r.unwrap_get_email())
+                    .transpose()?
+                    .map(|mut r| r.take_list())
+                    .unwrap_or_default();
+                let thread = responses
+                    .pop()
+                    .map(|r| r.unwrap_get_thread())
+                    .transpose()?
+                    .and_then(|mut r| r.take_list().pop());
+                Ok::<_, jmap_client::Error>((thread, emails))
+            })
+            .map_err(|e| PortError::msg(format!("thread query failed: {}", e)))?;
+        let thread = thread.ok_or_else(|| PortError::msg(format!("email not found: {}", id)))?;
+
+        // Thread/get lists members oldest first; Email/get order is unspecified
+        let summaries: Vec<PortValue> = thread
+            .email_ids()
+            .iter()
+            .filter_map(|member| emails.iter().find(|e| e.id() == Some(member.as_str())))
+            .map(Self::email_to_summary)
+            .collect();
+
+        let mut map = HashMap::new();
+        map.insert(
+            "thread_id".into(),
+            PortValue::String(thread.id().into()),
+        );
+        map.insert("count".into(), PortValue::Int(summaries.len() as i64));
+        map.insert("emails".into(), PortValue::List(summaries));
+        Ok(PortValue::Map(map))
+    }
+
     fn cmd_mark_read(&self, args: &HashMap<String, String>) -> PortResult {
-        let id = args.get("id").ok_or_else(|| Self::port_err("missing 'id' argument"))?;
+        let id = &required_arg(args, "id")?;
 
         let guard = self.require_client()?;
         let client = guard.as_ref().unwrap();
 
         self.rt
             .block_on(client.email_set_keyword(id, "$seen", true))
-            .map_err(|e| Self::port_err(format!("email_set_keyword failed: {}", e)))?;
+            .map_err(|e| PortError::msg(format!("email_set_keyword failed: {}", e)))?;
 
         Ok(PortValue::String(format!("marked {} as read", id)))
     }
@@ -337,7 +718,7 @@ impl MailPort {
                     .await
                     .map(|mut r| r.take_list())
             })
-            .map_err(|e| Self::port_err(format!("get_identity failed: {}", e)))?;
+            .map_err(|e| PortError::msg(format!("get_identity failed: {}", e)))?;
 
         let mut list = Vec::new();
         for ident in &identities {
@@ -358,268 +739,655 @@ impl MailPort {
     }
 
     fn cmd_send(&self, args: &HashMap<String, String>) -> PortResult {
-        let to = args.get("to").ok_or_else(|| Self::port_err("missing 'to' argument"))?;
-        let subject = args
-            .get("subject")
-            .ok_or_else(|| Self::port_err("missing 'subject' argument"))?;
-        let body = args
-            .get("body")
-            .ok_or_else(|| Self::port_err("missing 'body' argument"))?;
+        let subject = &required_arg(args, "subject")?;
+        if !args.contains_key("body") && !args.contains_key("html") {
+            return Err(PortError::msg("missing 'body' argument"));
+        }
+        let hold = Self::hold_arg(args)?;
 
         let guard = self.require_client()?;
         let client = guard.as_ref().unwrap();
 
-        // Get first identity
-        let (identity_id, from_email) = self.find_first_identity(client)?;
-
-        let from = args.get("from").map(|s| s.as_str()).unwrap_or(&from_email);
-
-        // Find Sent mailbox
-        let sent_id = self.find_mailbox_id(client, "Sent")?;
-
-        // Create email via Email/set with structured properties
-        let email_id = self.create_email(client, from, to, subject, body, &sent_id, None, None)?;
-
-        // Submit for delivery
-        let submission = self
-            .rt
-            .block_on(client.email_submission_create(&email_id, &identity_id))
-            .map_err(|e| Self::port_err(format!("email_submission_create failed: {}", e)))?;
+        let identities = self.load_identities(client)?;
+        let identity = Self::pick_identity(&identities, args.get("from").map(|s| s.as_str()))?;
+        let mut msg = self.outgoing(client, args, identity)?;
+        if msg.to.is_empty() {
+            return Err(PortError::msg("missing 'to' argument"));
+        }
+        msg.subject = subject.clone();
 
-        let sub_id = submission.id().unwrap_or("unknown");
+        let sub_id = self.submit(client, &msg, identity, hold.as_ref())?;
         Ok(PortValue::String(format!(
-            "sent to {} (submission: {})",
-            to, sub_id
+            "{} to {} (submission: {})",
+            Self::sent_label(hold.as_ref()),
+            Self::address_list(&msg.to),
+            sub_id
         )))
     }
 
-    fn cmd_reply(&self, args: &HashMap<String, String>) -> PortResult {
-        let id = args.get("id").ok_or_else(|| Self::port_err("missing 'id' argument"))?;
-        let body = args
-            .get("body")
-            .ok_or_else(|| Self::port_err("missing 'body' argument"))?;
+    /// Reply to the sender (Reply-To if set), or with `all` also to every
+    /// other recipient except ourselves.
+    fn cmd_reply(&self, args: &HashMap<String, String>, all: bool) -> PortResult {
+        let id = &required_arg(args, "id")?;
+        if !args.contains_key("body") && !args.contains_key("html") {
+            return Err(PortError::msg("missing 'body' argument"));
+        }
+        let quote = bool_arg(args, "quote", true);
+        let hold = Self::hold_arg(args)?;
 
         let guard = self.require_client()?;
         let client = guard.as_ref().unwrap();
 
-        // Fetch original email headers
-        let original = self
-            .rt
-            .block_on(async {
-                let mut request = client.build();
-                let get_request = request.get_email().ids([id.as_str()]);
-                get_request.properties([
-                    email::Property::Id,
-                    email::Property::Subject,
-                    email::Property::From,
-                    email::Property::To,
-                    email::Property::MessageId,
-                    email::Property::InReplyTo,
-                    email::Property::References,
-                ]);
-                request
-                    .send_single::<EmailGetResponse>()
-                    .await
-                    .map(|mut r| r.take_list().pop())
-            })
-            .map_err(|e| Self::port_err(format!("email_get failed: {}", e)))?
-            .ok_or_else(|| Self::port_err(format!("email not found: {}", id)))?;
-
-        // Get identity for From header
-        let (identity_id, from_email) = self.find_first_identity(client)?;
-
-        // Build reply headers
-        let orig_from = original
-            .from()
-            .and_then(|f| f.first())
-            .map(|a| a.email().to_string())
-            .unwrap_or_default();
-        let orig_subject = original.subject().unwrap_or("").to_string();
-        let reply_subject = if orig_subject.to_lowercase().starts_with("re:") {
-            orig_subject
-        } else {
-            format!("Re: {}", orig_subject)
+        let original = self.fetch_original(client, id)?;
+        let identities = self.load_identities(client)?;
+        let own = |a: &Address| {
+            identities
+                .iter()
+                .any(|i| i.email.eq_ignore_ascii_case(&a.email))
         };
 
-        // Find Sent mailbox
-        let sent_id = self.find_mailbox_id(client, "Sent")?;
-
-        // Build reply In-Reply-To and References lists (without angle brackets for JMAP)
-        let in_reply_to_ids: Option<Vec<String>> = original
-            .message_id()
-            .and_then(|ids| ids.first())
-            .map(|id| vec![id.to_string()]);
+        let orig_from = Self::addresses(original.from());
+        let orig_to = Self::addresses(original.to());
+        let orig_cc = Self::addresses(original.cc());
 
-        let reference_ids: Option<Vec<String>> = {
-            let mut refs: Vec<String> = original
-                .references()
-                .unwrap_or(&[])
-                .iter()
-                .map(|r| r.to_string())
-                .collect();
-            if let Some(msg_id) = original.message_id().and_then(|ids| ids.first()) {
-                refs.push(msg_id.to_string());
+        // Answer from the identity the message was sent to, unless told otherwise
+        let recipient = orig_to.iter().chain(&orig_cc).find(|a| own(a));
+        let from = args
+            .get("from")
+            .map(|s| s.as_str())
+            .or(recipient.map(|a| a.email.as_str()));
+        let identity = Self::pick_identity(&identities, from)?;
+        let mut msg = self.outgoing(client, args, identity)?;
+
+        // Reply-To wins over From; replying to our own message goes to its recipients
+        let mut to = Self::addresses(original.reply_to());
+        if to.is_empty() {
+            to = orig_from.clone();
+        }
+        if !to.is_empty() && to.iter().all(own) {
+            to = orig_to.clone();
+        }
+        let mut cc = Vec::new();
+        if all {
+            for a in orig_to.iter().chain(&orig_cc).chain(&orig_from) {
+                let seen = to
+                    .iter()
+                    .chain(&cc)
+                    .any(|b: &Address| b.email.eq_ignore_ascii_case(&a.email));
+                if !seen && !own(a) {
+                    cc.push(a.clone());
+                }
             }
-            if refs.is_empty() { None } else { Some(refs) }
-        };
+        }
+        to.append(&mut msg.to);
+        cc.append(&mut msg.cc);
+        msg.to = to;
+        msg.cc = cc;
+        if msg.to.is_empty() {
+            return Err(PortError::msg("original message has no sender to reply to"));
+        }
 
-        // Create reply via Email/set
-        let email_id = self.create_email(
-            client,
-            &from_email,
-            &orig_from,
-            &reply_subject,
-            body,
-            &sent_id,
-            in_reply_to_ids.as_deref(),
-            reference_ids.as_deref(),
-        )?;
+        msg.subject = compose::reply_subject(original.subject().unwrap_or(""));
+
+        if quote {
+            let attribution = compose::attribution(original.received_at(), orig_from.first());
+            let text = self.extract_body_text(&original);
+            msg.text = format!(
+                "{}\n\n{}\n{}",
+                msg.text.trim_end(),
+                attribution,
+                compose::quote(&text)
+            );
+            if let Some(html) = &msg.html {
+                msg.html = Some(format!(
+                    "{}<br><br>{}<blockquote>{}</blockquote>",
+                    html,
+                    compose::escape_html(&attribution),
+                    compose::escape_html(&text).replace('\n', "<br>")
+                ));
+            }
+        }
 
-        // Submit for delivery
-        let submission = self
-            .rt
-            .block_on(client.email_submission_create(&email_id, &identity_id))
-            .map_err(|e| Self::port_err(format!("email_submission_create failed: {}", e)))?;
+        // In-Reply-To and References (without angle brackets for JMAP)
+        if let Some(msg_id) = original.message_id().and_then(|ids| ids.first()) {
+            msg.in_reply_to = vec![msg_id.to_string()];
+        }
+        msg.references = original
+            .references()
+            .unwrap_or(&[])
+            .iter()
+            .map(|r| r.to_string())
+            .chain(msg.in_reply_to.iter().cloned())
+            .collect();
 
-        let sub_id = submission.id().unwrap_or("unknown");
+        let sub_id = self.submit(client, &msg, identity, hold.as_ref())?;
         Ok(PortValue::String(format!(
-            "replied to {} (submission: {})",
-            id, sub_id
+            "reply to {} {} (submission: {})",
+            id,
+            Self::sent_label(hold.as_ref()),
+            sub_id
         )))
     }
 
-    // --- Phase 3: Mail Management ---
-
-    fn cmd_move(&self, args: &HashMap<String, String>) -> PortResult {
-        let id = args.get("id").ok_or_else(|| Self::port_err("missing 'id' argument"))?;
-        let mailbox = args
-            .get("mailbox")
-            .ok_or_else(|| Self::port_err("missing 'mailbox' argument"))?;
+    /// Forward inline (the original's text below ours, its attachments
+    /// carried over) or as a message/rfc822 attachment.
+    fn cmd_forward(&self, args: &HashMap<String, String>) -> PortResult {
+        let id = &required_arg(args, "id")?;
+        let as_attachment = match args.get("mode").map(|s| s.as_str()).unwrap_or("inline") {
+            "inline" => false,
+            "attachment" => true,
+            other => {
+                return Err(PortError::msg(format!(
+                    "invalid 'mode': {} (expected inline or attachment)",
+                    other
+                )))
+            }
+        };
+        let hold = Self::hold_arg(args)?;
 
         let guard = self.require_client()?;
         let client = guard.as_ref().unwrap();
 
-        let mailbox_id = self.find_mailbox_id(client, mailbox)?;
-
-        self.rt
-            .block_on(client.email_set_mailboxes(id, [&mailbox_id]))
-            .map_err(|e| Self::port_err(format!("email_set_mailboxes failed: {}", e)))?;
-
-        Ok(PortValue::String(format!("moved {} to {}", id, mailbox)))
-    }
-
-    fn cmd_delete(&self, args: &HashMap<String, String>) -> PortResult {
-        let id = args.get("id").ok_or_else(|| Self::port_err("missing 'id' argument"))?;
-        let permanent = args
-            .get("permanent")
-            .map(|s| s == "true" || s == "1")
-            .unwrap_or(false);
-
-        let guard = self.require_client()?;
-        let client = guard.as_ref().unwrap();
+        let original = self.fetch_original(client, id)?;
+        let identities = self.load_identities(client)?;
+        let identity = Self::pick_identity(&identities, args.get("from").map(|s| s.as_str()))?;
+        let mut msg = self.outgoing(client, args, identity)?;
+        if msg.to.is_empty() {
+            return Err(PortError::msg("missing 'to' argument"));
+        }
 
-        if permanent {
-            self.rt
-                .block_on(client.email_destroy(id))
-                .map_err(|e| Self::port_err(format!("email_destroy failed: {}", e)))?;
-            Ok(PortValue::String(format!("deleted {}", id)))
+        let orig_subject = original.subject().unwrap_or("").to_string();
+        msg.subject = compose::forward_subject(&orig_subject);
+
+        if as_attachment {
+            let blob_id = original
+                .blob_id()
+                .ok_or_else(|| PortError::msg(format!("email has no blob: {}", id)))?;
+            let name = if orig_subject.is_empty() { "message" } else { orig_subject.as_str() };
+            msg.attachments.insert(
+                0,
+                Attachment {
+                    blob_id: blob_id.to_string(),
+                    name: format!("{}.eml", name.replace(['/', '\\'], "_")),
+                    content_type: "message/rfc822".into(),
+                },
+            );
         } else {
-            let trash_id = self.find_mailbox_id(client, "Trash")?;
-            self.rt
-                .block_on(client.email_set_mailboxes(id, [&trash_id]))
-                .map_err(|e| Self::port_err(format!("email_set_mailboxes failed: {}", e)))?;
-            Ok(PortValue::String(format!("moved {} to Trash", id)))
+            // The note (if any) above the original's headers and text
+            let mut header = String::from("---------- Forwarded message ----------\n");
+            let from = Self::addresses(original.from());
+            header.push_str(&format!("From: {}\n", Self::address_list(&from)));
+            if let Some(ts) = original.received_at() {
+                header.push_str(&format!("Date: {}\n", compose::format_date(ts)));
+            }
+            header.push_str(&format!("Subject: {}\n", orig_subject));
+            let to = Self::addresses(original.to());
+            header.push_str(&format!("To: {}\n", Self::address_list(&to)));
+            let cc = Self::addresses(original.cc());
+            if !cc.is_empty() {
+                header.push_str(&format!("Cc: {}\n", Self::address_list(&cc)));
+            }
+            let forwarded = format!("{}\n{}", header, self.extract_body_text(&original));
+            let note = msg.text.trim_end();
+            msg.text = if note.is_empty() {
+                forwarded.clone()
+            } else {
+                format!("{}\n\n{}", note, forwarded)
+            };
+            if let Some(html) = &msg.html {
+                msg.html = Some(format!(
+                    "{}<br><br><pre>{}</pre>",
+                    html,
+                    compose::escape_html(&forwarded)
+                ));
+            }
+
+            // Carry the original attachments over by blob ID; no re-upload needed
+            msg.attachments.append(&mut Self::carried_attachments(&original));
         }
+
+        let sub_id = self.submit(client, &msg, identity, hold.as_ref())?;
+        Ok(PortValue::String(format!(
+            "forward of {} {} to {} (submission: {})",
+            id,
+            Self::sent_label(hold.as_ref()),
+            Self::address_list(&msg.to),
+            sub_id
+        )))
     }
 
-    fn cmd_flag(&self, args: &HashMap<String, String>) -> PortResult {
-        let id = args.get("id").ok_or_else(|| Self::port_err("missing 'id' argument"))?;
+    // --- Drafts ---
 
+    /// Store a message in Drafts for review instead of sending it.
+    fn cmd_draft_save(&self, args: &HashMap<String, String>) -> PortResult {
         let guard = self.require_client()?;
         let client = guard.as_ref().unwrap();
 
-        self.rt
-            .block_on(client.email_set_keyword(id, "$flagged", true))
-            .map_err(|e| Self::port_err(format!("email_set_keyword failed: {}", e)))?;
+        let identities = self.load_identities(client)?;
+        let identity = Self::pick_identity(&identities, args.get("from").map(|s| s.as_str()))?;
+        let msg = self.outgoing(client, args, identity)?;
 
-        Ok(PortValue::String(format!("flagged {}", id)))
+        let drafts_id = self.find_mailbox_id(client, "Drafts")?;
+        let email_id = self.create_email(client, &msg, &drafts_id, DRAFT_KEYWORDS)?;
+        Ok(Self::draft_value(&email_id))
     }
 
-    fn cmd_unflag(&self, args: &HashMap<String, String>) -> PortResult {
-        let id = args.get("id").ok_or_else(|| Self::port_err("missing 'id' argument"))?;
+    /// JMAP emails are immutable, so an edit creates a new draft from the
+    /// old one with the given fields replaced, then destroys the old one.
+    /// Attachments carry over; extra headers must be given again.
+    fn cmd_draft_update(&self, args: &HashMap<String, String>) -> PortResult {
+        let id = &required_arg(args, "id")?;
 
         let guard = self.require_client()?;
         let client = guard.as_ref().unwrap();
 
+        let draft = self.fetch_draft(client, id)?;
+        let draft_from = Self::addresses(draft.from());
+        let identities = self.load_identities(client)?;
+        let from = args
+            .get("from")
+            .map(|s| s.as_str())
+            .or(draft_from.first().map(|a| a.email.as_str()));
+        let identity = Self::pick_identity(&identities, from)?;
+        let mut msg = self.outgoing(client, args, identity)?;
+
+        let keep = |name: &str| !args.contains_key(name);
+        if keep("from") {
+            if let Some(from) = draft_from.into_iter().next() {
+                msg.from = from;
+            }
+        }
+        if keep("to") {
+            msg.to = Self::addresses(draft.to());
+        }
+        if keep("cc") {
+            msg.cc = Self::addresses(draft.cc());
+        }
+        if keep("bcc") {
+            msg.bcc = Self::addresses(draft.bcc());
+        }
+        if keep("reply_to") {
+            msg.reply_to = Self::addresses(draft.reply_to());
+        }
+        if keep("subject") {
+            msg.subject = draft.subject().unwrap_or("").to_string();
+        }
+        if keep("body") && keep("html") {
+            msg.text = self.extract_body_text(&draft);
+            msg.html = Self::extract_body_html(&draft);
+        }
+        let mut attachments = Self::carried_attachments(&draft);
+        attachments.append(&mut msg.attachments);
+        msg.attachments = attachments;
+        msg.in_reply_to = draft
+            .in_reply_to()
+            .unwrap_or(&[])
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        msg.references = draft
+            .references()
+            .unwrap_or(&[])
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let drafts_id = self.find_mailbox_id(client, "Drafts")?;
+        let email_id = self.create_email(client, &msg, &drafts_id, DRAFT_KEYWORDS)?;
         self.rt
-            .block_on(client.email_set_keyword(id, "$flagged", false))
-            .map_err(|e| Self::port_err(format!("email_set_keyword failed: {}", e)))?;
+            .block_on(client.email_destroy(id))
+            .map_err(|e| PortError::msg(format!("email_destroy failed: {}", e)))?;
 
-        Ok(PortValue::String(format!("unflagged {}", id)))
+        Ok(Self::draft_value(&email_id))
     }
 
-    fn cmd_mark_unread(&self, args: &HashMap<String, String>) -> PortResult {
-        let id = args.get("id").ok_or_else(|| Self::port_err("missing 'id' argument"))?;
-
+    fn cmd_drafts(&self, args: &HashMap<String, String>) -> PortResult {
         let guard = self.require_client()?;
         let client = guard.as_ref().unwrap();
 
-        self.rt
-            .block_on(client.email_set_keyword(id, "$seen", false))
-            .map_err(|e| Self::port_err(format!("email_set_keyword failed: {}", e)))?;
+        let mut email_query = self.email_query(client, args)?;
+        let drafts_id = self.find_mailbox_id(client, "Drafts")?;
+        email_query
+            .conditions
+            .push(email::query::Filter::in_mailbox(&drafts_id));
 
-        Ok(PortValue::String(format!("marked {} as unread", id)))
+        let page = self.query_summaries(client, email_query)?;
+        Ok(Self::page_value(page))
     }
 
-    fn cmd_search(&self, args: &HashMap<String, String>) -> PortResult {
-        let text = args
-            .get("text")
-            .ok_or_else(|| Self::port_err("missing 'text' argument"))?;
-        let limit: usize = args
-            .get("limit")
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(20);
+    /// Submit a draft as is. On success the server moves it from Drafts to
+    /// Sent and clears `$draft`, so a failed send leaves the draft in place.
+    fn cmd_draft_send(&self, args: &HashMap<String, String>) -> PortResult {
+        let id = &required_arg(args, "id")?;
+        let hold = Self::hold_arg(args)?;
 
         let guard = self.require_client()?;
         let client = guard.as_ref().unwrap();
 
-        let filter = email::query::Filter::text(text);
-        let sort = vec![email::query::Comparator::received_at()];
-
-        let response = self
-            .rt
-            .block_on(client.email_query(Some(filter), Some(sort)))
-            .map_err(|e| Self::port_err(format!("email_query failed: {}", e)))?;
-
-        let ids = response.ids();
-        let count = ids.len().min(limit);
-        let mut emails = Vec::new();
-
-        let properties = [
-            email::Property::Id,
-            email::Property::Subject,
-            email::Property::From,
-            email::Property::ReceivedAt,
-            email::Property::Preview,
-        ];
-
-        for id in &ids[..count] {
-            if let Ok(Some(msg)) = self
-                .rt
-                .block_on(client.email_get(id, Some(properties.clone())))
-            {
-                emails.push(self.email_to_summary(&msg));
-            }
+        let draft = self.fetch_draft(client, id)?;
+        if !draft.keywords().contains(&"$draft") {
+            return Err(PortError::msg(format!("not a draft: {}", id)));
+        }
+        let recipients = [draft.to(), draft.cc(), draft.bcc()];
+        if recipients.iter().all(|r| r.unwrap_or(&[]).is_empty()) {
+            return Err(PortError::msg("draft has no recipients"));
         }
 
-        Ok(PortValue::List(emails))
-    }
-
-    fn cmd_attachment_list(&self, args: &HashMap<String, String>) -> PortResult {
-        let id = args.get("id").ok_or_else(|| Self::port_err("missing 'id' argument"))?;
+        let identities = self.load_identities(client)?;
+        let from = Self::addresses(draft.from());
+        let identity = Self::pick_identity(&identities, from.first().map(|a| a.email.as_str()))?;
 
-        let guard = self.require_client()?;
-        let client = guard.as_ref().unwrap();
+        let drafts_id = self.find_mailbox_id(client, "Drafts")?;
+        let sent_id = self.find_mailbox_id(client, "Sent")?;
+
+        let rcpt_to: Vec<Address> = recipients
+            .iter()
+            .flat_map(|r| Self::addresses(*r))
+            .collect();
+        let sub_id = self.submit_email(
+            client,
+            id,
+            identity,
+            &rcpt_to,
+            hold.as_ref(),
+            Some((&drafts_id, &sent_id)),
+        )?;
+
+        Ok(PortValue::String(format!(
+            "draft {} {} (submission: {})",
+            id,
+            Self::sent_label(hold.as_ref()),
+            sub_id
+        )))
+    }
+
+    fn draft_value(email_id: &str) -> PortValue {
+        let mut map = HashMap::new();
+        map.insert("id".into(), PortValue::String(email_id.to_string()));
+        PortValue::Map(map)
+    }
+
+    // --- Submissions ---
+
+    /// Recent submissions, newest first, with their undo and delivery status.
+    fn cmd_submissions(&self, args: &HashMap<String, String>) -> PortResult {
+        let limit: usize = match args.get("limit") {
+            Some(v) => Self::number_arg("limit", v)?,
+            None => 20,
+        };
+        let status = args
+            .get("status")
+            .map(|s| Self::undo_status_arg(s))
+            .transpose()?;
+
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
+
+        // Submissions, then the subject and recipients of their emails
+        let (submissions, emails) = self
+            .rt
+            .block_on(async {
+                let mut request = client.build();
+                let query = request.query_email_submission();
+                if let Some(status) = status {
+                    query.filter(email_submission::query::Filter::undo_status(status));
+                }
+                query
+                    .sort([email_submission::query::Comparator::sent_at().descending()])
+                    .limit(limit);
+                let ids = query.result_reference();
+                let get_request = request.get_email_submission().ids_ref(ids);
+                get_request.properties([
+                    email_submission::Property::Id,
+                    email_submission::Property::EmailId,
+                    email_submission::Property::SendAt,
+                    email_submission::Property::UndoStatus,
+                    email_submission::Property::DeliveryStatus,
+                ]);
+                let email_ids = get_request.result_reference(email_submission::Property::EmailId);
+                request.get_email().ids_ref(email_ids).properties([
+                    email::Property::Id,
+                    email::Property::Subject,
+                    email::Property::To,
+                ]);
+
+                let mut responses = request.send().await?.unwrap_method_responses();
+                let emails = responses
+                    .pop()
+                    .map(|r| r.unwrap_get_email())
+                    .transpose()?
+                    .map(|mut r| r.take_list())
+                    .unwrap_or_default();
+                let submissions = responses
+                    .pop()
+                    .map(|r| r.unwrap_get_email_submission())
+                    .transpose()?
+                    .map(|mut r| r.take_list())
+                    .unwrap_or_default();
+                Ok::<_, jmap_client::Error>((submissions, emails))
+            })
+            .map_err(|e| PortError::msg(format!("submission query failed: {}", e)))?;
+
+        let list = submissions
+            .iter()
+            .map(|sub| {
+                let email_id = sub.email_id().unwrap_or("");
+                let email = emails.iter().find(|e| e.id() == Some(email_id));
+                let mut map = HashMap::new();
+                map.insert("id".into(), PortValue::String(sub.id().unwrap_or("").into()));
+                map.insert("email_id".into(), PortValue::String(email_id.into()));
+                map.insert(
+                    "subject".into(),
+                    PortValue::String(email.and_then(|e| e.subject()).unwrap_or("").into()),
+                );
+                map.insert("to".into(), Self::addresses_to_value(email.and_then(|e| e.to())));
+                map.insert(
+                    "send_at".into(),
+                    PortValue::String(sub.send_at().map(|ts| ts.to_string()).unwrap_or_default()),
+                );
+                map.insert(
+                    "status".into(),
+                    PortValue::String(
+                        sub.undo_status()
+                            .map(|s| format!("{:?}", s).to_lowercase())
+                            .unwrap_or_default(),
+                    ),
+                );
+                // Per recipient: queued, yes, no or unknown, plus the SMTP reply
+                let delivery = sub
+                    .delivery_status()
+                    .map(|statuses| {
+                        statuses
+                            .iter()
+                            .map(|(rcpt, status)| {
+                                let mut entry = HashMap::new();
+                                entry.insert(
+                                    "delivered".into(),
+                                    PortValue::String(
+                                        format!("{:?}", status.delivered()).to_lowercase(),
+                                    ),
+                                );
+                                entry.insert(
+                                    "smtp_reply".into(),
+                                    PortValue::String(status.smtp_reply().into()),
+                                );
+                                (rcpt.clone(), PortValue::Map(entry))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                map.insert("delivery".into(), PortValue::Map(delivery));
+                PortValue::Map(map)
+            })
+            .collect();
+
+        Ok(PortValue::List(list))
+    }
+
+    /// Cancel a held submission and return its message to Drafts, where it
+    /// can be edited or sent again with `draft_send`.
+    fn cmd_cancel_send(&self, args: &HashMap<String, String>) -> PortResult {
+        let id = &required_arg(args, "id")?;
+
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
+
+        let submission = self
+            .rt
+            .block_on(client.email_submission_get(
+                id,
+                Some(vec![
+                    email_submission::Property::Id,
+                    email_submission::Property::EmailId,
+                    email_submission::Property::UndoStatus,
+                ]),
+            ))
+            .map_err(|e| PortError::msg(format!("email_submission_get failed: {}", e)))?
+            .ok_or_else(|| PortError::msg(format!("submission not found: {}", id)))?;
+        match submission.undo_status() {
+            Some(UndoStatus::Pending) => {}
+            other => {
+                return Err(PortError::msg(format!(
+                    "submission {} can no longer be cancelled (status: {})",
+                    id,
+                    other.map(|s| format!("{:?}", s).to_lowercase()).unwrap_or_default()
+                )))
+            }
+        }
+
+        self.rt
+            .block_on(client.email_submission_change_status(id, UndoStatus::Canceled))
+            .map_err(|e| PortError::msg(format!("email_submission_change_status failed: {}", e)))?;
+
+        let email_id = submission.email_id().unwrap_or("");
+        if email_id.is_empty() {
+            return Ok(PortValue::String(format!("cancelled {}", id)));
+        }
+        let drafts_id = self.find_mailbox_id(client, "Drafts")?;
+        self.rt
+            .block_on(async {
+                client.email_set_mailboxes(email_id, [&drafts_id]).await?;
+                client.email_set_keyword(email_id, "$draft", true).await
+            })
+            .map_err(|e| PortError::msg(format!("moving {} to Drafts failed: {}", email_id, e)))?;
+
+        Ok(PortValue::String(format!(
+            "cancelled {}; message {} is back in Drafts",
+            id, email_id
+        )))
+    }
+
+    fn undo_status_arg(value: &str) -> Result<UndoStatus, PortError> {
+        match value {
+            "pending" => Ok(UndoStatus::Pending),
+            "final" => Ok(UndoStatus::Final),
+            "canceled" | "cancelled" => Ok(UndoStatus::Canceled),
+            other => Err(PortError::msg(format!(
+                "invalid 'status': {} (expected pending, final or canceled)",
+                other
+            ))),
+        }
+    }
+
+    // --- Phase 3: Mail Management ---
+
+    fn cmd_move(&self, args: &HashMap<String, String>) -> PortResult {
+        let id = &required_arg(args, "id")?;
+        let mailbox = &required_arg(args, "mailbox")?;
+
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
+
+        let mailbox_id = self.find_mailbox_id(client, mailbox)?;
+
+        self.rt
+            .block_on(client.email_set_mailboxes(id, [&mailbox_id]))
+            .map_err(|e| PortError::msg(format!("email_set_mailboxes failed: {}", e)))?;
+
+        Ok(PortValue::String(format!("moved {} to {}", id, mailbox)))
+    }
+
+    fn cmd_delete(&self, args: &HashMap<String, String>) -> PortResult {
+        let id = &required_arg(args, "id")?;
+        let permanent = bool_arg(args, "permanent", false);
+
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
+
+        if permanent {
+            self.rt
+                .block_on(client.email_destroy(id))
+                .map_err(|e| PortError::msg(format!("email_destroy failed: {}", e)))?;
+            Ok(PortValue::String(format!("deleted {}", id)))
+        } else {
+            let trash_id = self.find_mailbox_id(client, "Trash")?;
+            self.rt
+                .block_on(client.email_set_mailboxes(id, [&trash_id]))
+                .map_err(|e| PortError::msg(format!("email_set_mailboxes failed: {}", e)))?;
+            Ok(PortValue::String(format!("moved {} to Trash", id)))
+        }
+    }
+
+    fn cmd_flag(&self, args: &HashMap<String, String>) -> PortResult {
+        let id = &required_arg(args, "id")?;
+
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
+
+        self.rt
+            .block_on(client.email_set_keyword(id, "$flagged", true))
+            .map_err(|e| PortError::msg(format!("email_set_keyword failed: {}", e)))?;
+
+        Ok(PortValue::String(format!("flagged {}", id)))
+    }
+
+    fn cmd_unflag(&self, args: &HashMap<String, String>) -> PortResult {
+        let id = &required_arg(args, "id")?;
+
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
+
+        self.rt
+            .block_on(client.email_set_keyword(id, "$flagged", false))
+            .map_err(|e| PortError::msg(format!("email_set_keyword failed: {}", e)))?;
+
+        Ok(PortValue::String(format!("unflagged {}", id)))
+    }
+
+    fn cmd_mark_unread(&self, args: &HashMap<String, String>) -> PortResult {
+        let id = &required_arg(args, "id")?;
+
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
+
+        self.rt
+            .block_on(client.email_set_keyword(id, "$seen", false))
+            .map_err(|e| PortError::msg(format!("email_set_keyword failed: {}", e)))?;
+
+        Ok(PortValue::String(format!("marked {} as unread", id)))
+    }
+
+    fn cmd_search(&self, args: &HashMap<String, String>) -> PortResult {
+        let text = &required_arg(args, "text")?;
+        if let Some(page) = self.local_page(args, None, Some(text))? {
+            return Ok(page);
+        }
+
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
+
+        let mut email_query = self.email_query(client, args)?;
+        email_query.conditions.push(email::query::Filter::text(text));
+
+        let page = self.query_summaries(client, email_query)?;
+        Ok(Self::page_value(page))
+    }
+
+    fn cmd_attachment_list(&self, args: &HashMap<String, String>) -> PortResult {
+        let id = &required_arg(args, "id")?;
+
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
 
         let msg = self
             .rt
@@ -627,8 +1395,8 @@ impl MailPort {
                 id,
                 Some([email::Property::Id, email::Property::Attachments]),
             ))
-            .map_err(|e| Self::port_err(format!("email_get failed: {}", e)))?
-            .ok_or_else(|| Self::port_err(format!("email not found: {}", id)))?;
+            .map_err(|e| PortError::msg(format!("email_get failed: {}", e)))?
+            .ok_or_else(|| PortError::msg(format!("email not found: {}", id)))?;
 
         let mut list = Vec::new();
         if let Some(attachments) = msg.attachments() {
@@ -657,7 +1425,7 @@ impl MailPort {
     fn cmd_attachment_download(&self, args: &HashMap<String, String>) -> PortResult {
         let blob_id = args
             .get("id")
-            .ok_or_else(|| Self::port_err("missing 'id' argument (blob_id)"))?;
+            .ok_or_else(|| PortError::msg("missing 'id' argument (blob_id)"))?;
         let name = args
             .get("name")
             .map(|s| s.as_str())
@@ -669,12 +1437,12 @@ impl MailPort {
         let bytes = self
             .rt
             .block_on(client.download(blob_id))
-            .map_err(|e| Self::port_err(format!("download failed: {}", e)))?;
+            .map_err(|e| PortError::msg(format!("download failed: {}", e)))?;
 
         // Write to temp directory
         let dir = std::path::Path::new("/tmp/appmesh-mail");
         std::fs::create_dir_all(dir)
-            .map_err(|e| Self::port_err(format!("mkdir failed: {}", e)))?;
+            .map_err(|e| PortError::msg(format!("mkdir failed: {}", e)))?;
 
         // Sanitize filename
         let safe_name: String = name
@@ -684,7 +1452,7 @@ impl MailPort {
         let path = dir.join(&safe_name);
 
         std::fs::write(&path, &bytes)
-            .map_err(|e| Self::port_err(format!("write failed: {}", e)))?;
+            .map_err(|e| PortError::msg(format!("write failed: {}", e)))?;
 
         Ok(PortValue::String(format!(
             "saved to {} ({} bytes)",
@@ -693,9 +1461,536 @@ impl MailPort {
         )))
     }
 
+    // --- Offline cache ---
+
+    /// Bring the offline cache up to date, creating it on first use.
+    fn cmd_sync(&self, args: &HashMap<String, String>) -> PortResult {
+        let limit = args
+            .get("limit")
+            .map(|v| Self::number_arg("limit", v))
+            .transpose()?
+            .unwrap_or(SYNC_LIMIT);
+        let reset = bool_arg(args, "reset", false);
+
+        let guard = self.require_client()?;
+        let client = guard.as_ref().unwrap();
+        let mut cache_guard = self.cache.lock().map_err(PortError::msg)?;
+        if cache_guard.is_none() {
+            let login = self
+                .login
+                .lock()
+                .map_err(PortError::msg)?
+                .clone()
+                .ok_or_else(|| PortError::msg(self.not_connected()))?;
+            *cache_guard = Some(
+                Self::open_cache(&login, true)
+                    .ok_or_else(|| PortError::msg("cannot open the mail cache"))?,
+            );
+        }
+        let cache = cache_guard.as_mut().unwrap();
+        if reset {
+            cache.clear().map_err(PortError::msg)?;
+        }
+
+        let counts = self.sync_cache(client, cache, limit)?;
+        let stats = cache.stats().map_err(PortError::msg)?;
+
+        let mut map = HashMap::new();
+        map.insert("full".into(), PortValue::Bool(counts.full));
+        map.insert("mailboxes".into(), PortValue::Int(counts.mailboxes as i64));
+        map.insert("created".into(), PortValue::Int(counts.created as i64));
+        map.insert("updated".into(), PortValue::Int(counts.updated as i64));
+        map.insert("destroyed".into(), PortValue::Int(counts.destroyed as i64));
+        map.insert("emails".into(), PortValue::Int(stats.emails));
+        Ok(PortValue::Map(map))
+    }
+
+    fn cmd_sync_status(&self) -> PortResult {
+        let guard = self.cache.lock().map_err(PortError::msg)?;
+        let mut map = HashMap::new();
+        let Some(cache) = guard.as_ref() else {
+            map.insert("enabled".into(), PortValue::Bool(false));
+            return Ok(PortValue::Map(map));
+        };
+
+        let stats = cache.stats().map_err(PortError::msg)?;
+        map.insert("enabled".into(), PortValue::Bool(true));
+        map.insert(
+            "path".into(),
+            PortValue::String(cache.path().display().to_string()),
+        );
+        map.insert("mailboxes".into(), PortValue::Int(stats.mailboxes));
+        map.insert("emails".into(), PortValue::Int(stats.emails));
+        map.insert("bodies".into(), PortValue::Int(stats.bodies));
+        for kind in ["Email", "Mailbox"] {
+            map.insert(
+                format!("{}_state", kind.to_lowercase()),
+                cache
+                    .state(kind)
+                    .map_or(PortValue::Null, |(state, _)| PortValue::String(state)),
+            );
+        }
+        // Zero after a change made through the port: the next lookup syncs
+        let synced_at = cache.state("Email").map(|(_, at)| at).filter(|at| *at > 0);
+        map.insert(
+            "synced_at".into(),
+            synced_at.map_or(PortValue::Null, PortValue::Int),
+        );
+        map.insert(
+            "age".into(),
+            synced_at.map_or(PortValue::Null, |at| PortValue::Int(mailcache::now() - at)),
+        );
+        Ok(PortValue::Map(map))
+    }
+
+    /// Answer a query or search from the cache, syncing first if it is stale
+    /// and the server is reachable. `None` when there is no synced cache, the
+    /// arguments need the server (`live`, anchors, collapsed threads), or the
+    /// server is up and the cache lacks some of the mailbox or the lookup is
+    /// a text search.
+    ///
+    /// A page served without the server is marked `partial` (no exact
+    /// `total`) when the cache lacks some of the mailbox, and `stale` when
+    /// the sync it was due failed (with `sync_error`) or could not be tried.
+    fn local_page(
+        &self,
+        args: &HashMap<String, String>,
+        mailbox: Option<&str>,
+        text: Option<&str>,
+    ) -> Result<Option<PortValue>, PortError> {
+        let live = bool_arg(args, "live", false);
+        if live
+            || ["anchor", "anchor_offset", "collapse_threads"]
+                .iter()
+                .any(|k| args.contains_key(*k))
+        {
+            return Ok(None);
+        }
+
+        let client_guard = self.client.lock().map_err(PortError::msg)?;
+        let mut cache_guard = self.cache.lock().map_err(PortError::msg)?;
+        let Some(cache) = cache_guard.as_mut() else {
+            return Ok(None);
+        };
+        let online = client_guard.is_some();
+        let fresh = cache
+            .state("Email")
+            .is_some_and(|(_, at)| mailcache::now() - at < SYNC_INTERVAL);
+        // Offline or failing, the cache still answers as of its last sync
+        let sync_error = match client_guard.as_ref() {
+            Some(client) if !fresh => self.sync_cache(client, cache, SYNC_LIMIT).err(),
+            _ => None,
+        };
+        drop(client_guard);
+        if cache.state("Email").is_none() {
+            return Ok(None);
+        }
+        let stale = !fresh && (!online || sync_error.is_some());
+
+        let mailboxes = cache.mailboxes().map_err(PortError::msg)?;
+        let mailbox_id = |name: &str| -> Result<String, PortError> {
+            let name_lower = name.to_lowercase();
+            mailboxes
+                .iter()
+                .find(|mb| {
+                    mb.name.to_lowercase() == name_lower || mb.role == name_lower || mb.id == name
+                })
+                .map(|mb| mb.id.clone())
+                .ok_or_else(|| PortError::msg(format!("mailbox not found: {}", name)))
+        };
+
+        let text_arg = |name: &str| args.get(name).cloned();
+        let local = LocalQuery {
+            in_mailbox: mailbox.map(mailbox_id).transpose()?,
+            not_in: args
+                .get("not_in")
+                .map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(mailbox_id)
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .transpose()?
+                .unwrap_or_default(),
+            text: text.map(str::to_string),
+            from: text_arg("from"),
+            to: text_arg("to"),
+            subject: text_arg("subject"),
+            before: args
+                .get("before")
+                .map(|v| Self::date_arg("before", v))
+                .transpose()?,
+            after: args
+                .get("after")
+                .map(|v| Self::date_arg("after", v))
+                .transpose()?,
+            has_attachment: args.get("has_attachment").map(|v| v == "true" || v == "1"),
+            keyword: text_arg("keyword"),
+            not_keyword: text_arg("not_keyword"),
+            min_size: args
+                .get("min_size")
+                .map(|v| Self::number_arg("min_size", v))
+                .transpose()?,
+            max_size: args
+                .get("max_size")
+                .map(|v| Self::number_arg("max_size", v))
+                .transpose()?,
+            sort: Self::parse_sort(
+                args.get("sort")
+                    .map(|s| s.as_str())
+                    .unwrap_or("received:desc"),
+            )?,
+            position: args
+                .get("position")
+                .map(|v| Self::number_arg("position", v))
+                .transpose()?
+                .unwrap_or(0),
+            limit: args
+                .get("limit")
+                .map(|v| Self::number_arg("limit", v))
+                .transpose()?
+                .unwrap_or(20),
+        };
+
+        // While the server can be asked, leave it what the cache may be
+        // missing (older mail past the sync limit) and full-text search,
+        // which it matches by its own rules rather than substrings
+        let complete = cache
+            .complete(local.in_mailbox.as_deref())
+            .map_err(PortError::msg)?;
+        if online && sync_error.is_none() && (!complete || local.text.is_some()) {
+            return Ok(None);
+        }
+
+        let (emails, total) = cache.query(&local).map_err(PortError::msg)?;
+        let mut page = Self::page_value(EmailPage {
+            emails: emails.iter().map(Self::cached_summary).collect(),
+            total: complete.then_some(total),
+            position: local.position,
+        });
+        if let PortValue::Map(map) = &mut page {
+            if !complete {
+                map.insert("partial".into(), PortValue::Bool(true));
+            }
+            if stale {
+                map.insert("stale".into(), PortValue::Bool(true));
+            }
+            if let Some(e) = sync_error {
+                map.insert("sync_error".into(), PortValue::String(e.message));
+            }
+        }
+        Ok(Some(page))
+    }
+
+    /// `read` from the cache, if the email and its body are there.
+    fn local_read(&self, id: &str) -> Result<Option<PortValue>, PortError> {
+        let guard = self.cache.lock().map_err(PortError::msg)?;
+        let Some(cache) = guard.as_ref() else {
+            return Ok(None);
+        };
+        let Some((email, body)) = cache.email_with_body(id).map_err(PortError::msg)? else {
+            return Ok(None);
+        };
+
+        let mut summary = Self::cached_summary(&email);
+        if let PortValue::Map(map) = &mut summary {
+            map.remove("thread_id");
+            map.insert("body".into(), PortValue::String(body));
+            map.insert(
+                "keywords".into(),
+                PortValue::List(email.keywords.into_iter().map(PortValue::String).collect()),
+            );
+        }
+        Ok(Some(summary))
+    }
+
+    /// After a change made through the port, have the next cached lookup
+    /// sync first rather than wait out `SYNC_INTERVAL`.
+    fn mark_cache_stale(&self) {
+        if let Ok(Some(cache)) = self.cache.lock().as_deref() {
+            let _ = cache.mark_stale();
+        }
+    }
+
+    fn changes_mail(cmd: &str) -> bool {
+        matches!(
+            cmd,
+            "mark_read"
+                | "mark_unread"
+                | "flag"
+                | "unflag"
+                | "move"
+                | "delete"
+                | "send"
+                | "reply"
+                | "reply_all"
+                | "forward"
+                | "draft_save"
+                | "draft_update"
+                | "draft_send"
+                | "cancel_send"
+        )
+    }
+
+    /// Mailbox/changes then Email/changes since the cached states, or a full
+    /// load where there is no state yet or the server can no longer
+    /// calculate changes from it.
+    fn sync_cache(
+        &self,
+        client: &Client,
+        cache: &mut MailCache,
+        limit: usize,
+    ) -> Result<SyncCounts, PortError> {
+        let mut counts = SyncCounts {
+            mailboxes: self.sync_mailboxes(client, cache)?,
+            ..SyncCounts::default()
+        };
+        self.sync_emails(client, cache, limit, &mut counts)?;
+        Ok(counts)
+    }
+
+    fn sync_mailboxes(&self, client: &Client, cache: &mut MailCache) -> Result<usize, PortError> {
+        let Some((mut since, _)) = cache.state("Mailbox") else {
+            return self.reload_mailboxes(client, cache);
+        };
+        let mut count = 0;
+        loop {
+            let changes = match self
+                .rt
+                .block_on(client.mailbox_changes(since.as_str(), CHANGES_LIMIT))
+            {
+                Ok(changes) => changes,
+                Err(e) if Self::cannot_calculate_changes(&e) => {
+                    return self.reload_mailboxes(client, cache)
+                }
+                Err(e) => return Err(PortError::msg(format!("mailbox_changes failed: {}", e))),
+            };
+
+            let ids: Vec<&str> = changes
+                .created()
+                .iter()
+                .chain(changes.updated())
+                .map(|id| id.as_str())
+                .collect();
+            let changed: Vec<CachedMailbox> = if ids.is_empty() {
+                Vec::new()
+            } else {
+                self.rt
+                    .block_on(async {
+                        let mut request = client.build();
+                        request
+                            .get_mailbox()
+                            .ids(ids)
+                            .properties(CACHE_MAILBOX_PROPERTIES);
+                        request.send_single::<MailboxGetResponse>().await
+                    })
+                    .map_err(|e| PortError::msg(format!("mailbox_get failed: {}", e)))?
+                    .take_list()
+                    .iter()
+                    .map(Self::cached_mailbox)
+                    .collect()
+            };
+            cache
+                .update_mailboxes(&changed, changes.destroyed())
+                .map_err(PortError::msg)?;
+            count += changed.len() + changes.destroyed().len();
+
+            since = changes.new_state().to_string();
+            cache
+                .set_state("Mailbox", &since)
+                .map_err(PortError::msg)?;
+            if !changes.has_more_changes() {
+                return Ok(count);
+            }
+        }
+    }
+
+    /// The server no longer knows our state, so only a full reload helps.
+    /// Any other error (serverFail, forbidden, ...) leaves the cache alone.
+    fn cannot_calculate_changes(e: &jmap_client::Error) -> bool {
+        matches!(e, jmap_client::Error::Method(m) if *m.error() == MethodErrorType::CannotCalculateChanges)
+    }
+
+    fn reload_mailboxes(&self, client: &Client, cache: &mut MailCache) -> Result<usize, PortError> {
+        let mut response = self
+            .rt
+            .block_on(async {
+                let mut request = client.build();
+                request.get_mailbox().properties(CACHE_MAILBOX_PROPERTIES);
+                request.send_single::<MailboxGetResponse>().await
+            })
+            .map_err(|e| PortError::msg(format!("mailbox_get failed: {}", e)))?;
+
+        let mailboxes: Vec<CachedMailbox> = response
+            .take_list()
+            .iter()
+            .map(Self::cached_mailbox)
+            .collect();
+        cache
+            .replace_mailboxes(&mailboxes)
+            .map_err(PortError::msg)?;
+        cache
+            .set_state("Mailbox", response.state())
+            .map_err(PortError::msg)?;
+        Ok(mailboxes.len())
+    }
+
+    fn sync_emails(
+        &self,
+        client: &Client,
+        cache: &mut MailCache,
+        limit: usize,
+        counts: &mut SyncCounts,
+    ) -> Result<(), PortError> {
+        let Some((mut since, _)) = cache.state("Email") else {
+            return self.reload_emails(client, cache, limit, counts);
+        };
+        loop {
+            let changes = match self
+                .rt
+                .block_on(client.email_changes(since.as_str(), Some(CHANGES_LIMIT)))
+            {
+                Ok(changes) => changes,
+                Err(e) if Self::cannot_calculate_changes(&e) => {
+                    return self.reload_emails(client, cache, limit, counts)
+                }
+                Err(e) => return Err(PortError::msg(format!("email_changes failed: {}", e))),
+            };
+
+            let ids: Vec<&str> = changes
+                .created()
+                .iter()
+                .chain(changes.updated())
+                .map(|id| id.as_str())
+                .collect();
+            let changed: Vec<CachedEmail> = if ids.is_empty() {
+                Vec::new()
+            } else {
+                self.rt
+                    .block_on(async {
+                        let mut request = client.build();
+                        request.get_email().ids(ids).properties(CACHE_PROPERTIES);
+                        request.send_single::<EmailGetResponse>().await
+                    })
+                    .map_err(|e| PortError::msg(format!("email_get failed: {}", e)))?
+                    .take_list()
+                    .iter()
+                    .map(Self::cached_email)
+                    .collect()
+            };
+            cache
+                .update_emails(&changed, changes.destroyed())
+                .map_err(PortError::msg)?;
+            counts.created += changes.created().len();
+            counts.updated += changes.updated().len();
+            counts.destroyed += changes.destroyed().len();
+
+            since = changes.new_state().to_string();
+            cache
+                .set_state("Email", &since)
+                .map_err(PortError::msg)?;
+            if !changes.has_more_changes() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Load the newest `limit` emails from scratch, `SYNC_BATCH` per request.
+    /// The state of the first batch is kept, so changes made during the load
+    /// arrive with the next sync. The cache keeps its old rows until every
+    /// batch has arrived.
+    fn reload_emails(
+        &self,
+        client: &Client,
+        cache: &mut MailCache,
+        limit: usize,
+        counts: &mut SyncCounts,
+    ) -> Result<(), PortError> {
+        let mut state = None;
+        let mut loaded: Vec<CachedEmail> = Vec::new();
+        while loaded.len() < limit {
+            let position = loaded.len();
+            let batch = SYNC_BATCH.min(limit - position);
+            let mut response = self
+                .rt
+                .block_on(async {
+                    let mut request = client.build();
+                    let query = request.query_email();
+                    query
+                        .sort([email::query::Comparator::received_at().descending()])
+                        .position(position as i32)
+                        .limit(batch);
+                    let ids = query.result_reference();
+                    request
+                        .get_email()
+                        .ids_ref(ids)
+                        .properties(CACHE_PROPERTIES);
+                    request.send_single::<EmailGetResponse>().await
+                })
+                .map_err(|e| PortError::msg(format!("email_query failed: {}", e)))?;
+
+            state.get_or_insert_with(|| response.state().to_string());
+            let emails = response.take_list();
+            loaded.extend(emails.iter().map(Self::cached_email));
+            if emails.len() < batch {
+                break;
+            }
+        }
+
+        if let Some(state) = state {
+            cache
+                .replace_emails(&loaded, &state)
+                .map_err(PortError::msg)?;
+            counts.full = true;
+            counts.created += loaded.len();
+        }
+        Ok(())
+    }
+
+    fn cached_mailbox(mb: &mailbox::Mailbox<jmap_client::Get>) -> CachedMailbox {
+        CachedMailbox {
+            id: mb.id().unwrap_or("").to_string(),
+            name: mb.name().unwrap_or("").to_string(),
+            role: Self::role_name(mb),
+            total: mb.total_emails() as i64,
+            unread: mb.unread_emails() as i64,
+        }
+    }
+
+    fn cached_email(msg: &email::Email<jmap_client::Get>) -> CachedEmail {
+        CachedEmail {
+            id: msg.id().unwrap_or("").to_string(),
+            thread_id: msg.thread_id().unwrap_or("").to_string(),
+            subject: msg.subject().unwrap_or("").to_string(),
+            from: Self::addresses_display(msg.from()),
+            to: Self::addresses_display(msg.to()),
+            received_at: msg.received_at().unwrap_or(0),
+            sent_at: msg.sent_at().unwrap_or(0),
+            size: msg.size() as i64,
+            preview: msg.preview().unwrap_or("").to_string(),
+            has_attachment: msg.has_attachment(),
+            mailbox_ids: msg.mailbox_ids().iter().map(|id| id.to_string()).collect(),
+            keywords: msg.keywords().iter().map(|k| k.to_string()).collect(),
+        }
+    }
+
+    /// The same row `email_to_summary` gives for a server result.
+    fn cached_summary(e: &CachedEmail) -> PortValue {
+        let mut map = HashMap::new();
+        map.insert("id".into(), PortValue::String(e.id.clone()));
+        map.insert("thread_id".into(), PortValue::String(e.thread_id.clone()));
+        map.insert("subject".into(), PortValue::String(e.subject.clone()));
+        map.insert("from".into(), PortValue::String(e.from.clone()));
+        map.insert("to".into(), PortValue::String(e.to.clone()));
+        map.insert("date".into(), PortValue::String(e.received_at.to_string()));
+        map.insert("preview".into(), PortValue::String(e.preview.clone()));
+        PortValue::Map(map)
+    }
+
     // --- Helpers ---
 
-    fn find_first_identity(&self, client: &Client) -> Result<(String, String), PortError> {
+    fn load_identities(&self, client: &Client) -> Result<Vec<Identity>, PortError> {
         let identities = self
             .rt
             .block_on(async {
@@ -706,16 +2001,331 @@ impl MailPort {
                     .await
                     .map(|mut r| r.take_list())
             })
-            .map_err(|e| Self::port_err(format!("get_identity failed: {}", e)))?;
+            .map_err(|e| PortError::msg(format!("get_identity failed: {}", e)))?;
+
+        Ok(identities
+            .iter()
+            .map(|ident| Identity {
+                id: ident.id().unwrap_or("").to_string(),
+                name: ident.name().unwrap_or("").to_string(),
+                email: ident.email().unwrap_or("").to_string(),
+            })
+            .collect())
+    }
+
+    /// The identity whose address matches `from`, or the first one.
+    fn pick_identity<'a>(
+        identities: &'a [Identity],
+        from: Option<&str>,
+    ) -> Result<&'a Identity, PortError> {
+        let wanted = from.and_then(Address::parse);
+        wanted
+            .as_ref()
+            .and_then(|w| {
+                identities
+                    .iter()
+                    .find(|i| i.email.eq_ignore_ascii_case(&w.email))
+            })
+            .or_else(|| identities.first())
+            .ok_or_else(|| PortError::msg("no identities configured on server"))
+    }
+
+    /// Collect the compose options shared by send, reply and forward.
+    /// Attachments are uploaded here; subject and threading are left to the caller.
+    fn outgoing(
+        &self,
+        client: &Client,
+        args: &HashMap<String, String>,
+        identity: &Identity,
+    ) -> Result<Outgoing, PortError> {
+        let list = |name: &str| -> Result<Vec<Address>, PortError> {
+            match args.get(name) {
+                Some(v) => compose::parse_addresses(v)
+                    .map_err(|e| PortError::msg(format!("invalid '{}': {}", name, e))),
+                None => Ok(Vec::new()),
+            }
+        };
+
+        // An explicit from wins; otherwise the identity, with its display name
+        let from = match args.get("from") {
+            Some(v) => {
+                let mut from = Address::parse(v)
+                    .ok_or_else(|| PortError::msg(format!("invalid 'from': {}", v)))?;
+                if from.name.is_none() && from.email.eq_ignore_ascii_case(&identity.email) {
+                    from.name = (!identity.name.is_empty()).then(|| identity.name.clone());
+                }
+                from
+            }
+            None => Address {
+                name: (!identity.name.is_empty()).then(|| identity.name.clone()),
+                email: identity.email.clone(),
+            },
+        };
+
+        let html = args.get("html").cloned();
+        let text = match (args.get("body"), &html) {
+            (Some(body), _) => body.clone(),
+            (None, Some(html)) => compose::html_to_text(html),
+            (None, None) => String::new(),
+        };
+
+        let mut headers = Vec::new();
+        for line in args.get("headers").map(|s| s.as_str()).unwrap_or("").lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (name, value) = line
+                .split_once(':')
+                .filter(|(name, _)| {
+                    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                })
+                .ok_or_else(|| PortError::msg(format!("invalid 'headers' line: {}", line)))?;
+            headers.push((name.to_string(), value.trim().to_string()));
+        }
+
+        let mut attachments = Vec::new();
+        for path in args
+            .get("attach")
+            .map(|s| s.as_str())
+            .unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+        {
+            attachments.push(self.upload_file(client, path)?);
+        }
+
+        Ok(Outgoing {
+            from,
+            to: list("to")?,
+            cc: list("cc")?,
+            bcc: list("bcc")?,
+            reply_to: list("reply_to")?,
+            subject: args.get("subject").cloned().unwrap_or_default(),
+            text,
+            html,
+            attachments,
+            headers,
+            in_reply_to: Vec::new(),
+            references: Vec::new(),
+        })
+    }
+
+    /// Upload a local file as a blob, detecting its content type.
+    fn upload_file(&self, client: &Client, path: &str) -> Result<Attachment, PortError> {
+        let path = std::path::Path::new(path);
+        let data = std::fs::read(path)
+            .map_err(|e| PortError::msg(format!("failed to read {}: {}", path.display(), e)))?;
+        let content_type = compose::content_type(path, &data).to_string();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "attachment".into());
+
+        let blob_id = self
+            .rt
+            .block_on(client.upload(None, data, Some(&content_type)))
+            .map_err(|e| PortError::msg(format!("upload of {} failed: {}", name, e)))?
+            .blob_id()
+            .to_string();
+
+        Ok(Attachment {
+            blob_id,
+            name,
+            content_type,
+        })
+    }
+
+    /// Fetch a message with everything reply and forward need from it.
+    fn fetch_original(
+        &self,
+        client: &Client,
+        id: &str,
+    ) -> Result<email::Email<jmap_client::Get>, PortError> {
+        self.rt
+            .block_on(async {
+                let mut request = client.build();
+                let get_request = request.get_email().ids([id]);
+                get_request.properties([
+                    email::Property::Id,
+                    email::Property::BlobId,
+                    email::Property::Subject,
+                    email::Property::From,
+                    email::Property::To,
+                    email::Property::Cc,
+                    email::Property::ReplyTo,
+                    email::Property::MessageId,
+                    email::Property::InReplyTo,
+                    email::Property::References,
+                    email::Property::ReceivedAt,
+                    email::Property::Preview,
+                    email::Property::TextBody,
+                    email::Property::BodyValues,
+                    email::Property::Attachments,
+                ]);
+                get_request.arguments().fetch_text_body_values(true);
+                request
+                    .send_single::<EmailGetResponse>()
+                    .await
+                    .map(|mut r| r.take_list().pop())
+            })
+            .map_err(|e| PortError::msg(format!("email_get failed: {}", e)))?
+            .ok_or_else(|| PortError::msg(format!("email not found: {}", id)))
+    }
+
+    /// Fetch a draft with everything needed to edit or send it.
+    fn fetch_draft(
+        &self,
+        client: &Client,
+        id: &str,
+    ) -> Result<email::Email<jmap_client::Get>, PortError> {
+        self.rt
+            .block_on(async {
+                let mut request = client.build();
+                let get_request = request.get_email().ids([id]);
+                get_request.properties([
+                    email::Property::Id,
+                    email::Property::Keywords,
+                    email::Property::Subject,
+                    email::Property::From,
+                    email::Property::To,
+                    email::Property::Cc,
+                    email::Property::Bcc,
+                    email::Property::ReplyTo,
+                    email::Property::InReplyTo,
+                    email::Property::References,
+                    email::Property::Preview,
+                    email::Property::TextBody,
+                    email::Property::HtmlBody,
+                    email::Property::BodyValues,
+                    email::Property::Attachments,
+                ]);
+                get_request.arguments().fetch_all_body_values(true);
+                request
+                    .send_single::<EmailGetResponse>()
+                    .await
+                    .map(|mut r| r.take_list().pop())
+            })
+            .map_err(|e| PortError::msg(format!("email_get failed: {}", e)))?
+            .ok_or_else(|| PortError::msg(format!("email not found: {}", id)))
+    }
+
+    /// A message's attachments as blobs to attach again, without re-uploading.
+    fn carried_attachments(msg: &email::Email<jmap_client::Get>) -> Vec<Attachment> {
+        msg.attachments()
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|part| {
+                Some(Attachment {
+                    blob_id: part.blob_id()?.to_string(),
+                    name: part.name().unwrap_or("attachment").to_string(),
+                    content_type: part
+                        .content_type()
+                        .unwrap_or("application/octet-stream")
+                        .to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Store `msg` in Sent and submit it for delivery. Returns the submission ID.
+    fn submit(
+        &self,
+        client: &Client,
+        msg: &Outgoing,
+        identity: &Identity,
+        hold: Option<&Hold>,
+    ) -> Result<String, PortError> {
+        let sent_id = self.find_mailbox_id(client, "Sent")?;
+        let email_id = self.create_email(client, msg, &sent_id, &[])?;
+        let rcpt_to: Vec<Address> = msg
+            .to
+            .iter()
+            .chain(&msg.cc)
+            .chain(&msg.bcc)
+            .cloned()
+            .collect();
+        self.submit_email(client, &email_id, identity, &rcpt_to, hold, None)
+    }
+
+    /// Create an EmailSubmission for a stored email. A hold needs an explicit
+    /// envelope to carry HOLDFOR/HOLDUNTIL, so `rcpt_to` is only sent then.
+    /// With `from_drafts` (Drafts and Sent mailbox IDs) the server moves the
+    /// message to Sent and clears `$draft` once the submission is created.
+    fn submit_email(
+        &self,
+        client: &Client,
+        email_id: &str,
+        identity: &Identity,
+        rcpt_to: &[Address],
+        hold: Option<&Hold>,
+        from_drafts: Option<(&str, &str)>,
+    ) -> Result<String, PortError> {
+        let mut rcpt: Vec<&str> = Vec::new();
+        for a in rcpt_to {
+            if !rcpt.iter().any(|r| r.eq_ignore_ascii_case(&a.email)) {
+                rcpt.push(&a.email);
+            }
+        }
 
-        let ident = identities
-            .first()
-            .ok_or_else(|| Self::port_err("no identities configured on server"))?;
+        self.rt
+            .block_on(async {
+                let mut request = client.build();
+                let set_req = request.set_email_submission();
+                let create = set_req.create().email_id(email_id).identity_id(&identity.id);
+                if let Some(hold) = hold {
+                    let (parameter, value) = match hold {
+                        Hold::For(secs) => ("HOLDFOR", secs.to_string()),
+                        Hold::Until(ts) => ("HOLDUNTIL", compose::format_rfc3339(*ts)),
+                    };
+                    create.envelope(
+                        email_submission::Address::new(identity.email.clone())
+                            .parameter(parameter, Some(value)),
+                        rcpt.iter()
+                            .map(|r| email_submission::Address::new(r.to_string())),
+                    );
+                }
+                let create_id = create.create_id().unwrap();
+                if let Some((drafts_id, sent_id)) = from_drafts {
+                    set_req
+                        .arguments()
+                        .on_success_update_email(&create_id)
+                        .mailbox_id(drafts_id, false)
+                        .mailbox_id(sent_id, true)
+                        .keyword("$draft", false);
+                }
 
-        Ok((
-            ident.id().unwrap_or("").to_string(),
-            ident.email().unwrap_or("").to_string(),
-        ))
+                // Any implicit Email/set follows; the submission comes first
+                request
+                    .send()
+                    .await?
+                    .unwrap_method_responses()
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| jmap_client::Error::Internal("empty response".into()))?
+                    .unwrap_set_email_submission()?
+                    .created(&create_id)
+                    .map(|s| s.id().unwrap_or("unknown").to_string())
+            })
+            .map_err(|e| PortError::msg(format!("email_submission_set failed: {}", e)))
+    }
+
+    /// `send_at` (a date) or `delay` (seconds) as a hold, if either is given.
+    fn hold_arg(args: &HashMap<String, String>) -> Result<Option<Hold>, PortError> {
+        match (args.get("send_at"), args.get("delay")) {
+            (Some(_), Some(_)) => Err(PortError::msg("give either 'send_at' or 'delay', not both")),
+            (Some(v), None) => Ok(Some(Hold::Until(Self::date_arg("send_at", v)?))),
+            (None, Some(v)) => Ok(Some(Hold::For(Self::number_arg("delay", v)?))),
+            (None, None) => Ok(None),
+        }
+    }
+
+    fn sent_label(hold: Option<&Hold>) -> String {
+        match hold {
+            None => "sent".into(),
+            Some(Hold::For(secs)) => format!("scheduled in {}s", secs),
+            Some(Hold::Until(ts)) => format!("scheduled for {}", compose::format_date(*ts)),
+        }
     }
 
     /// Create an email via Email/set with structured JMAP properties.
@@ -723,13 +2333,9 @@ impl MailPort {
     fn create_email(
         &self,
         client: &Client,
-        from: &str,
-        to: &str,
-        subject: &str,
-        body: &str,
+        msg: &Outgoing,
         mailbox_id: &str,
-        in_reply_to: Option<&[String]>,
-        references: Option<&[String]>,
+        keywords: &[&str],
     ) -> Result<String, PortError> {
         let email_id = self
             .rt
@@ -739,20 +2345,57 @@ impl MailPort {
                 let create = set_req.create();
                 create
                     .mailbox_ids([mailbox_id])
-                    .from([from])
-                    .to([to])
-                    .subject(subject)
+                    .from([Self::jmap_address(&msg.from)])
+                    .subject(&msg.subject)
                     .text_body(
                         EmailBodyPart::new()
-                            .part_id("1")
+                            .part_id("text")
                             .content_type("text/plain"),
                     )
-                    .body_value("1".to_string(), body);
-                if let Some(ids) = in_reply_to {
-                    create.in_reply_to(ids.iter().map(|s| s.as_str()));
+                    .body_value("text".to_string(), msg.text.as_str());
+                if let Some(html) = &msg.html {
+                    create
+                        .html_body(
+                            EmailBodyPart::new()
+                                .part_id("html")
+                                .content_type("text/html"),
+                        )
+                        .body_value("html".to_string(), html.as_str());
+                }
+                if !keywords.is_empty() {
+                    create.keywords(keywords.iter().copied());
                 }
-                if let Some(ids) = references {
-                    create.references(ids.iter().map(|s| s.as_str()));
+                if !msg.to.is_empty() {
+                    create.to(msg.to.iter().map(Self::jmap_address));
+                }
+                if !msg.cc.is_empty() {
+                    create.cc(msg.cc.iter().map(Self::jmap_address));
+                }
+                if !msg.bcc.is_empty() {
+                    create.bcc(msg.bcc.iter().map(Self::jmap_address));
+                }
+                if !msg.reply_to.is_empty() {
+                    create.reply_to(msg.reply_to.iter().map(Self::jmap_address));
+                }
+                for att in &msg.attachments {
+                    create.attachment(
+                        EmailBodyPart::new()
+                            .blob_id(&att.blob_id)
+                            .name(&att.name)
+                            .content_type(&att.content_type),
+                    );
+                }
+                for (name, value) in &msg.headers {
+                    create.header(
+                        email::Header::as_text(name, false),
+                        email::HeaderValue::AsText(value.clone()),
+                    );
+                }
+                if !msg.in_reply_to.is_empty() {
+                    create.in_reply_to(msg.in_reply_to.iter().map(|s| s.as_str()));
+                }
+                if !msg.references.is_empty() {
+                    create.references(msg.references.iter().map(|s| s.as_str()));
                 }
                 let id = create.create_id().unwrap();
                 request
@@ -761,50 +2404,468 @@ impl MailPort {
                     .and_then(|mut r| r.created(&id))
                     .map(|e| e.id().unwrap_or("unknown").to_string())
             })
-            .map_err(|e| Self::port_err(format!("email_set create failed: {}", e)))?;
+            .map_err(|e| PortError::msg(format!("email_set create failed: {}", e)))?;
+
+        Ok(email_id)
+    }
+
+    fn jmap_address(address: &Address) -> email::EmailAddress {
+        match &address.name {
+            Some(name) => (name.clone(), address.email.clone()).into(),
+            None => address.email.clone().into(),
+        }
+    }
+
+    fn addresses(list: Option<&[email::EmailAddress]>) -> Vec<Address> {
+        list.unwrap_or(&[])
+            .iter()
+            .map(|a| Address {
+                name: a.name().filter(|n| !n.is_empty()).map(str::to_string),
+                email: a.email().to_string(),
+            })
+            .collect()
+    }
+
+    fn address_list(list: &[Address]) -> String {
+        list.iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Build the filter, sort and window shared by `query` and `search`.
+    fn email_query(
+        &self,
+        client: &Client,
+        args: &HashMap<String, String>,
+    ) -> Result<EmailQuery, PortError> {
+        let mut conditions = Vec::new();
+        if let Some(v) = args.get("from") {
+            conditions.push(email::query::Filter::from(v));
+        }
+        if let Some(v) = args.get("to") {
+            conditions.push(email::query::Filter::to(v));
+        }
+        if let Some(v) = args.get("subject") {
+            conditions.push(email::query::Filter::subject(v));
+        }
+        if let Some(v) = args.get("before") {
+            conditions.push(email::query::Filter::before(Self::date_arg("before", v)?));
+        }
+        if let Some(v) = args.get("after") {
+            conditions.push(email::query::Filter::after(Self::date_arg("after", v)?));
+        }
+        if let Some(v) = args.get("has_attachment") {
+            conditions.push(email::query::Filter::has_attachment(v == "true" || v == "1"));
+        }
+        if let Some(v) = args.get("keyword") {
+            conditions.push(email::query::Filter::has_keyword(v));
+        }
+        if let Some(v) = args.get("not_keyword") {
+            conditions.push(email::query::Filter::not_keyword(v));
+        }
+        if let Some(v) = args.get("min_size") {
+            conditions.push(email::query::Filter::min_size(Self::number_arg("min_size", v)?));
+        }
+        if let Some(v) = args.get("max_size") {
+            conditions.push(email::query::Filter::max_size(Self::number_arg("max_size", v)?));
+        }
+        if let Some(v) = args.get("not_in") {
+            let ids = v
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(|name| self.find_mailbox_id(client, name))
+                .collect::<Result<Vec<_>, _>>()?;
+            conditions.push(email::query::Filter::in_mailbox_other_than(ids));
+        }
+
+        let sort = Self::parse_sort(
+            args.get("sort")
+                .map(|s| s.as_str())
+                .unwrap_or("received:desc"),
+        )?
+        .into_iter()
+        .map(|(key, ascending)| Self::comparator(key, ascending))
+        .collect();
+        let position = args
+            .get("position")
+            .map(|v| Self::number_arg("position", v))
+            .transpose()?;
+        let anchor_offset = args
+            .get("anchor_offset")
+            .map(|v| Self::number_arg("anchor_offset", v))
+            .transpose()?;
+        let limit = args
+            .get("limit")
+            .map(|v| Self::number_arg("limit", v))
+            .transpose()?
+            .unwrap_or(20);
+
+        Ok(EmailQuery {
+            conditions,
+            sort,
+            position,
+            anchor: args.get("anchor").cloned(),
+            anchor_offset,
+            limit,
+            collapse_threads: bool_arg(args, "collapse_threads", false),
+        })
+    }
+
+    /// Parse `received:desc,from` into sort keys with an ascending flag;
+    /// properties sort ascending unless suffixed with `:desc`.
+    fn parse_sort(spec: &str) -> Result<Vec<(SortKey, bool)>, PortError> {
+        spec.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|item| {
+                let (property, order) = item.split_once(':').unwrap_or((item, "asc"));
+                let key = match property {
+                    "received" => SortKey::Received,
+                    "sent" => SortKey::Sent,
+                    "size" => SortKey::Size,
+                    "from" => SortKey::From,
+                    "to" => SortKey::To,
+                    "subject" => SortKey::Subject,
+                    other => {
+                        return Err(PortError::msg(format!(
+                            "invalid 'sort' property: {} (expected received, sent, size, from, to or subject)",
+                            other
+                        )))
+                    }
+                };
+                match order {
+                    "asc" => Ok((key, true)),
+                    "desc" => Ok((key, false)),
+                    other => Err(PortError::msg(format!(
+                        "invalid 'sort' order: {} (expected asc or desc)",
+                        other
+                    ))),
+                }
+            })
+            .collect()
+    }
+
+    fn comparator(key: SortKey, ascending: bool) -> query::Comparator<email::query::Comparator> {
+        let comparator = match key {
+            SortKey::Received => email::query::Comparator::received_at(),
+            SortKey::Sent => email::query::Comparator::sent_at(),
+            SortKey::Size => email::query::Comparator::size(),
+            SortKey::From => email::query::Comparator::from(),
+            SortKey::To => email::query::Comparator::to(),
+            SortKey::Subject => email::query::Comparator::subject(),
+        };
+        if ascending {
+            comparator.ascending()
+        } else {
+            comparator.descending()
+        }
+    }
+
+    fn number_arg<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, PortError> {
+        value
+            .parse()
+            .map_err(|_| PortError::msg(format!("invalid '{}': {}", name, value)))
+    }
+
+    /// Unix time from a unix timestamp, `YYYY-MM-DD` or `YYYY-MM-DDTHH:MM[:SS]`, in UTC.
+    fn date_arg(name: &str, value: &str) -> Result<i64, PortError> {
+        let invalid = || {
+            PortError::msg(format!(
+                "invalid '{}': {} (expected YYYY-MM-DD, YYYY-MM-DDTHH:MM:SS or unix time)",
+                name, value
+            ))
+        };
+        if let Ok(ts) = value.parse::<i64>() {
+            return Ok(ts);
+        }
+        let value = value.trim_end_matches('Z');
+        let (date, time) = value.split_once(['T', ' ']).unwrap_or((value, "00:00:00"));
+        let numbers = |s: &str, sep: char| -> Option<Vec<i64>> {
+            s.split(sep).map(|p| p.parse().ok()).collect()
+        };
+        let date = numbers(date, '-').ok_or_else(invalid)?;
+        let mut time = numbers(time, ':').ok_or_else(invalid)?;
+        time.resize(3, 0);
+        let [year, month, day] = date[..] else {
+            return Err(invalid());
+        };
+        if !(1..=12).contains(&month)
+            || !(1..=31).contains(&day)
+            || !(0..24).contains(&time[0])
+            || !(0..60).contains(&time[1])
+            || !(0..=60).contains(&time[2])
+        {
+            return Err(invalid());
+        }
+        // Days since 1970-01-01 in the proleptic Gregorian calendar
+        let y = if month <= 2 { year - 1 } else { year };
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146097 + doe - 719468;
+        Ok(days * 86400 + time[0] * 3600 + time[1] * 60 + time[2])
+    }
+
+    fn page_value(page: EmailPage) -> PortValue {
+        let end = page.position + page.emails.len();
+        let more = match page.total {
+            Some(total) => end < total,
+            None => !page.emails.is_empty(),
+        };
+        let mut map = HashMap::new();
+        map.insert(
+            "total".into(),
+            page.total.map_or(PortValue::Null, |t| PortValue::Int(t as i64)),
+        );
+        map.insert("position".into(), PortValue::Int(page.position as i64));
+        // Pass back as position= to fetch the next page
+        map.insert(
+            "next_position".into(),
+            if more { PortValue::Int(end as i64) } else { PortValue::Null },
+        );
+        map.insert("emails".into(), PortValue::List(page.emails));
+        PortValue::Map(map)
+    }
+
+    /// Email/query and Email/get for the matches in one request, linked by a
+    /// result reference. A Mailbox/get for no ids rides along to check the
+    /// mailbox cache against the server's state. With collapsed threads,
+    /// Thread/get and an Email/get of every thread member follow the rows
+    /// to count them and collect the participants.
+    fn query_summaries(
+        &self,
+        client: &Client,
+        email_query: EmailQuery,
+    ) -> Result<EmailPage, PortError> {
+        let collapse_threads = email_query.collapse_threads;
+        let (emails, total, position, threads, members, mailbox_state) = self
+            .rt
+            .block_on(async {
+                let mut request = client.build();
+                let query = request.query_email();
+                let mut conditions = email_query.conditions;
+                match conditions.len() {
+                    0 => {}
+                    1 => {
+                        query.filter(conditions.remove(0));
+                    }
+                    _ => {
+                        query.filter(query::Filter::and(conditions));
+                    }
+                }
+                query
+                    .sort(email_query.sort)
+                    .limit(email_query.limit)
+                    .calculate_total(true);
+                if let Some(position) = email_query.position {
+                    query.position(position);
+                }
+                if let Some(anchor) = email_query.anchor {
+                    query.anchor(anchor);
+                }
+                if let Some(offset) = email_query.anchor_offset {
+                    query.anchor_offset(offset);
+                }
+                if collapse_threads {
+                    query.arguments().collapse_threads(true);
+                }
+                let ids = query.result_reference();
+                let get_request = request.get_email().ids_ref(ids);
+                get_request.properties(SUMMARY_PROPERTIES);
+                if collapse_threads {
+                    let thread_ids = get_request.result_reference(email::Property::ThreadId);
+                    let get_threads = request.get_thread().ids_ref(thread_ids);
+                    let member_ids = get_threads.result_reference(thread::Property::EmailIds);
+                    request
+                        .get_email()
+                        .ids_ref(member_ids)
+                        .properties([email::Property::Id, email::Property::From]);
+                }
+                request.get_mailbox().ids(Vec::<String>::new());
+
+                let mut responses = request.send().await?.unwrap_method_responses();
+                let mailbox_state = responses
+                    .pop()
+                    .map(|r| r.unwrap_get_mailbox())
+                    .transpose()?
+                    .map(|r| r.state().to_string());
+                let (threads, members) = if collapse_threads {
+                    let members = responses
+                        .pop()
+                        .map(|r| r.unwrap_get_email())
+                        .transpose()?
+                        .map(|mut r| r.take_list())
+                        .unwrap_or_default();
+                    let threads = responses
+                        .pop()
+                        .map(|r| r.unwrap_get_thread())
+                        .transpose()?
+                        .map(|mut r| r.take_list())
+                        .unwrap_or_default();
+                    (threads, members)
+                } else {
+                    (Vec::new(), Vec::new())
+                };
+                let emails = responses
+                    .pop()
+                    .map(|r| r.unwrap_get_email())
+                    .transpose()?
+                    .map(|mut r| r.take_list())
+                    .unwrap_or_default();
+                let (total, position) = responses
+                    .pop()
+                    .map(|r| r.unwrap_query_email())
+                    .transpose()?
+                    .map(|r| (r.total(), r.position().max(0) as usize))
+                    .unwrap_or((None, 0));
+                Ok::<_, jmap_client::Error>((
+                    emails,
+                    total,
+                    position,
+                    threads,
+                    members,
+                    mailbox_state,
+                ))
+            })
+            .map_err(|e| PortError::msg(format!("email query failed: {}", e)))?;
 
-        Ok(email_id)
+        if let Some(state) = mailbox_state {
+            self.check_mailbox_state(&state);
+        }
+        Ok(EmailPage {
+            emails: emails
+                .iter()
+                .map(|msg| {
+                    let mut summary = Self::email_to_summary(msg);
+                    if let (true, PortValue::Map(map)) = (collapse_threads, &mut summary) {
+                        let email_ids = msg
+                            .thread_id()
+                            .and_then(|id| threads.iter().find(|t| t.id() == id))
+                            .map(|t| t.email_ids())
+                            .unwrap_or_default();
+                        map.insert("count".into(), PortValue::Int(email_ids.len().max(1) as i64));
+                        map.insert(
+                            "participants".into(),
+                            Self::participants(email_ids, &members),
+                        );
+                    }
+                    summary
+                })
+                .collect(),
+            total,
+            position,
+        })
     }
 
-    fn find_mailbox_id(&self, client: &Client, name: &str) -> Result<String, PortError> {
-        let response = self
-            .rt
-            .block_on(client.mailbox_query(
-                None::<mailbox::query::Filter>,
-                None::<Vec<query::Comparator<mailbox::query::Comparator>>>,
-            ))
-            .map_err(|e| Self::port_err(format!("mailbox_query failed: {}", e)))?;
+    /// Distinct senders of the given messages, in thread order.
+    fn participants(email_ids: &[String], members: &[email::Email<jmap_client::Get>]) -> PortValue {
+        let mut seen: Vec<Address> = Vec::new();
+        for id in email_ids {
+            let Some(msg) = members.iter().find(|m| m.id() == Some(id.as_str())) else {
+                continue;
+            };
+            for a in Self::addresses(msg.from()) {
+                if !seen.iter().any(|s| s.email.eq_ignore_ascii_case(&a.email)) {
+                    seen.push(a);
+                }
+            }
+        }
+        PortValue::String(Self::address_list(&seen))
+    }
 
-        let name_lower = name.to_lowercase();
+    /// Fetch every mailbox in one Mailbox/get and refresh the cache from it.
+    fn load_mailboxes(
+        &self,
+        client: &Client,
+    ) -> Result<Vec<mailbox::Mailbox<jmap_client::Get>>, PortError> {
+        let mut response = self
+            .rt
+            .block_on(async {
+                let mut request = client.build();
+                request.get_mailbox().properties([
+                    mailbox::Property::Id,
+                    mailbox::Property::Name,
+                    mailbox::Property::Role,
+                    mailbox::Property::ParentId,
+                    mailbox::Property::SortOrder,
+                    mailbox::Property::TotalEmails,
+                    mailbox::Property::UnreadEmails,
+                ]);
+                request.send_single::<MailboxGetResponse>().await
+            })
+            .map_err(|e| PortError::msg(format!("mailbox_get failed: {}", e)))?;
+
+        let state = response.state().to_string();
+        let list = response.take_list();
+        let mailboxes = list
+            .iter()
+            .map(|mb| MailboxInfo {
+                id: mb.id().unwrap_or("").to_string(),
+                name: mb.name().unwrap_or("").to_string(),
+                role: Self::role_name(mb),
+            })
+            .collect();
+        if let Ok(mut cache) = self.mailboxes.lock() {
+            *cache = Some(MailboxCache { state, mailboxes });
+        }
+        Ok(list)
+    }
 
-        for id in response.ids() {
-            if let Ok(Some(mb)) = self
-                .rt
-                .block_on(client.mailbox_get(id, None::<Vec<mailbox::Property>>))
-            {
-                let mb_name = mb.name().unwrap_or("");
-                let mb_role = format!("{:?}", mb.role());
-
-                if mb_name.to_lowercase() == name_lower
-                    || mb_role.to_lowercase() == name_lower
-                    || id == name
-                {
-                    return Ok(id.to_string());
-                }
+    /// Drop the mailbox cache if the server has moved past its state.
+    fn check_mailbox_state(&self, state: &str) {
+        if let Ok(mut cache) = self.mailboxes.lock() {
+            if cache.as_ref().is_some_and(|c| c.state != state) {
+                *cache = None;
             }
         }
+    }
+
+    fn role_name(mb: &mailbox::Mailbox<jmap_client::Get>) -> String {
+        match mb.role() {
+            mailbox::Role::None => String::new(),
+            role => format!("{:?}", role).to_lowercase(),
+        }
+    }
+
+    /// Resolve a mailbox by name, role or ID from the cache, reloading it once on a miss.
+    fn find_mailbox_id(&self, client: &Client, name: &str) -> Result<String, PortError> {
+        if let Some(id) = self.cached_mailbox_id(name) {
+            return Ok(id);
+        }
+        self.load_mailboxes(client)?;
+        self.cached_mailbox_id(name)
+            .ok_or_else(|| PortError::msg(format!("mailbox not found: {}", name)))
+    }
 
-        Err(Self::port_err(format!("mailbox not found: {}", name)))
+    fn cached_mailbox_id(&self, name: &str) -> Option<String> {
+        let cache = self.mailboxes.lock().ok()?;
+        let name_lower = name.to_lowercase();
+        cache
+            .as_ref()?
+            .mailboxes
+            .iter()
+            .find(|mb| {
+                mb.name.to_lowercase() == name_lower || mb.role == name_lower || mb.id == name
+            })
+            .map(|mb| mb.id.clone())
     }
 
-    fn email_to_summary(&self, msg: &email::Email<jmap_client::Get>) -> PortValue {
+    fn email_to_summary(msg: &email::Email<jmap_client::Get>) -> PortValue {
         let mut map = HashMap::new();
         map.insert("id".into(), PortValue::String(msg.id().unwrap_or("").into()));
+        map.insert(
+            "thread_id".into(),
+            PortValue::String(msg.thread_id().unwrap_or("").into()),
+        );
         map.insert(
             "subject".into(),
             PortValue::String(msg.subject().unwrap_or("").into()),
         );
-        map.insert("from".into(), self.addresses_to_value(msg.from()));
+        map.insert("from".into(), Self::addresses_to_value(msg.from()));
+        map.insert("to".into(), Self::addresses_to_value(msg.to()));
         map.insert(
             "date".into(),
             PortValue::String(
@@ -820,7 +2881,11 @@ impl MailPort {
         PortValue::Map(map)
     }
 
-    fn addresses_to_value(&self, addrs: Option<&[email::EmailAddress]>) -> PortValue {
+    fn addresses_to_value(addrs: Option<&[email::EmailAddress]>) -> PortValue {
+        PortValue::String(Self::addresses_display(addrs))
+    }
+
+    fn addresses_display(addrs: Option<&[email::EmailAddress]>) -> String {
         match addrs {
             Some(list) => {
                 let formatted: Vec<String> = list
@@ -835,9 +2900,9 @@ impl MailPort {
                         }
                     })
                     .collect();
-                PortValue::String(formatted.join(", "))
+                formatted.join(", ")
             }
-            None => PortValue::String(String::new()),
+            None => String::new(),
         }
     }
 
@@ -854,6 +2919,110 @@ impl MailPort {
         // Fallback to preview
         msg.preview().unwrap_or("").to_string()
     }
+
+    /// The HTML alternative, if the message has one and its value was fetched.
+    fn extract_body_html(msg: &email::Email<jmap_client::Get>) -> Option<String> {
+        msg.html_body()?
+            .iter()
+            .filter(|part| part.content_type() == Some("text/html"))
+            .find_map(|part| msg.body_value(part.part_id()?))
+            .map(|value| value.value().to_string())
+    }
+
+    /// Parameters shared by reply and reply_all.
+    fn reply_params() -> Vec<ParamDef> {
+        vec![
+            ParamDef {
+                name: "id".into(),
+                description: "Email ID to reply to".into(),
+                required: true,
+            },
+            ParamDef {
+                name: "body".into(),
+                description: "Reply body text (required unless html is given)".into(),
+                required: false,
+            },
+            ParamDef {
+                name: "to".into(),
+                description: "Extra recipients besides the original sender".into(),
+                required: false,
+            },
+            ParamDef {
+                name: "quote".into(),
+                description: "Quote the original below the reply (default true)".into(),
+                required: false,
+            },
+        ]
+        .into_iter()
+        .chain(Self::compose_params())
+        .chain(Self::schedule_params())
+        .collect()
+    }
+
+    /// Compose options shared by `send`, `reply`, `forward` and the drafts.
+    fn compose_params() -> Vec<ParamDef> {
+        [
+            ("from", "From address, optionally with a name (default: first identity)"),
+            ("cc", "Cc recipients, comma-separated"),
+            ("bcc", "Bcc recipients, comma-separated"),
+            ("reply_to", "Reply-To addresses, comma-separated"),
+            ("html", "HTML body; sent as multipart/alternative with the text body"),
+            ("attach", "Comma-separated local file paths to attach"),
+            ("headers", "Extra header lines, newline-separated (e.g. X-Priority: 1)"),
+        ]
+        .into_iter()
+        .map(|(name, description)| ParamDef {
+            name: name.into(),
+            description: description.into(),
+            required: false,
+        })
+        .collect()
+    }
+
+    /// Delayed sending, shared by everything that submits.
+    fn schedule_params() -> Vec<ParamDef> {
+        [
+            ("send_at", "Send at this time (YYYY-MM-DD, YYYY-MM-DDTHH:MM:SS or unix time, UTC)"),
+            ("delay", "Send after this many seconds; cancel_send until then"),
+        ]
+        .into_iter()
+        .map(|(name, description)| ParamDef {
+            name: name.into(),
+            description: description.into(),
+            required: false,
+        })
+        .collect()
+    }
+
+    /// Filter, sort and paging parameters shared by `query` and `search`.
+    fn query_params() -> Vec<ParamDef> {
+        [
+            ("from", "Sender contains"),
+            ("to", "Recipient contains"),
+            ("subject", "Subject contains"),
+            ("before", "Received before (YYYY-MM-DD, YYYY-MM-DDTHH:MM:SS or unix time, UTC)"),
+            ("after", "Received at or after (YYYY-MM-DD, YYYY-MM-DDTHH:MM:SS or unix time, UTC)"),
+            ("has_attachment", "true/false"),
+            ("keyword", "Has keyword (e.g. $flagged, $seen)"),
+            ("not_keyword", "Lacks keyword (e.g. $seen for unread)"),
+            ("min_size", "Minimum size in bytes"),
+            ("max_size", "Maximum size in bytes"),
+            ("not_in", "Comma-separated mailboxes to exclude (e.g. Trash,Junk)"),
+            ("sort", "Comma-separated received, sent, size, from, to, subject, each with optional :asc/:desc (default: received:desc)"),
+            ("limit", "Max results (default: 20)"),
+            ("position", "0-based index of the first result (use next_position from the previous page)"),
+            ("anchor", "Email ID to page from instead of position"),
+            ("anchor_offset", "Offset from the anchor (may be negative)"),
+            ("collapse_threads", "true for one row per conversation, with count and participants"),
+        ]
+        .into_iter()
+        .map(|(name, description)| ParamDef {
+            name: name.into(),
+            description: description.into(),
+            required: false,
+        })
+        .collect()
+    }
 }
 
 // Safety: MailPort is only used from one thread at a time via Mutex or single-threaded FFI
@@ -865,6 +3034,38 @@ impl AppMeshPort for MailPort {
     }
 
     fn commands(&self) -> Vec<CommandDef> {
+        let account = ParamDef {
+            name: "account".into(),
+            description: format!("Named account (default: {})", self.default_account),
+            required: false,
+        };
+        let mut commands = MailAccount::commands();
+        for command in &mut commands {
+            command.params.push(account.clone());
+        }
+        commands.push(CommandDef {
+            name: "accounts".into(),
+            description: "List configured mail accounts and their connection state".into(),
+            params: vec![],
+        });
+        commands
+    }
+
+    fn execute(&self, cmd: &str, args: &HashMap<String, String>) -> PortResult {
+        if cmd == "accounts" {
+            return self.cmd_accounts();
+        }
+        let name = args.get("account").unwrap_or(&self.default_account);
+        self.account(name)?.execute(cmd, args)
+    }
+
+    fn subscribe(&self) -> Result<EventStream, PortError> {
+        self.account(&self.default_account)?.subscribe()
+    }
+}
+
+impl MailAccount {
+    fn commands() -> Vec<CommandDef> {
         vec![
             // Phase 1: Read-only
             CommandDef {
@@ -886,6 +3087,17 @@ impl AppMeshPort for MailPort {
                         description: "Password (falls back to JMAP_PASS env)".into(),
                         required: false,
                     },
+                    ParamDef {
+                        name: "account_id".into(),
+                        description: "JMAP accountId, e.g. of a shared mailbox (default: primary)"
+                            .into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "save".into(),
+                        description: "true to save the login as this named account".into(),
+                        required: false,
+                    },
                 ],
             },
             CommandDef {
@@ -900,19 +3112,22 @@ impl AppMeshPort for MailPort {
             },
             CommandDef {
                 name: "query".into(),
-                description: "Query emails in a mailbox".into(),
-                params: vec![
+                description: "Query emails in a mailbox, one page at a time".into(),
+                params: [
                     ParamDef {
                         name: "mailbox".into(),
-                        description: "Mailbox name or ID (default: Inbox)".into(),
+                        description: "Mailbox name or ID, or * for all (default: Inbox)".into(),
                         required: false,
                     },
                     ParamDef {
-                        name: "limit".into(),
-                        description: "Max results (default: 20)".into(),
+                        name: "live".into(),
+                        description: "true to skip the offline cache and ask the server".into(),
                         required: false,
                     },
-                ],
+                ]
+                .into_iter()
+                .chain(Self::query_params())
+                .collect(),
             },
             CommandDef {
                 name: "read".into(),
@@ -923,6 +3138,15 @@ impl AppMeshPort for MailPort {
                     required: true,
                 }],
             },
+            CommandDef {
+                name: "thread".into(),
+                description: "All messages in an email's conversation, oldest first".into(),
+                params: vec![ParamDef {
+                    name: "id".into(),
+                    description: "Email ID".into(),
+                    required: true,
+                }],
+            },
             CommandDef {
                 name: "mark_read".into(),
                 description: "Mark an email as read".into(),
@@ -944,7 +3168,7 @@ impl AppMeshPort for MailPort {
                 params: vec![
                     ParamDef {
                         name: "to".into(),
-                        description: "Recipient email address".into(),
+                        description: "Recipients, comma-separated (e.g. Ann <ann@example.com>, bob@example.com)".into(),
                         required: true,
                     },
                     ParamDef {
@@ -954,32 +3178,152 @@ impl AppMeshPort for MailPort {
                     },
                     ParamDef {
                         name: "body".into(),
-                        description: "Email body text".into(),
+                        description: "Email body text (required unless html is given)".into(),
+                        required: false,
+                    },
+                ]
+                .into_iter()
+                .chain(Self::compose_params())
+                .chain(Self::schedule_params())
+                .collect(),
+            },
+            CommandDef {
+                name: "reply".into(),
+                description: "Reply to the sender (Reply-To if set), quoting the original".into(),
+                params: Self::reply_params(),
+            },
+            CommandDef {
+                name: "reply_all".into(),
+                description: "Reply to the sender and all other recipients".into(),
+                params: Self::reply_params(),
+            },
+            CommandDef {
+                name: "forward".into(),
+                description: "Forward an email inline or as an attachment".into(),
+                params: vec![
+                    ParamDef {
+                        name: "id".into(),
+                        description: "Email ID to forward".into(),
                         required: true,
                     },
                     ParamDef {
-                        name: "from".into(),
-                        description: "From address (default: first identity)".into(),
+                        name: "to".into(),
+                        description: "Recipients, comma-separated".into(),
+                        required: true,
+                    },
+                    ParamDef {
+                        name: "body".into(),
+                        description: "Note above the forwarded message".into(),
                         required: false,
                     },
-                ],
+                    ParamDef {
+                        name: "mode".into(),
+                        description: "inline (default) or attachment (original as .eml)".into(),
+                        required: false,
+                    },
+                ]
+                .into_iter()
+                .chain(Self::compose_params())
+                .chain(Self::schedule_params())
+                .collect(),
             },
+            // Drafts
             CommandDef {
-                name: "reply".into(),
-                description: "Reply to an email".into(),
+                name: "draft_save".into(),
+                description: "Save a message to Drafts for review instead of sending".into(),
+                params: vec![
+                    ParamDef {
+                        name: "to".into(),
+                        description: "Recipients, comma-separated".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "subject".into(),
+                        description: "Email subject".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "body".into(),
+                        description: "Email body text".into(),
+                        required: false,
+                    },
+                ]
+                .into_iter()
+                .chain(Self::compose_params())
+                .collect(),
+            },
+            CommandDef {
+                name: "draft_update".into(),
+                description: "Replace fields of a draft; returns the new draft ID".into(),
                 params: vec![
                     ParamDef {
                         name: "id".into(),
-                        description: "Email ID to reply to".into(),
+                        description: "Draft email ID".into(),
                         required: true,
                     },
+                    ParamDef {
+                        name: "to".into(),
+                        description: "Recipients, comma-separated".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "subject".into(),
+                        description: "Email subject".into(),
+                        required: false,
+                    },
                     ParamDef {
                         name: "body".into(),
-                        description: "Reply body text".into(),
-                        required: true,
+                        description: "Email body text".into(),
+                        required: false,
+                    },
+                ]
+                .into_iter()
+                .chain(Self::compose_params())
+                .collect(),
+            },
+            CommandDef {
+                name: "drafts".into(),
+                description: "List drafts".into(),
+                params: Self::query_params(),
+            },
+            CommandDef {
+                name: "draft_send".into(),
+                description: "Send a draft; it moves to Sent once submitted".into(),
+                params: vec![ParamDef {
+                    name: "id".into(),
+                    description: "Draft email ID".into(),
+                    required: true,
+                }]
+                .into_iter()
+                .chain(Self::schedule_params())
+                .collect(),
+            },
+            // Submissions
+            CommandDef {
+                name: "submissions".into(),
+                description: "List recent submissions with undo and delivery status".into(),
+                params: vec![
+                    ParamDef {
+                        name: "status".into(),
+                        description: "pending, final or canceled".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "limit".into(),
+                        description: "Max results (default: 20)".into(),
+                        required: false,
                     },
                 ],
             },
+            CommandDef {
+                name: "cancel_send".into(),
+                description: "Cancel a scheduled send; the message returns to Drafts".into(),
+                params: vec![ParamDef {
+                    name: "id".into(),
+                    description: "Submission ID".into(),
+                    required: true,
+                }],
+            },
             // Phase 3: Mail Management
             CommandDef {
                 name: "move".into(),
@@ -1043,18 +3387,21 @@ impl AppMeshPort for MailPort {
             CommandDef {
                 name: "search".into(),
                 description: "Full-text search across all mailboxes".into(),
-                params: vec![
+                params: [
                     ParamDef {
                         name: "text".into(),
                         description: "Search text".into(),
                         required: true,
                     },
                     ParamDef {
-                        name: "limit".into(),
-                        description: "Max results (default: 20)".into(),
+                        name: "live".into(),
+                        description: "true to skip the offline cache and ask the server".into(),
                         required: false,
                     },
-                ],
+                ]
+                .into_iter()
+                .chain(Self::query_params())
+                .collect(),
             },
             CommandDef {
                 name: "attachment_list".into(),
@@ -1081,22 +3428,55 @@ impl AppMeshPort for MailPort {
                     },
                 ],
             },
+            // Offline cache
+            CommandDef {
+                name: "sync".into(),
+                description: "Update the offline cache used by query, search and read".into(),
+                params: vec![
+                    ParamDef {
+                        name: "limit".into(),
+                        description: "Newest emails to load on a full sync (default: 5000)".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "reset".into(),
+                        description: "true to discard the cache and load it again".into(),
+                        required: false,
+                    },
+                ],
+            },
+            CommandDef {
+                name: "sync_status".into(),
+                description: "Offline cache location, counts, states and age".into(),
+                params: vec![],
+            },
         ]
     }
 
     fn execute(&self, cmd: &str, args: &HashMap<String, String>) -> PortResult {
-        match cmd {
+        let result = match cmd {
             // Phase 1
             "connect" => self.cmd_connect(args),
             "status" => self.cmd_status(),
             "mailboxes" => self.cmd_mailboxes(),
             "query" => self.cmd_query(args),
             "read" => self.cmd_read(args),
+            "thread" => self.cmd_thread(args),
             "mark_read" => self.cmd_mark_read(args),
             // Phase 2
             "identities" => self.cmd_identities(),
             "send" => self.cmd_send(args),
-            "reply" => self.cmd_reply(args),
+            "reply" => self.cmd_reply(args, false),
+            "reply_all" => self.cmd_reply(args, true),
+            "forward" => self.cmd_forward(args),
+            // Drafts
+            "draft_save" => self.cmd_draft_save(args),
+            "draft_update" => self.cmd_draft_update(args),
+            "drafts" => self.cmd_drafts(args),
+            "draft_send" => self.cmd_draft_send(args),
+            // Submissions
+            "submissions" => self.cmd_submissions(args),
+            "cancel_send" => self.cmd_cancel_send(args),
             // Phase 3
             "move" => self.cmd_move(args),
             "delete" => self.cmd_delete(args),
@@ -1106,10 +3486,328 @@ impl AppMeshPort for MailPort {
             "search" => self.cmd_search(args),
             "attachment_list" => self.cmd_attachment_list(args),
             "attachment_download" => self.cmd_attachment_download(args),
+            // Offline cache
+            "sync" => self.cmd_sync(args),
+            "sync_status" => self.cmd_sync_status(),
             other => Err(PortError {
                 code: -1,
                 message: format!("unknown command: {}", other),
             }),
+        };
+        if result.is_ok() && Self::changes_mail(cmd) {
+            self.mark_cache_stale();
+        }
+        result
+    }
+
+    fn subscribe(&self) -> Result<EventStream, PortError> {
+        let login = self
+            .login
+            .lock()
+            .map_err(PortError::msg)?
+            .clone()
+            .ok_or_else(|| PortError::msg(self.not_connected()))?;
+        spawn_watcher("mail", move |sink, ready| watch_mail(login, sink, ready))
+    }
+}
+
+/// Where the watcher has got to: the last Email and Mailbox states it has
+/// reported changes up to, and the mailboxes as of the latter.
+struct WatchState {
+    email_state: String,
+    mailbox_state: String,
+    mailboxes: Vec<MailboxInfo>,
+}
+
+/// Push events for `subscribe`.
+///
+/// The session's EventSource announces new Email and Mailbox states; Email/changes
+/// and Mailbox/changes since the last reported state then say what happened.
+/// A dropped push connection is reopened, and the changes made in between are
+/// caught up from the kept states.
+async fn watch_mail(login: Login, sink: EventSink, ready: mpsc::Sender<Result<(), String>>) {
+    let setup = async {
+        let client = MailAccount::connect_inner(&login).await?;
+        let state = watch_baseline(&client).await?;
+        let stream = Box::pin(open_event_source(&client).await?);
+        Ok::<_, Box<dyn std::error::Error>>((client, state, stream))
+    };
+
+    let (client, mut state, mut stream) = match setup.await {
+        Ok(running) => {
+            let _ = ready.send(Ok(()));
+            running
+        }
+        Err(e) => {
+            let _ = ready.send(Err(format!("mail push failed: {}", e)));
+            return;
+        }
+    };
+
+    let account_id = client.default_account_id().to_string();
+    loop {
+        tokio::select! {
+            pushed = stream.next() => {
+                let changes = match pushed {
+                    Some(Ok(changes)) => changes,
+                    Some(Err(e)) => {
+                        sink.emit("error", PortValue::String(format!("push: {}", e)));
+                        continue;
+                    }
+                    None => {
+                        // Server closed the connection; reopen it and catch up
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                        match open_event_source(&client).await {
+                            Ok(reopened) => stream = Box::pin(reopened),
+                            Err(e) => sink.emit("error", PortValue::String(format!("push: {}", e))),
+                        }
+                        let caught_up = async {
+                            watch_email_changes(&client, &sink, &mut state).await?;
+                            watch_mailbox_changes(&client, &sink, &mut state).await
+                        };
+                        if let Err(e) = caught_up.await {
+                            sink.emit("error", PortValue::String(e.to_string()));
+                        }
+                        continue;
+                    }
+                };
+                let Some(account_changes) = changes.changes(&account_id) else { continue };
+                let (mut email, mut mailbox) = (false, false);
+                for (type_state, new_state) in account_changes {
+                    match type_state {
+                        TypeState::Email => email |= *new_state != state.email_state,
+                        TypeState::Mailbox => mailbox |= *new_state != state.mailbox_state,
+                        _ => {}
+                    }
+                }
+                // Mailboxes first, so new mail is reported with current names
+                if mailbox {
+                    if let Err(e) = watch_mailbox_changes(&client, &sink, &mut state).await {
+                        sink.emit("error", PortValue::String(e.to_string()));
+                    }
+                }
+                if email {
+                    if let Err(e) = watch_email_changes(&client, &sink, &mut state).await {
+                        sink.emit("error", PortValue::String(e.to_string()));
+                    }
+                }
+            }
+            _ = sink.closed() => break,
+        }
+    }
+}
+
+async fn open_event_source(
+    client: &Client,
+) -> jmap_client::Result<
+    impl futures_util::Stream<Item = jmap_client::Result<jmap_client::event_source::Changes>>,
+> {
+    client
+        .event_source(
+            Some([TypeState::Email, TypeState::Mailbox]),
+            false,
+            Some(60),
+            None,
+        )
+        .await
+}
+
+/// The current Email and Mailbox states, so only later changes are reported.
+async fn watch_baseline(client: &Client) -> jmap_client::Result<WatchState> {
+    let mut request = client.build();
+    request.get_email().ids(Vec::<String>::new());
+    request.get_mailbox().properties([
+        mailbox::Property::Id,
+        mailbox::Property::Name,
+        mailbox::Property::Role,
+    ]);
+
+    let mut responses = request.send().await?.unwrap_method_responses();
+    let mut mailboxes = responses
+        .pop()
+        .map(|r| r.unwrap_get_mailbox())
+        .transpose()?
+        .ok_or_else(|| jmap_client::Error::Internal("empty response".into()))?;
+    let emails = responses
+        .pop()
+        .map(|r| r.unwrap_get_email())
+        .transpose()?
+        .ok_or_else(|| jmap_client::Error::Internal("empty response".into()))?;
+
+    Ok(WatchState {
+        email_state: emails.state().to_string(),
+        mailbox_state: mailboxes.state().to_string(),
+        mailboxes: mailboxes
+            .take_list()
+            .iter()
+            .map(|mb| MailboxInfo {
+                id: mb.id().unwrap_or("").to_string(),
+                name: mb.name().unwrap_or("").to_string(),
+                role: MailAccount::role_name(mb),
+            })
+            .collect(),
+    })
+}
+
+/// Report Email changes since the kept state: `new_mail` for messages that
+/// arrived, `mail_created` for ones we stored (drafts, sent copies),
+/// `mail_updated` (read, flagged, moved) and `mail_destroyed`.
+async fn watch_email_changes(
+    client: &Client,
+    sink: &EventSink,
+    state: &mut WatchState,
+) -> jmap_client::Result<()> {
+    loop {
+        let changes = client
+            .email_changes(state.email_state.as_str(), Some(CHANGES_LIMIT))
+            .await?;
+
+        let ids: Vec<&str> = changes
+            .created()
+            .iter()
+            .chain(changes.updated())
+            .map(|id| id.as_str())
+            .collect();
+        if !ids.is_empty() {
+            let mut request = client.build();
+            request.get_email().ids(ids).properties(WATCH_PROPERTIES);
+            let emails = request.send_single::<EmailGetResponse>().await?.take_list();
+            for msg in &emails {
+                let created = changes
+                    .created()
+                    .iter()
+                    .any(|id| Some(id.as_str()) == msg.id());
+                let (event, data) = watch_email_event(msg, created, &state.mailboxes);
+                sink.emit(event, data);
+            }
+        }
+        for id in changes.destroyed() {
+            let mut map = HashMap::new();
+            map.insert("id".into(), PortValue::String(id.clone()));
+            sink.emit("mail_destroyed", PortValue::Map(map));
+        }
+
+        state.email_state = changes.new_state().to_string();
+        if !changes.has_more_changes() {
+            return Ok(());
+        }
+    }
+}
+
+/// An email change as an event: its summary plus mailboxes, keywords and
+/// unread state.
+fn watch_email_event(
+    msg: &email::Email<jmap_client::Get>,
+    created: bool,
+    mailboxes: &[MailboxInfo],
+) -> (&'static str, PortValue) {
+    let in_mailboxes: Vec<&MailboxInfo> = msg
+        .mailbox_ids()
+        .iter()
+        .filter_map(|id| mailboxes.iter().find(|mb| mb.id == *id))
+        .collect();
+    let keywords = msg.keywords();
+    let ours = keywords.contains(&"$draft")
+        || in_mailboxes
+            .iter()
+            .any(|mb| mb.role == "sent" || mb.role == "drafts");
+
+    let mut summary = MailAccount::email_to_summary(msg);
+    if let PortValue::Map(map) = &mut summary {
+        map.insert(
+            "mailboxes".into(),
+            PortValue::List(
+                in_mailboxes
+                    .iter()
+                    .map(|mb| PortValue::String(mb.name.clone()))
+                    .collect(),
+            ),
+        );
+        map.insert(
+            "unread".into(),
+            PortValue::Bool(!keywords.contains(&"$seen")),
+        );
+        map.insert(
+            "keywords".into(),
+            PortValue::List(
+                keywords
+                    .iter()
+                    .map(|k| PortValue::String(k.to_string()))
+                    .collect(),
+            ),
+        );
+    }
+
+    let event = match (created, ours) {
+        (true, false) => "new_mail",
+        (true, true) => "mail_created",
+        (false, _) => "mail_updated",
+    };
+    (event, summary)
+}
+
+/// Report Mailbox changes since the kept state as `mailbox_updated` (with
+/// counts) and `mailbox_destroyed`, keeping the watcher's mailbox names current.
+async fn watch_mailbox_changes(
+    client: &Client,
+    sink: &EventSink,
+    state: &mut WatchState,
+) -> jmap_client::Result<()> {
+    loop {
+        let changes = client
+            .mailbox_changes(state.mailbox_state.as_str(), CHANGES_LIMIT)
+            .await?;
+
+        let ids: Vec<&str> = changes
+            .created()
+            .iter()
+            .chain(changes.updated())
+            .map(|id| id.as_str())
+            .collect();
+        if !ids.is_empty() {
+            let mut request = client.build();
+            request.get_mailbox().ids(ids).properties([
+                mailbox::Property::Id,
+                mailbox::Property::Name,
+                mailbox::Property::Role,
+                mailbox::Property::TotalEmails,
+                mailbox::Property::UnreadEmails,
+            ]);
+            let list = request
+                .send_single::<MailboxGetResponse>()
+                .await?
+                .take_list();
+            for mb in &list {
+                let info = MailboxInfo {
+                    id: mb.id().unwrap_or("").to_string(),
+                    name: mb.name().unwrap_or("").to_string(),
+                    role: MailAccount::role_name(mb),
+                };
+                let mut map = HashMap::new();
+                map.insert("id".into(), PortValue::String(info.id.clone()));
+                map.insert("name".into(), PortValue::String(info.name.clone()));
+                map.insert("role".into(), PortValue::String(info.role.clone()));
+                map.insert("total".into(), PortValue::Int(mb.total_emails() as i64));
+                map.insert("unread".into(), PortValue::Int(mb.unread_emails() as i64));
+                sink.emit("mailbox_updated", PortValue::Map(map));
+
+                state.mailboxes.retain(|known| known.id != info.id);
+                state.mailboxes.push(info);
+            }
+        }
+        for id in changes.destroyed() {
+            let mut map = HashMap::new();
+            map.insert("id".into(), PortValue::String(id.clone()));
+            if let Some(known) = state.mailboxes.iter().find(|mb| mb.id == *id) {
+                map.insert("name".into(), PortValue::String(known.name.clone()));
+            }
+            sink.emit("mailbox_destroyed", PortValue::Map(map));
+            state.mailboxes.retain(|mb| mb.id != *id);
+        }
+
+        state.mailbox_state = changes.new_state().to_string();
+        if !changes.has_more_changes() {
+            return Ok(());
         }
     }
 }
@@ -1,6 +1,8 @@
 pub mod input;
 pub mod clipboard;
+pub mod desktops;
 pub mod mail;
 pub mod notify;
+pub mod outputs;
 pub mod screenshot;
 pub mod windows;
@@ -1,11 +1,64 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{mpsc, Arc, Mutex, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures_util::StreamExt;
+use zbus::zvariant::Value;
 
 use crate::port::*;
 
+const NOTIFICATIONS_SERVICE: &str = "org.freedesktop.Notifications";
+const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+const NOTIFICATIONS_INTERFACE: &str = "org.freedesktop.Notifications";
+
+/// Notifications kept for `history`.
+const HISTORY_LIMIT: usize = 200;
+
+/// How often the history recorder checks whether its port is gone.
+const HISTORY_POLL: Duration = Duration::from_millis(500);
+
+/// Notify calls seen but not yet answered; more means replies are being lost.
+const PENDING_LIMIT: usize = 64;
+
 /// Notify port — desktop notifications via freedesktop D-Bus interface.
 pub struct NotifyPort {
     rt: tokio::runtime::Runtime,
     connection: zbus::Connection,
+    /// Notifications from every application since recording started, oldest first.
+    history: Arc<Mutex<VecDeque<PortValue>>>,
+    /// Whether the history recorder is running; it starts on the first
+    /// `history` or `subscribe` rather than with the port.
+    recording: Mutex<bool>,
+}
+
+/// A notification to show.
+struct Notification<'a> {
+    /// ID of a shown notification to replace in place, 0 for a new one.
+    replaces_id: u32,
+    title: String,
+    body: &'a str,
+    icon: &'a str,
+    timeout_ms: i32,
+    /// `(key, label)` pairs shown as buttons; the key comes back in ActionInvoked.
+    actions: Vec<(String, String)>,
+    hints: HashMap<&'static str, Value<'static>>,
+}
+
+impl Notification<'_> {
+    fn from_args(args: &HashMap<String, String>, default_timeout: i32) -> Result<Notification<'_>, PortError> {
+        Ok(Notification {
+            replaces_id: 0,
+            title: required_arg(args, "title")?,
+            body: args.get("body").map(|s| s.as_str()).unwrap_or(""),
+            icon: args.get("icon").map(|s| s.as_str()).unwrap_or("dialog-information"),
+            timeout_ms: args
+                .get("timeout")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default_timeout),
+            actions: args.get("actions").map(|a| parse_actions(a)).unwrap_or_default(),
+            hints: parse_hints(args)?,
+        })
+    }
 }
 
 impl NotifyPort {
@@ -14,41 +67,108 @@ impl NotifyPort {
             .enable_all()
             .build()?;
         let connection = rt.block_on(zbus::Connection::session())?;
-        Ok(Self { rt, connection })
+        Ok(Self {
+            rt,
+            connection,
+            history: Arc::new(Mutex::new(VecDeque::new())),
+            recording: Mutex::new(false),
+        })
+    }
+
+    /// Start recording history unless already running. Ports that only send
+    /// never pay for a bus monitor.
+    fn start_recording(&self) -> Result<(), PortError> {
+        let mut recording = self.recording.lock().unwrap_or_else(|e| e.into_inner());
+        if !*recording {
+            record_history(&self.history)?;
+            *recording = true;
+        }
+        Ok(())
+    }
+
+    fn history(&self, limit: Option<usize>, app: Option<&str>) -> PortValue {
+        let history = self.history.lock().unwrap_or_else(|e| e.into_inner());
+        let matching: Vec<&PortValue> = history
+            .iter()
+            .filter(|n| app.is_none_or(|app| field_str(n, "app").is_some_and(|a| a.eq_ignore_ascii_case(app))))
+            .collect();
+        let skip = limit.map_or(0, |l| matching.len().saturating_sub(l));
+        PortValue::List(matching.into_iter().skip(skip).cloned().collect())
     }
 
-    fn send_notification(
-        &self,
-        title: &str,
-        body: &str,
-        icon: &str,
-        timeout_ms: i32,
-    ) -> Result<u32, PortError> {
+    fn send_notification(&self, notification: &Notification) -> Result<u32, PortError> {
         self.rt.block_on(async {
-            let proxy = zbus::Proxy::new(
-                &self.connection,
-                "org.freedesktop.Notifications",
-                "/org/freedesktop/Notifications",
-                "org.freedesktop.Notifications",
-            )
-            .await
-            .map_err(|e| PortError { code: -1, message: e.to_string() })?;
-
-            // Notify(app_name, replaces_id, icon, summary, body, actions, hints, timeout)
-            let actions: Vec<&str> = vec![];
-            let hints: HashMap<&str, zbus::zvariant::Value> = HashMap::new();
-
-            let reply: zbus::Message = proxy
-                .call_method("Notify", &("AppMesh", 0u32, icon, title, body, actions, hints, timeout_ms))
-                .await
-                .map_err(|e| PortError { code: -1, message: e.to_string() })?;
-
-            let body = reply.body();
-            let notification_id: u32 = body
-                .deserialize()
-                .map_err(|e| PortError { code: -1, message: e.to_string() })?;
-
-            Ok(notification_id)
+            let proxy = notifications_proxy(&self.connection).await.map_err(PortError::msg)?;
+            notify(&proxy, notification).await.map_err(PortError::msg)
+        })
+    }
+
+    fn close_notification(&self, id: u32) -> Result<(), PortError> {
+        self.rt.block_on(async {
+            let proxy = notifications_proxy(&self.connection).await.map_err(PortError::msg)?;
+            proxy.call::<_, _, ()>("CloseNotification", &(id,)).await.map_err(PortError::msg)
+        })
+    }
+
+    fn capabilities(&self) -> PortResult {
+        self.rt.block_on(async {
+            let proxy = notifications_proxy(&self.connection).await.map_err(PortError::msg)?;
+            let caps: Vec<String> = proxy.call("GetCapabilities", &()).await.map_err(PortError::msg)?;
+            Ok(PortValue::List(caps.into_iter().map(PortValue::String).collect()))
+        })
+    }
+
+    fn server_info(&self) -> PortResult {
+        self.rt.block_on(async {
+            let proxy = notifications_proxy(&self.connection).await.map_err(PortError::msg)?;
+            let (name, vendor, version, spec_version): (String, String, String, String) =
+                proxy.call("GetServerInformation", &()).await.map_err(PortError::msg)?;
+            let mut map = HashMap::new();
+            map.insert("name".into(), PortValue::String(name));
+            map.insert("vendor".into(), PortValue::String(vendor));
+            map.insert("version".into(), PortValue::String(version));
+            map.insert("spec_version".into(), PortValue::String(spec_version));
+            Ok(PortValue::Map(map))
+        })
+    }
+
+    /// Show a notification with action buttons and block until the user picks
+    /// one, dismisses it, or `wait` runs out (which closes it).
+    fn ask(&self, notification: &Notification, wait: Duration) -> PortResult {
+        if notification.actions.is_empty() {
+            return Err(PortError::msg("missing 'actions' argument"));
+        }
+        self.rt.block_on(async {
+            let proxy = notifications_proxy(&self.connection).await.map_err(PortError::msg)?;
+            // Listen before sending so a quick click cannot be missed
+            let mut invoked = proxy.receive_signal("ActionInvoked").await.map_err(PortError::msg)?;
+            let mut closed = proxy.receive_signal("NotificationClosed").await.map_err(PortError::msg)?;
+            let id = notify(&proxy, notification).await.map_err(PortError::msg)?;
+
+            let deadline = tokio::time::sleep(wait);
+            tokio::pin!(deadline);
+            loop {
+                tokio::select! {
+                    Some(msg) = invoked.next() => {
+                        let Ok((nid, key)) = msg.body().deserialize::<(u32, String)>() else { continue };
+                        if nid == id {
+                            // Servers normally close on click, but resident ones may not
+                            let _: Result<(), _> = proxy.call("CloseNotification", &(id,)).await;
+                            return Ok(answer(id, Some(key), "action"));
+                        }
+                    }
+                    Some(msg) = closed.next() => {
+                        let Ok((nid, reason)) = msg.body().deserialize::<(u32, u32)>() else { continue };
+                        if nid == id {
+                            return Ok(answer(id, None, close_reason(reason)));
+                        }
+                    }
+                    _ = &mut deadline => {
+                        let _: Result<(), _> = proxy.call("CloseNotification", &(id,)).await;
+                        return Ok(answer(id, None, "timeout"));
+                    }
+                }
+            }
         })
     }
 }
@@ -66,32 +186,110 @@ impl AppMeshPort for NotifyPort {
             CommandDef {
                 name: "send".into(),
                 description: "Send a desktop notification".into(),
-                params: vec![
+                params: [
+                    ParamDef { name: "title".into(), description: "Notification title".into(), required: true },
+                    ParamDef { name: "body".into(), description: "Notification body text".into(), required: false },
+                    ParamDef { name: "icon".into(), description: "Icon name (e.g. dialog-information)".into(), required: false },
+                    ParamDef { name: "timeout".into(), description: "Timeout in ms (-1=server default, 0=never)".into(), required: false },
+                    ParamDef { name: "actions".into(), description: "Action buttons as key:Label pairs, e.g. yes:Approve,no:Reject".into(), required: false },
+                ]
+                .into_iter()
+                .chain(hint_params())
+                .collect(),
+            },
+            CommandDef {
+                name: "update".into(),
+                description: "Replace a shown notification in place (e.g. to advance progress)".into(),
+                params: [
+                    ParamDef { name: "id".into(), description: "Notification ID from send".into(), required: true },
                     ParamDef { name: "title".into(), description: "Notification title".into(), required: true },
                     ParamDef { name: "body".into(), description: "Notification body text".into(), required: false },
                     ParamDef { name: "icon".into(), description: "Icon name (e.g. dialog-information)".into(), required: false },
                     ParamDef { name: "timeout".into(), description: "Timeout in ms (-1=server default, 0=never)".into(), required: false },
+                    ParamDef { name: "actions".into(), description: "Action buttons as key:Label pairs, e.g. yes:Approve,no:Reject".into(), required: false },
+                ]
+                .into_iter()
+                .chain(hint_params())
+                .collect(),
+            },
+            CommandDef {
+                name: "close".into(),
+                description: "Close a notification".into(),
+                params: vec![
+                    ParamDef { name: "id".into(), description: "Notification ID from send".into(), required: true },
+                ],
+            },
+            CommandDef {
+                name: "capabilities".into(),
+                description: "List features the notification server supports".into(),
+                params: vec![],
+            },
+            CommandDef {
+                name: "server_info".into(),
+                description: "Notification server name, vendor, version and spec version".into(),
+                params: vec![],
+            },
+            CommandDef {
+                name: "history".into(),
+                description: "Notifications shown by any application since history or watch was first used, oldest first".into(),
+                params: vec![
+                    ParamDef { name: "limit".into(), description: "Return only the most recent N".into(), required: false },
+                    ParamDef { name: "app".into(), description: "Only notifications from this application name".into(), required: false },
                 ],
             },
+            CommandDef {
+                name: "ask".into(),
+                description: "Show a notification with action buttons and wait for the user's choice".into(),
+                params: vec![
+                    ParamDef { name: "title".into(), description: "Notification title".into(), required: true },
+                    ParamDef { name: "actions".into(), description: "Action buttons as key:Label pairs, e.g. yes:Approve,no:Reject".into(), required: true },
+                    ParamDef { name: "body".into(), description: "Notification body text".into(), required: false },
+                    ParamDef { name: "icon".into(), description: "Icon name (e.g. dialog-question)".into(), required: false },
+                    ParamDef { name: "wait".into(), description: "Ms to wait for an answer before closing it (default: 60000)".into(), required: false },
+                ]
+                .into_iter()
+                .chain(hint_params())
+                .collect(),
+            },
         ]
     }
 
     fn execute(&self, cmd: &str, args: &HashMap<String, String>) -> PortResult {
         match cmd {
             "send" => {
-                let title = args.get("title").ok_or_else(|| PortError {
-                    code: -1,
-                    message: "missing 'title' argument".into(),
-                })?;
-                let body = args.get("body").map(|s| s.as_str()).unwrap_or("");
-                let icon = args.get("icon").map(|s| s.as_str()).unwrap_or("dialog-information");
-                let timeout_ms: i32 = args
-                    .get("timeout")
+                let notification = Notification::from_args(args, -1)?;
+                let id = self.send_notification(&notification)?;
+                Ok(id_value(id))
+            }
+            "update" => {
+                let mut notification = Notification::from_args(args, -1)?;
+                notification.replaces_id = id_arg(args)?;
+                let id = self.send_notification(&notification)?;
+                Ok(id_value(id))
+            }
+            "close" => {
+                let id = id_arg(args)?;
+                self.close_notification(id)?;
+                Ok(id_value(id))
+            }
+            "capabilities" => self.capabilities(),
+            "server_info" => self.server_info(),
+            "history" => {
+                let limit = match args.get("limit") {
+                    Some(v) => Some(v.parse().map_err(|_| PortError::msg(format!("invalid 'limit': {}", v)))?),
+                    None => None,
+                };
+                self.start_recording()?;
+                Ok(self.history(limit, args.get("app").map(|s| s.as_str())))
+            }
+            "ask" => {
+                // Never expire on its own; `wait` decides how long to keep it up
+                let notification = Notification::from_args(args, 0)?;
+                let wait_ms: u64 = args
+                    .get("wait")
                     .and_then(|v| v.parse().ok())
-                    .unwrap_or(-1);
-
-                let id = self.send_notification(title, body, icon, timeout_ms)?;
-                Ok(PortValue::String(format!("notification sent (id: {})", id)))
+                    .unwrap_or(60000);
+                self.ask(&notification, Duration::from_millis(wait_ms))
             }
             other => Err(PortError {
                 code: -1,
@@ -99,4 +297,343 @@ impl AppMeshPort for NotifyPort {
             }),
         }
     }
+
+    fn subscribe(&self) -> Result<EventStream, PortError> {
+        self.start_recording()?;
+        spawn_watcher("notify", watch_notifications)
+    }
+}
+
+/// Keep `history` filled from a notification watcher of its own.
+///
+/// The consumer thread holds the history weakly and checks it between waits,
+/// so dropping the port ends the thread within `HISTORY_POLL` and, with its
+/// event stream, the watcher.
+fn record_history(history: &Arc<Mutex<VecDeque<PortValue>>>) -> Result<(), PortError> {
+    let events = spawn_watcher("notify", watch_notifications)?;
+    let history: Weak<Mutex<VecDeque<PortValue>>> = Arc::downgrade(history);
+    std::thread::Builder::new()
+        .name("appmesh-notify-history".into())
+        .spawn(move || loop {
+            let event = events.next(HISTORY_POLL);
+            let Some(history) = history.upgrade() else { break };
+            if let Some(event) = event.filter(|e| e.event == "notification") {
+                let mut history = history.lock().unwrap_or_else(|e| e.into_inner());
+                if history.len() >= HISTORY_LIMIT {
+                    history.pop_front();
+                }
+                history.push_back(event.data);
+            }
+        })
+        .map_err(PortError::msg)?;
+    Ok(())
+}
+
+/// Stream `notification`, `action_invoked` and `closed` events for all
+/// applications.
+///
+/// Notification servers may send ActionInvoked/NotificationClosed only to the
+/// application that owns the notification (Plasma does), and Notify calls are
+/// addressed to the server, so an ordinary match rule would miss most of this.
+/// The watcher turns its connection into a bus monitor instead. A notification
+/// is reported once the server's reply gives it an ID.
+async fn watch_notifications(sink: EventSink, ready: mpsc::Sender<Result<(), String>>) {
+    let setup = async {
+        let connection = zbus::Connection::session().await?;
+        // Create the stream first: a monitor can no longer add match rules
+        let stream = zbus::MessageStream::from(&connection);
+        let rules = [
+            zbus::MatchRule::builder()
+                .msg_type(zbus::message::Type::MethodCall)
+                .interface(NOTIFICATIONS_INTERFACE)?
+                .member("Notify")?
+                .build(),
+            zbus::MatchRule::builder()
+                .msg_type(zbus::message::Type::MethodReturn)
+                .sender(NOTIFICATIONS_SERVICE)?
+                .build(),
+            signal_rule("ActionInvoked")?,
+            signal_rule("NotificationClosed")?,
+        ];
+        zbus::fdo::MonitoringProxy::new(&connection)
+            .await?
+            .become_monitor(&rules, 0)
+            .await?;
+        Ok::<_, zbus::Error>((connection, stream))
+    };
+
+    let (_connection, mut stream) = match setup.await {
+        Ok(running) => {
+            let _ = ready.send(Ok(()));
+            running
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e.to_string()));
+            return;
+        }
+    };
+
+    // Notify calls awaiting their reply, by (caller, serial)
+    let mut pending: HashMap<(String, u32), HashMap<String, PortValue>> = HashMap::new();
+    loop {
+        tokio::select! {
+            Some(Ok(msg)) = stream.next() => {
+                let header = msg.header();
+                match header.message_type() {
+                    zbus::message::Type::MethodCall => {
+                        let (Some(sender), Some(map)) = (header.sender(), notify_call(&msg)) else { continue };
+                        if pending.len() >= PENDING_LIMIT {
+                            pending.clear();
+                        }
+                        pending.insert((sender.to_string(), header.primary().serial_num().get()), map);
+                    }
+                    zbus::message::Type::MethodReturn => {
+                        let (Some(dest), Some(serial)) = (header.destination(), header.reply_serial()) else { continue };
+                        let Some(mut map) = pending.remove(&(dest.to_string(), serial.get())) else { continue };
+                        let Ok((id,)) = msg.body().deserialize::<(u32,)>() else { continue };
+                        map.insert("id".into(), PortValue::Int(id as i64));
+                        sink.emit("notification", PortValue::Map(map));
+                    }
+                    _ => {
+                        if let Some((event, data)) = notification_event(&msg) {
+                            sink.emit(event, data);
+                        }
+                    }
+                }
+            }
+            _ = sink.closed() => break,
+        }
+    }
+}
+
+/// Notify(app_name, replaces_id, icon, summary, body, actions, hints, timeout)
+type NotifyArgs = (
+    String,
+    u32,
+    String,
+    String,
+    String,
+    Vec<String>,
+    HashMap<String, zbus::zvariant::OwnedValue>,
+    i32,
+);
+
+/// Record of a Notify call seen on the bus, minus the ID the reply carries.
+fn notify_call(msg: &zbus::Message) -> Option<HashMap<String, PortValue>> {
+    let header = msg.header();
+    if header.interface()?.as_str() != NOTIFICATIONS_INTERFACE || header.member()?.as_str() != "Notify" {
+        return None;
+    }
+    let (app, replaces_id, icon, title, body, actions, hints, timeout): NotifyArgs =
+        msg.body().deserialize().ok()?;
+
+    let mut map = HashMap::new();
+    map.insert("app".into(), PortValue::String(app));
+    map.insert("sender".into(), PortValue::String(header.sender()?.to_string()));
+    map.insert("title".into(), PortValue::String(title));
+    map.insert("body".into(), PortValue::String(body));
+    map.insert("icon".into(), PortValue::String(icon));
+    map.insert("timeout".into(), PortValue::Int(timeout as i64));
+    if replaces_id != 0 {
+        map.insert("replaces_id".into(), PortValue::Int(replaces_id as i64));
+    }
+    // Actions come as flat key, label pairs
+    let actions = actions
+        .chunks(2)
+        .map(|pair| {
+            let mut action = HashMap::new();
+            action.insert("key".into(), PortValue::String(pair[0].clone()));
+            action.insert("label".into(), PortValue::String(pair.get(1).cloned().unwrap_or_default()));
+            PortValue::Map(action)
+        })
+        .collect();
+    map.insert("actions".into(), PortValue::List(actions));
+    let hint_str = |name: &str| hints.get(name).and_then(|v| <&str>::try_from(v).ok()).map(str::to_string);
+    if let Some(urgency) = hints.get("urgency").and_then(|v| u8::try_from(v).ok()) {
+        let name = match urgency {
+            0 => "low",
+            2 => "critical",
+            _ => "normal",
+        };
+        map.insert("urgency".into(), PortValue::String(name.into()));
+    }
+    if let Some(category) = hint_str("category") {
+        map.insert("category".into(), PortValue::String(category));
+    }
+    if let Some(entry) = hint_str("desktop-entry") {
+        map.insert("desktop_entry".into(), PortValue::String(entry));
+    }
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    map.insert("time".into(), PortValue::Int(time as i64));
+    Some(map)
+}
+
+fn signal_rule(member: &'static str) -> zbus::Result<zbus::MatchRule<'static>> {
+    Ok(zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface(NOTIFICATIONS_INTERFACE)?
+        .member(member)?
+        .build())
+}
+
+fn notification_event(msg: &zbus::Message) -> Option<(&'static str, PortValue)> {
+    let header = msg.header();
+    if header.message_type() != zbus::message::Type::Signal
+        || header.interface()?.as_str() != NOTIFICATIONS_INTERFACE
+    {
+        return None;
+    }
+    let body = msg.body();
+    let mut map = HashMap::new();
+    let event = match header.member()?.as_str() {
+        "ActionInvoked" => {
+            let (id, key): (u32, String) = body.deserialize().ok()?;
+            map.insert("id".into(), PortValue::Int(id as i64));
+            map.insert("action".into(), PortValue::String(key));
+            "action_invoked"
+        }
+        "NotificationClosed" => {
+            let (id, reason): (u32, u32) = body.deserialize().ok()?;
+            map.insert("id".into(), PortValue::Int(id as i64));
+            map.insert("reason".into(), PortValue::String(close_reason(reason).into()));
+            "closed"
+        }
+        _ => return None,
+    };
+    Some((event, PortValue::Map(map)))
+}
+
+async fn notifications_proxy(connection: &zbus::Connection) -> zbus::Result<zbus::Proxy<'static>> {
+    zbus::Proxy::new(connection, NOTIFICATIONS_SERVICE, NOTIFICATIONS_PATH, NOTIFICATIONS_INTERFACE).await
+}
+
+async fn notify(proxy: &zbus::Proxy<'_>, n: &Notification<'_>) -> zbus::Result<u32> {
+    // Notify(app_name, replaces_id, icon, summary, body, actions, hints, timeout)
+    let actions: Vec<&str> = n
+        .actions
+        .iter()
+        .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+        .collect();
+    proxy
+        .call(
+            "Notify",
+            &("AppMesh", n.replaces_id, n.icon, n.title.as_str(), n.body, actions, &n.hints, n.timeout_ms),
+        )
+        .await
+}
+
+/// Optional hint arguments shared by every command that shows a notification.
+fn hint_params() -> Vec<ParamDef> {
+    vec![
+        ParamDef { name: "urgency".into(), description: "low, normal or critical".into(), required: false },
+        ParamDef { name: "category".into(), description: "Notification category (e.g. transfer.complete, im.received)".into(), required: false },
+        ParamDef { name: "desktop_entry".into(), description: "Desktop file name of the sending app, without .desktop".into(), required: false },
+        ParamDef { name: "transient".into(), description: "true to skip the notification history".into(), required: false },
+        ParamDef { name: "resident".into(), description: "true to keep it open after an action is invoked".into(), required: false },
+        ParamDef { name: "image_path".into(), description: "Image shown in the notification, as a file path, file:// URI or icon name".into(), required: false },
+        ParamDef { name: "image".into(), description: "Image file sent inline as pixel data".into(), required: false },
+        ParamDef { name: "progress".into(), description: "Progress percentage 0-100 (KDE)".into(), required: false },
+    ]
+}
+
+/// Build the Notify hints dictionary from the hint arguments.
+fn parse_hints(args: &HashMap<String, String>) -> Result<HashMap<&'static str, Value<'static>>, PortError> {
+    let mut hints = HashMap::new();
+    if let Some(v) = args.get("urgency") {
+        let level: u8 = match v.as_str() {
+            "low" | "0" => 0,
+            "normal" | "1" => 1,
+            "critical" | "2" => 2,
+            _ => return Err(PortError::msg(format!("invalid 'urgency': {} (expected low, normal or critical)", v))),
+        };
+        hints.insert("urgency", Value::from(level));
+    }
+    if let Some(v) = args.get("category") {
+        hints.insert("category", Value::from(v.clone()));
+    }
+    if let Some(v) = args.get("desktop_entry") {
+        hints.insert("desktop-entry", Value::from(v.trim_end_matches(".desktop").to_string()));
+    }
+    if let Some(v) = args.get("transient") {
+        hints.insert("transient", Value::from(v == "true" || v == "1"));
+    }
+    if let Some(v) = args.get("resident") {
+        hints.insert("resident", Value::from(v == "true" || v == "1"));
+    }
+    if let Some(v) = args.get("image_path") {
+        hints.insert("image-path", Value::from(v.clone()));
+    }
+    if let Some(path) = args.get("image") {
+        hints.insert("image-data", image_data(path)?);
+    }
+    if let Some(v) = args.get("progress") {
+        let percent: i32 = v
+            .parse()
+            .ok()
+            .filter(|p| (0..=100).contains(p))
+            .ok_or_else(|| PortError::msg(format!("invalid 'progress': {} (expected 0-100)", v)))?;
+        hints.insert("value", Value::from(percent));
+    }
+    Ok(hints)
+}
+
+/// Load an image file as the spec's `(iiibiiay)` raw image hint.
+fn image_data(path: &str) -> Result<Value<'static>, PortError> {
+    let image = image::open(path)
+        .map_err(|e| PortError::msg(format!("failed to load image {}: {}", path, e)))?
+        .into_rgba8();
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    // (width, height, rowstride, has_alpha, bits_per_sample, channels, data)
+    Ok(Value::from((width, height, width * 4, true, 8i32, 4i32, image.into_raw())))
+}
+
+/// Parse `yes:Approve,no:Reject`; an entry without a colon is its own key.
+fn parse_actions(spec: &str) -> Vec<(String, String)> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|entry| match entry.split_once(':') {
+            Some((key, label)) => (key.trim().to_string(), label.trim().to_string()),
+            None => (entry.to_string(), entry.to_string()),
+        })
+        .collect()
+}
+
+/// Name for a NotificationClosed reason code.
+fn close_reason(reason: u32) -> &'static str {
+    match reason {
+        1 => "expired",
+        2 => "dismissed",
+        3 => "closed",
+        _ => "undefined",
+    }
+}
+
+fn field_str<'a>(value: &'a PortValue, name: &str) -> Option<&'a str> {
+    match value {
+        PortValue::Map(map) => match map.get(name) {
+            Some(PortValue::String(s)) => Some(s),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn id_arg(args: &HashMap<String, String>) -> Result<u32, PortError> {
+    let v = required_arg(args, "id")?;
+    v.parse().map_err(|_| PortError::msg(format!("invalid 'id': {}", v)))
+}
+
+fn id_value(id: u32) -> PortValue {
+    let mut map = HashMap::new();
+    map.insert("id".into(), PortValue::Int(id as i64));
+    PortValue::Map(map)
+}
+
+fn answer(id: u32, action: Option<String>, reason: &str) -> PortValue {
+    let mut map = HashMap::new();
+    map.insert("id".into(), PortValue::Int(id as i64));
+    map.insert("action".into(), action.map(PortValue::String).unwrap_or(PortValue::Null));
+    map.insert("reason".into(), PortValue::String(reason.into()));
+    PortValue::Map(map)
 }
@@ -0,0 +1,618 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use zbus::zvariant;
+
+use crate::config;
+use crate::port::*;
+
+/// Subdirectory of the appmesh config dir that output configurations are saved in.
+const KIND: &str = "outputs";
+
+/// Outputs port — monitor layout via the KScreen backend D-Bus service.
+///
+/// `org.kde.KScreen` serves the whole screen configuration as one nested
+/// `a{sv}` map (the libkscreen serialisation). It is converted to JSON for
+/// reading and editing, and sent back whole to apply changes.
+pub struct OutputsPort {
+    rt: tokio::runtime::Runtime,
+    connection: zbus::Connection,
+}
+
+/// A saved set of output settings, restored by `config_apply`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavedConfig {
+    name: String,
+    /// Unix timestamp of the save.
+    saved_at: u64,
+    outputs: Vec<SavedOutput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedOutput {
+    name: String,
+    enabled: bool,
+    #[serde(default)]
+    primary: bool,
+    x: i64,
+    y: i64,
+    scale: f64,
+    /// Clockwise rotation in degrees.
+    rotation: u32,
+    #[serde(default)]
+    mode: Option<SavedMode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedMode {
+    width: i64,
+    height: i64,
+    refresh: f64,
+}
+
+impl OutputsPort {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let connection = rt.block_on(zbus::Connection::session())?;
+        Ok(Self { rt, connection })
+    }
+
+    async fn backend(&self) -> zbus::Result<zbus::Proxy<'static>> {
+        zbus::Proxy::new(&self.connection, "org.kde.KScreen", "/backend", "org.kde.kscreen.Backend").await
+    }
+
+    /// Fetch the current screen configuration as libkscreen sends it.
+    fn raw_config(&self) -> Result<HashMap<String, zvariant::OwnedValue>, PortError> {
+        self.rt.block_on(async {
+            let proxy = self.backend().await.map_err(PortError::msg)?;
+            proxy.call("getConfig", &()).await.map_err(PortError::msg)
+        })
+    }
+
+    /// Fetch the current screen configuration as JSON.
+    fn get_config(&self) -> Result<Value, PortError> {
+        let config = self.raw_config()?;
+        Ok(Value::Object(
+            config.iter().map(|(k, v)| (k.clone(), dbus_to_json(v))).collect(),
+        ))
+    }
+
+    /// Apply a (modified) screen configuration.
+    fn set_config(&self, config: &Value) -> Result<(), PortError> {
+        let Value::Object(map) = config else {
+            return Err(PortError::msg("screen configuration is not an object"));
+        };
+        let enabled = outputs(config).filter(|o| flag(o, "enabled")).count();
+        if enabled == 0 {
+            return Err(PortError::msg("refusing to disable every output"));
+        }
+        // The current configuration says which D-Bus type each field has
+        let template = self.raw_config()?;
+        let body: HashMap<String, zvariant::Value> = map
+            .iter()
+            .filter(|(_, v)| !v.is_null())
+            .map(|(k, v)| Ok((k.clone(), json_to_dbus(v, template.get(k).map(|t| &**t), k)?)))
+            .collect::<Result<_, String>>()
+            .map_err(PortError::msg)?;
+        self.rt.block_on(async {
+            let proxy = self.backend().await.map_err(PortError::msg)?;
+            let _: HashMap<String, zvariant::OwnedValue> =
+                proxy.call("setConfig", &(body,)).await.map_err(PortError::msg)?;
+            Ok(())
+        })
+    }
+
+    fn list_outputs(&self) -> Result<PortValue, PortError> {
+        let config = self.get_config()?;
+        Ok(PortValue::List(
+            outputs(&config).map(|o| PortValue::from(output_info(o))).collect(),
+        ))
+    }
+
+    fn set_enabled(&self, name: &str, enabled: bool) -> Result<PortValue, PortError> {
+        let mut config = self.get_config()?;
+        let output = find_output_mut(&mut config, name)?;
+        if !flag(output, "connected") && enabled {
+            return Err(PortError::msg(format!("output is not connected: {}", name)));
+        }
+        output["enabled"] = json!(enabled);
+        self.set_config(&config)?;
+        let verb = if enabled { "enabled" } else { "disabled" };
+        Ok(PortValue::String(format!("{} output: {}", verb, name)))
+    }
+
+    fn config_save(&self, name: &str) -> Result<PortValue, PortError> {
+        let config = self.get_config()?;
+        let saved_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let saved = SavedConfig {
+            name: name.to_string(),
+            saved_at,
+            outputs: outputs(&config)
+                .filter(|o| flag(o, "connected"))
+                .map(saved_output)
+                .collect(),
+        };
+        let path = config::save_named(KIND, name, &saved).map_err(PortError::msg)?;
+
+        let mut map = HashMap::new();
+        map.insert("path".into(), PortValue::String(path.display().to_string()));
+        map.insert("outputs".into(), PortValue::Int(saved.outputs.len() as i64));
+        Ok(PortValue::Map(map))
+    }
+
+    /// Apply a saved configuration to the outputs that are connected now.
+    fn config_apply(&self, name: &str) -> Result<PortValue, PortError> {
+        let saved: SavedConfig = config::load_named(KIND, name).map_err(PortError::msg)?;
+        let mut config = self.get_config()?;
+        let mut applied = Vec::new();
+        let mut missing = Vec::new();
+
+        let has_primary = saved.outputs.iter().any(|o| o.primary && o.enabled);
+        for entry in &saved.outputs {
+            let Ok(output) = find_output_mut(&mut config, &entry.name) else {
+                missing.push(PortValue::String(entry.name.clone()));
+                continue;
+            };
+            apply_saved(output, entry);
+            applied.push(PortValue::String(entry.name.clone()));
+        }
+        if applied.is_empty() {
+            return Err(PortError::msg(format!("no output in '{}' is connected", name)));
+        }
+        if has_primary {
+            renumber_priorities(&mut config, &saved);
+        }
+        self.set_config(&config)?;
+
+        let mut map = HashMap::new();
+        map.insert("applied".into(), PortValue::List(applied));
+        map.insert("missing".into(), PortValue::List(missing));
+        Ok(PortValue::Map(map))
+    }
+}
+
+// Safety: OutputsPort is only used from one thread at a time via Mutex or single-threaded FFI
+unsafe impl Send for OutputsPort {}
+
+impl AppMeshPort for OutputsPort {
+    fn name(&self) -> &str {
+        "outputs"
+    }
+
+    fn commands(&self) -> Vec<CommandDef> {
+        vec![
+            CommandDef {
+                name: "list".into(),
+                description: "List outputs with geometry, scale, rotation, refresh rate and state".into(),
+                params: vec![],
+            },
+            CommandDef {
+                name: "enable".into(),
+                description: "Enable an output".into(),
+                params: vec![output_param()],
+            },
+            CommandDef {
+                name: "disable".into(),
+                description: "Disable an output".into(),
+                params: vec![output_param()],
+            },
+            CommandDef {
+                name: "config_save".into(),
+                description: "Save the current output configuration under a name".into(),
+                params: vec![
+                    ParamDef { name: "name".into(), description: "Configuration name (e.g. docked)".into(), required: true },
+                ],
+            },
+            CommandDef {
+                name: "config_apply".into(),
+                description: "Switch to a saved output configuration".into(),
+                params: vec![
+                    ParamDef { name: "name".into(), description: "Configuration name".into(), required: true },
+                ],
+            },
+            CommandDef {
+                name: "config_list".into(),
+                description: "List saved output configurations".into(),
+                params: vec![],
+            },
+        ]
+    }
+
+    fn execute(&self, cmd: &str, args: &HashMap<String, String>) -> PortResult {
+        match cmd {
+            "list" => self.list_outputs(),
+            "enable" => self.set_enabled(&required_arg(args, "output")?, true),
+            "disable" => self.set_enabled(&required_arg(args, "output")?, false),
+            "config_save" => self.config_save(&required_arg(args, "name")?),
+            "config_apply" => self.config_apply(&required_arg(args, "name")?),
+            "config_list" => Ok(PortValue::List(
+                config::list_named(KIND).into_iter().map(PortValue::String).collect(),
+            )),
+            other => Err(PortError::msg(format!("unknown command: {}", other))),
+        }
+    }
+}
+
+/// Iterate the `outputs` array of a screen configuration.
+fn outputs(config: &Value) -> impl Iterator<Item = &Value> {
+    config
+        .get("outputs")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+}
+
+fn find_output_mut<'a>(config: &'a mut Value, name: &str) -> Result<&'a mut Value, PortError> {
+    config
+        .get_mut("outputs")
+        .and_then(|v| v.as_array_mut())
+        .and_then(|list| list.iter_mut().find(|o| o["name"] == name))
+        .ok_or_else(|| PortError::msg(format!("no such output: {}", name)))
+}
+
+fn flag(value: &Value, name: &str) -> bool {
+    value.get(name).and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+fn number(value: &Value, name: &str) -> f64 {
+    value.get(name).and_then(|v| v.as_f64()).unwrap_or(0.0)
+}
+
+fn text<'a>(value: &'a Value, name: &str) -> &'a str {
+    value.get(name).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+/// libkscreen marks the primary output with priority 1; older versions use `primary`.
+fn is_primary(output: &Value) -> bool {
+    number(output, "priority") == 1.0 || flag(output, "primary")
+}
+
+/// The output's current mode, if it has one.
+fn current_mode(output: &Value) -> Option<&Value> {
+    let id = text(output, "currentModeId");
+    output
+        .get("modes")
+        .and_then(|v| v.as_array())?
+        .iter()
+        .find(|m| text(m, "id") == id)
+}
+
+fn mode_size(mode: &Value) -> (i64, i64) {
+    let size = &mode["size"];
+    (number(size, "width") as i64, number(size, "height") as i64)
+}
+
+/// KScreen rotation flags to clockwise degrees and back.
+fn rotation_degrees(rotation: i64) -> u32 {
+    match rotation {
+        2 => 90,
+        4 => 180,
+        8 => 270,
+        _ => 0,
+    }
+}
+
+fn rotation_flag(degrees: u32) -> i64 {
+    match degrees % 360 {
+        90 => 2,
+        180 => 4,
+        270 => 8,
+        _ => 1,
+    }
+}
+
+/// Summarise a libkscreen output for `list`.
+fn output_info(output: &Value) -> Value {
+    let scale = match number(output, "scale") {
+        s if s > 0.0 => s,
+        _ => 1.0,
+    };
+    let degrees = rotation_degrees(number(output, "rotation") as i64);
+    let mode = current_mode(output);
+    let (mut width, mut height) = mode.map(mode_size).unwrap_or((0, 0));
+    if degrees == 90 || degrees == 270 {
+        std::mem::swap(&mut width, &mut height);
+    }
+    let pos = &output["pos"];
+    let modes: Vec<Value> = output
+        .get("modes")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .map(|m| {
+            let (w, h) = mode_size(m);
+            json!(format!("{}x{}@{:.2}", w, h, number(m, "refreshRate")))
+        })
+        .collect();
+
+    json!({
+        "id": number(output, "id") as i64,
+        "name": text(output, "name"),
+        "enabled": flag(output, "enabled"),
+        "connected": flag(output, "connected"),
+        "primary": is_primary(output),
+        // Logical geometry, as used by window placement and pointer coordinates
+        "geometry": {
+            "x": number(pos, "x") as i64,
+            "y": number(pos, "y") as i64,
+            "width": (width as f64 / scale).round() as i64,
+            "height": (height as f64 / scale).round() as i64,
+        },
+        "mode": mode.map(|m| { let (w, h) = mode_size(m); json!({"width": w, "height": h}) }),
+        "scale": scale,
+        "rotation": degrees,
+        "refresh": mode.map(|m| (number(m, "refreshRate") * 100.0).round() / 100.0),
+        "modes": modes,
+    })
+}
+
+fn saved_output(output: &Value) -> SavedOutput {
+    let pos = &output["pos"];
+    SavedOutput {
+        name: text(output, "name").to_string(),
+        enabled: flag(output, "enabled"),
+        primary: is_primary(output),
+        x: number(pos, "x") as i64,
+        y: number(pos, "y") as i64,
+        scale: number(output, "scale"),
+        rotation: rotation_degrees(number(output, "rotation") as i64),
+        mode: current_mode(output).map(|m| {
+            let (width, height) = mode_size(m);
+            SavedMode { width, height, refresh: number(m, "refreshRate") }
+        }),
+    }
+}
+
+/// Write a saved entry's settings into a libkscreen output object.
+fn apply_saved(output: &mut Value, entry: &SavedOutput) {
+    output["enabled"] = json!(entry.enabled);
+    output["pos"] = json!({"x": entry.x, "y": entry.y});
+    if entry.scale > 0.0 {
+        output["scale"] = json!(entry.scale);
+    }
+    output["rotation"] = json!(rotation_flag(entry.rotation));
+
+    // Mode IDs are not stable across sessions; match on size and nearest refresh
+    if let Some(want) = &entry.mode {
+        let best = output
+            .get("modes")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter(|m| mode_size(m) == (want.width, want.height))
+            .min_by(|a, b| {
+                let da = (number(a, "refreshRate") - want.refresh).abs();
+                let db = (number(b, "refreshRate") - want.refresh).abs();
+                da.total_cmp(&db)
+            })
+            .map(|m| text(m, "id").to_string());
+        if let Some(id) = best {
+            output["currentModeId"] = json!(id);
+            output["followPreferredMode"] = json!(false);
+        }
+    }
+}
+
+/// Make the saved primary output priority 1 and number the rest after it.
+fn renumber_priorities(config: &mut Value, saved: &SavedConfig) {
+    let primary = saved
+        .outputs
+        .iter()
+        .find(|o| o.primary && o.enabled)
+        .map(|o| o.name.clone());
+    let Some(list) = config.get_mut("outputs").and_then(|v| v.as_array_mut()) else {
+        return;
+    };
+    let mut next = 2;
+    for output in list.iter_mut() {
+        let is_primary = primary.as_deref() == Some(text(output, "name"));
+        if is_primary {
+            output["priority"] = json!(1);
+            output["primary"] = json!(true);
+        } else {
+            let enabled = flag(output, "enabled");
+            output["priority"] = json!(if enabled { next } else { 0 });
+            output["primary"] = json!(false);
+            if enabled {
+                next += 1;
+            }
+        }
+    }
+}
+
+/// Convert a D-Bus value (as received from libkscreen) to JSON.
+fn dbus_to_json(value: &zvariant::Value) -> Value {
+    use zvariant::Value as V;
+    match value {
+        V::U8(n) => json!(n),
+        V::Bool(b) => json!(b),
+        V::I16(n) => json!(n),
+        V::U16(n) => json!(n),
+        V::I32(n) => json!(n),
+        V::U32(n) => json!(n),
+        V::I64(n) => json!(n),
+        V::U64(n) => json!(n),
+        V::F64(n) => json!(n),
+        V::Str(s) => json!(s.as_str()),
+        V::Signature(s) => json!(s.to_string()),
+        V::ObjectPath(p) => json!(p.as_str()),
+        V::Value(inner) => dbus_to_json(inner),
+        V::Array(a) => Value::Array(a.iter().map(dbus_to_json).collect()),
+        V::Dict(d) => Value::Object(
+            d.iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        V::Str(s) => s.to_string(),
+                        other => dbus_to_json(other).to_string(),
+                    };
+                    (key, dbus_to_json(v))
+                })
+                .collect(),
+        ),
+        V::Structure(s) => Value::Array(s.fields().iter().map(dbus_to_json).collect()),
+        _ => Value::Null,
+    }
+}
+
+/// Convert JSON back to the `a{sv}` / `av` shapes libkscreen deserialises.
+///
+/// Each value takes its D-Bus type from the same place in `template`, the
+/// configuration as fetched, so unsigned, 64-bit and floating-point fields
+/// survive the JSON round trip. Values with no counterpart there fall back
+/// to `i32`, `i64` or `f64`. `path` names the value in errors.
+fn json_to_dbus(
+    value: &Value,
+    template: Option<&zvariant::Value>,
+    path: &str,
+) -> Result<zvariant::Value<'static>, String> {
+    use zvariant::Value as V;
+    // Dict and array entries arrive wrapped in variants
+    let template = match template {
+        Some(V::Value(inner)) => Some(&**inner),
+        other => other,
+    };
+    let out_of_range = || format!("{}: {} is out of range for its D-Bus type", path, value);
+    Ok(match value {
+        Value::Bool(b) => V::from(*b),
+        Value::Number(n) => {
+            let int = || n.as_i64().ok_or_else(|| format!("{}: expected an integer, got {}", path, n));
+            let uint = || {
+                n.as_u64()
+                    .ok_or_else(|| format!("{}: expected an unsigned integer, got {}", path, n))
+            };
+            match template {
+                Some(V::U8(_)) => V::from(u8::try_from(uint()?).map_err(|_| out_of_range())?),
+                Some(V::I16(_)) => V::from(i16::try_from(int()?).map_err(|_| out_of_range())?),
+                Some(V::U16(_)) => V::from(u16::try_from(uint()?).map_err(|_| out_of_range())?),
+                Some(V::I32(_)) => V::from(i32::try_from(int()?).map_err(|_| out_of_range())?),
+                Some(V::U32(_)) => V::from(u32::try_from(uint()?).map_err(|_| out_of_range())?),
+                Some(V::I64(_)) => V::from(int()?),
+                Some(V::U64(_)) => V::from(uint()?),
+                Some(V::F64(_)) => V::from(n.as_f64().ok_or_else(out_of_range)?),
+                _ => match n.as_i64() {
+                    Some(i) => match i32::try_from(i) {
+                        Ok(small) => V::from(small),
+                        Err(_) => V::from(i),
+                    },
+                    None => V::from(n.as_f64().ok_or_else(out_of_range)?),
+                },
+            }
+        }
+        Value::String(s) => V::from(s.clone()),
+        Value::Array(list) => {
+            let items: Vec<&V> = match template {
+                Some(V::Array(a)) => a.iter().collect(),
+                _ => Vec::new(),
+            };
+            let values = list
+                .iter()
+                .enumerate()
+                .map(|(i, v)| {
+                    // New entries are typed like the first existing one
+                    let item = items.get(i).or(items.first()).copied();
+                    json_to_dbus(v, item, &format!("{}.{}", path, i))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            V::from(values)
+        }
+        Value::Object(map) => {
+            let field = |key: &str| match template {
+                Some(V::Dict(d)) => d
+                    .iter()
+                    .find(|(k, _)| matches!(k, V::Str(s) if s.as_str() == key))
+                    .map(|(_, v)| v),
+                _ => None,
+            };
+            let values = map
+                .iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| Ok((k.clone(), json_to_dbus(v, field(k), &format!("{}.{}", path, k))?)))
+                .collect::<Result<HashMap<String, V>, String>>()?;
+            V::from(values)
+        }
+        Value::Null => return Err(format!("{}: null is not a D-Bus value", path)),
+    })
+}
+
+fn output_param() -> ParamDef {
+    ParamDef { name: "output".into(), description: "Output name (e.g. DP-1)".into(), required: true }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dict(entries: Vec<(&str, zvariant::Value<'static>)>) -> zvariant::Value<'static> {
+        zvariant::Value::from(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect::<HashMap<String, zvariant::Value>>(),
+        )
+    }
+
+    /// A dict entry, unwrapped from its variant.
+    fn field<'a>(value: &'a zvariant::Value<'static>, key: &str) -> &'a zvariant::Value<'static> {
+        use zvariant::Value as V;
+        let V::Dict(d) = value else {
+            panic!("not a dict");
+        };
+        match d.iter().find(|(k, _)| matches!(k, V::Str(s) if s.as_str() == key)) {
+            Some((_, V::Value(inner))) => inner,
+            Some((_, v)) => v,
+            None => panic!("no field {}", key),
+        }
+    }
+
+    #[test]
+    fn json_round_trip_keeps_dbus_types() {
+        use zvariant::Value as V;
+        let output = dict(vec![
+            ("id", V::from(1i32)),
+            ("priority", V::from(2u32)),
+            ("serial", V::from(5_000_000_000u64)),
+            ("scale", V::from(1.0f64)),
+            ("enabled", V::from(true)),
+            ("name", V::from("DP-1")),
+        ]);
+        let original = dict(vec![("outputs", V::from(vec![output]))]);
+
+        let mut json = dbus_to_json(&original);
+        json["outputs"][0]["scale"] = json!(2);
+        json["outputs"][0]["priority"] = json!(3);
+        let back = json_to_dbus(&json, Some(&original), "config").unwrap();
+        let back = dbus_to_json(&back);
+        assert_eq!(back["outputs"][0]["scale"], json!(2.0));
+
+        let config = json_to_dbus(&json, Some(&original), "config").unwrap();
+        let V::Array(outputs) = field(&config, "outputs") else {
+            panic!("outputs is not an array");
+        };
+        let first = match outputs.iter().next().unwrap() {
+            V::Value(inner) => &**inner,
+            other => other,
+        };
+        assert_eq!(field(first, "priority"), &V::from(3u32));
+        assert_eq!(field(first, "serial"), &V::from(5_000_000_000u64));
+        assert_eq!(field(first, "scale"), &V::from(2.0f64));
+        assert_eq!(field(first, "id"), &V::from(1i32));
+    }
+
+    #[test]
+    fn json_to_dbus_rejects_null_and_bad_numbers() {
+        let template = dict(vec![("priority", zvariant::Value::from(1u32))]);
+        let err = json_to_dbus(&json!({"priority": -1}), Some(&template), "output").unwrap_err();
+        assert!(err.starts_with("output.priority: expected an unsigned integer"), "{}", err);
+        let err = json_to_dbus(&json!([1, null]), None, "list").unwrap_err();
+        assert_eq!(err, "list.1: null is not a D-Bus value");
+        // Null object fields are dropped, not sent
+        assert!(json_to_dbus(&json!({"a": null}), None, "o").is_ok());
+    }
+}
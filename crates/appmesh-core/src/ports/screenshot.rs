@@ -1,16 +1,111 @@
 use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
+use base64::Engine;
+
+use crate::capture::{self, Format, Options, Target};
+use crate::imaging;
 use crate::port::*;
+use crate::vision;
+
+/// How long to wait for spectacle's file to appear after it exits.
+const SPECTACLE_WAIT: Duration = Duration::from_secs(3);
+
+/// Screenshot port — KWin ScreenShot2 capture, with spectacle as a fallback.
+pub struct ScreenshotPort {
+    rt: tokio::runtime::Runtime,
+    connection: zbus::Connection,
+}
+
+/// Which capture path `take` uses.
+#[derive(Clone, Copy, PartialEq)]
+enum Backend {
+    /// KWin first, spectacle if KWin refuses or fails.
+    Auto,
+    Kwin,
+    Spectacle,
+}
 
-/// Screenshot port — Spectacle screen capture.
-pub struct ScreenshotPort;
+/// A captured and encoded image, written to disk or kept inline.
+struct Shot {
+    /// File path, unless the image was returned inline.
+    path: Option<String>,
+    /// Base64 of the encoded image when returned inline.
+    data: Option<String>,
+    width: u32,
+    height: u32,
+    format: Format,
+    size: usize,
+    /// Capture path used; `None` for processed files.
+    backend: Option<&'static str>,
+}
+
+impl Shot {
+    fn to_value(&self) -> PortValue {
+        let mut map = HashMap::new();
+        if let Some(path) = &self.path {
+            map.insert("path".into(), PortValue::String(path.clone()));
+        }
+        if let Some(data) = &self.data {
+            map.insert("data".into(), PortValue::String(data.clone()));
+        }
+        map.insert("width".into(), PortValue::Int(self.width as i64));
+        map.insert("height".into(), PortValue::Int(self.height as i64));
+        map.insert("format".into(), PortValue::String(self.format.name().into()));
+        map.insert("size".into(), PortValue::Int(self.size as i64));
+        if let Some(backend) = self.backend {
+            map.insert("backend".into(), PortValue::String(backend.into()));
+        }
+        PortValue::Map(map)
+    }
+}
+
+/// How to encode and deliver a capture.
+struct Delivery {
+    format: Format,
+    quality: u8,
+    inline: bool,
+    /// Where to write; defaults to a new file under `/run/user/<uid>/appmesh`.
+    path: Option<String>,
+}
+
+impl Delivery {
+    /// Encode `image` and either write it out or return it as base64.
+    fn finish(&self, image: &image::RgbaImage, backend: Option<&'static str>) -> Result<Shot, PortError> {
+        let bytes = capture::encode(image, self.format, self.quality).map_err(PortError::msg)?;
+        let (path, data) = if self.inline {
+            (None, Some(base64::engine::general_purpose::STANDARD.encode(&bytes)))
+        } else {
+            let path = self
+                .path
+                .clone()
+                .unwrap_or_else(|| ScreenshotPort::output_path(self.format));
+            std::fs::write(&path, &bytes).map_err(|e| PortError::msg(format!("write failed: {}", e)))?;
+            (Some(path), None)
+        };
+        Ok(Shot {
+            path,
+            data,
+            width: image.width(),
+            height: image.height(),
+            format: self.format,
+            size: bytes.len(),
+            backend,
+        })
+    }
+}
 
 impl ScreenshotPort {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self)
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let connection = rt.block_on(zbus::Connection::session())?;
+        Ok(Self { rt, connection })
     }
 
-    fn output_path() -> String {
+    fn output_path(format: Format) -> String {
         let uid = unsafe { libc::getuid() };
         let dir = format!("/run/user/{}/appmesh", uid);
         let _ = std::fs::create_dir_all(&dir);
@@ -18,10 +113,337 @@ impl ScreenshotPort {
             .duration_since(std::time::UNIX_EPOCH)
             .map(|d| d.as_millis())
             .unwrap_or(0);
-        format!("{}/screenshot_{}.png", dir, ts)
+        format!("{}/screenshot_{}.{}", dir, ts, format.extension())
+    }
+
+    fn take(&self, args: &HashMap<String, String>) -> PortResult {
+        let mode = args.get("mode").map(|s| s.as_str()).unwrap_or("fullscreen");
+        let format = match args.get("format") {
+            Some(f) => Format::parse(f).map_err(PortError::msg)?,
+            None => Format::Png,
+        };
+        let quality = quality_arg(args)?;
+        let backend = match args.get("backend").map(|s| s.as_str()).unwrap_or("auto") {
+            "auto" => Backend::Auto,
+            "kwin" => Backend::Kwin,
+            "spectacle" => Backend::Spectacle,
+            other => {
+                return Err(PortError::msg(format!(
+                    "invalid 'backend': {} (expected auto, kwin or spectacle)",
+                    other
+                )))
+            }
+        };
+        let options = Options {
+            include_cursor: bool_arg(args, "cursor", false),
+            include_decoration: bool_arg(args, "decoration", true),
+            ..Options::default()
+        };
+        let delivery = Delivery { format, quality, inline: bool_arg(args, "inline", false), path: None };
+
+        // Explicit targets win over `mode`; spectacle can only do the modes
+        let (target, spectacle_mode) = if let Some(id) = args.get("window") {
+            (Some(Target::Window(id.clone())), None)
+        } else if let Some(rect) = args.get("rect") {
+            let a = parse_area(rect)?;
+            (Some(Target::Area { x: a.x, y: a.y, width: a.width, height: a.height }), None)
+        } else if let Some(output) = args.get("output") {
+            (Some(Target::Screen(output.clone())), None)
+        } else {
+            // KWin has no interactive rectangle picker; only spectacle can do `region`
+            let target = match mode {
+                "fullscreen" => Some(Target::Workspace),
+                "screen" => Some(Target::ActiveScreen),
+                "activewindow" => Some(Target::ActiveWindow),
+                "pick_window" => Some(Target::PickWindow),
+                "pick_screen" => Some(Target::PickScreen),
+                "region" => None,
+                other => return Err(PortError::msg(format!("invalid 'mode': {}", other))),
+            };
+            (target, Some(mode))
+        };
+
+        let shot = match (target, backend) {
+            (None, Backend::Kwin) => {
+                return Err(PortError::msg("mode 'region' needs spectacle (backend=auto or spectacle)"))
+            }
+            (None, _) => Self::take_spectacle(mode, &options, &delivery)?,
+            (Some(_), Backend::Spectacle) => match spectacle_mode {
+                Some(mode) => Self::take_spectacle(mode, &options, &delivery)?,
+                None => return Err(PortError::msg("'window', 'rect' and 'output' need the KWin backend")),
+            },
+            (Some(target), Backend::Kwin) => self.take_kwin(&target, &options, &delivery)?,
+            (Some(target), Backend::Auto) => match (self.take_kwin(&target, &options, &delivery), spectacle_mode) {
+                (Ok(shot), _) => shot,
+                (Err(kwin), None) => return Err(kwin),
+                (Err(kwin), Some(mode)) => Self::take_spectacle(mode, &options, &delivery).map_err(|spectacle| {
+                    PortError::msg(format!("{}; spectacle fallback: {}", kwin.message, spectacle.message))
+                })?,
+            },
+        };
+        Ok(shot.to_value())
+    }
+
+    fn take_kwin(&self, target: &Target, options: &Options, delivery: &Delivery) -> Result<Shot, PortError> {
+        let image = self
+            .rt
+            .block_on(capture::capture(&self.connection, target, options))
+            .map_err(|e| PortError::msg(format!("KWin capture failed: {}", e)))?;
+        delivery.finish(&image, Some("kwin"))
+    }
+
+    fn take_spectacle(mode: &str, options: &Options, delivery: &Delivery) -> Result<Shot, PortError> {
+        delivery.finish(&Self::spectacle(mode, options)?, Some("spectacle"))
+    }
+
+    /// Capture with the spectacle CLI and load the result.
+    fn spectacle(mode: &str, options: &Options) -> Result<image::RgbaImage, PortError> {
+        let flag = match mode {
+            "activewindow" => "-a",
+            "screen" | "pick_screen" => "-m",
+            "pick_window" => "-u",
+            "region" => "-r",
+            _ => "-f",
+        };
+        let mut flags = vec![flag, "-b", "-n"];
+        if options.include_cursor {
+            flags.push("-p");
+        }
+        if !options.include_decoration {
+            flags.push("-e");
+        }
+
+        let path = Self::output_path(Format::Png);
+        let output = std::process::Command::new("spectacle")
+            .args(&flags)
+            .args(["-o", &path])
+            .output()
+            .map_err(|e| PortError::msg(format!("spectacle: {}", e)))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(PortError::msg(format!("spectacle failed: {}", stderr)));
+        }
+
+        // spectacle can exit before the file is fully written
+        let deadline = Instant::now() + SPECTACLE_WAIT;
+        let image = loop {
+            if let Ok(image) = image::open(&path) {
+                break image.into_rgba8();
+            }
+            if Instant::now() >= deadline {
+                return Err(PortError::msg(format!("spectacle did not write {}", path)));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        };
+        let _ = std::fs::remove_file(&path);
+        Ok(image)
+    }
+
+    /// Capture the screen (or `area` of it) for image analysis, returning the
+    /// image and its top-left corner in global coordinates.
+    ///
+    /// Falls back to a cropped spectacle capture when KWin refuses; that path
+    /// assumes an unscaled workspace with its origin at 0,0.
+    fn grab(&self, area: Option<Area>) -> Result<(image::RgbaImage, i32, i32), PortError> {
+        let (x, y) = area.map(|a| (a.x, a.y)).unwrap_or((0, 0));
+        match self.grab_kwin(area) {
+            Ok(image) => Ok((image, x, y)),
+            Err(kwin) => {
+                let full = Self::spectacle("fullscreen", &Options::default()).map_err(|spectacle| {
+                    PortError::msg(format!("KWin capture failed: {}; spectacle fallback: {}", kwin, spectacle.message))
+                })?;
+                Ok((crop(full, area)?, x, y))
+            }
+        }
+    }
+
+    /// Capture the screen (or `area` of it) through KWin only.
+    fn grab_kwin(&self, area: Option<Area>) -> Result<image::RgbaImage, String> {
+        let target = match area {
+            Some(a) => Target::Area { x: a.x, y: a.y, width: a.width, height: a.height },
+            None => Target::Workspace,
+        };
+        self.rt
+            .block_on(capture::capture(&self.connection, &target, &Options::default()))
+            .map_err(|e| e.to_string())
+    }
+
+    /// The image to analyse: `image=<path>` if given, else a screen capture.
+    /// `rect` selects a region of either.
+    fn source(&self, args: &HashMap<String, String>) -> Result<(image::RgbaImage, i32, i32), PortError> {
+        let area = args.get("rect").map(|r| parse_area(r)).transpose()?;
+        match args.get("image") {
+            Some(path) => {
+                let image = image::open(path)
+                    .map_err(|e| PortError::msg(format!("cannot open {}: {}", path, e)))?
+                    .into_rgba8();
+                let (x, y) = area.map(|a| (a.x, a.y)).unwrap_or((0, 0));
+                Ok((crop(image, area)?, x, y))
+            }
+            None => self.grab(area),
+        }
+    }
+
+    fn pixel(&self, args: &HashMap<String, String>) -> PortResult {
+        let x = int_arg(args, "x")?;
+        let y = int_arg(args, "y")?;
+        let rgba = match args.get("image") {
+            Some(path) => {
+                let image = image::open(path)
+                    .map_err(|e| PortError::msg(format!("cannot open {}: {}", path, e)))?
+                    .into_rgba8();
+                let inside = u32::try_from(x).ok().zip(u32::try_from(y).ok());
+                inside.and_then(|(x, y)| vision::pixel(&image, x, y))
+            }
+            None => {
+                let (image, _, _) = self.grab(Some(Area { x, y, width: 1, height: 1 }))?;
+                vision::pixel(&image, 0, 0)
+            }
+        }
+        .ok_or_else(|| PortError::msg(format!("point {},{} is outside the image", x, y)))?;
+
+        let mut map = HashMap::new();
+        map.insert("x".into(), PortValue::Int(x as i64));
+        map.insert("y".into(), PortValue::Int(y as i64));
+        map.insert("r".into(), PortValue::Int(rgba[0] as i64));
+        map.insert("g".into(), PortValue::Int(rgba[1] as i64));
+        map.insert("b".into(), PortValue::Int(rgba[2] as i64));
+        map.insert("a".into(), PortValue::Int(rgba[3] as i64));
+        map.insert("hex".into(), PortValue::String(vision::hex(rgba)));
+        Ok(PortValue::Map(map))
+    }
+
+    fn find(&self, args: &HashMap<String, String>) -> PortResult {
+        let template_path = &required_arg(args, "template")?;
+        let template = image::open(template_path)
+            .map_err(|e| PortError::msg(format!("cannot open {}: {}", template_path, e)))?
+            .into_rgba8();
+        let threshold = float_arg(args, "threshold", 0.9)?;
+        let (haystack, ox, oy) = self.source(args)?;
+
+        let best = vision::find_template(&haystack, &template);
+        let score = best.map(|m| m.score).unwrap_or(0.0);
+        let mut map = HashMap::new();
+        map.insert("found".into(), PortValue::Bool(score >= threshold));
+        map.insert("score".into(), PortValue::Float((score * 1000.0).round() / 1000.0));
+        if let Some(m) = best {
+            map.insert("x".into(), PortValue::Int(ox as i64 + m.x as i64));
+            map.insert("y".into(), PortValue::Int(oy as i64 + m.y as i64));
+            map.insert("width".into(), PortValue::Int(template.width() as i64));
+            map.insert("height".into(), PortValue::Int(template.height() as i64));
+        }
+        Ok(PortValue::Map(map))
+    }
+
+    /// Run an operation chain over an image file and write the result.
+    fn process(&self, args: &HashMap<String, String>) -> PortResult {
+        let input = &required_arg(args, "image")?;
+        let ops = imaging::parse_ops(&required_arg(args, "ops")?).map_err(PortError::msg)?;
+        let output = args.get("output").cloned();
+
+        // Format: explicit, else the output's extension, else the input's, else PNG
+        let extension_of = |path: &str| {
+            Path::new(path)
+                .extension()
+                .and_then(|e| Format::parse(&e.to_string_lossy()).ok())
+        };
+        let format = match args.get("format") {
+            Some(f) => Format::parse(f).map_err(PortError::msg)?,
+            None => output
+                .as_deref()
+                .and_then(extension_of)
+                .or_else(|| extension_of(input))
+                .unwrap_or(Format::Png),
+        };
+        let delivery = Delivery {
+            format,
+            quality: quality_arg(args)?,
+            inline: bool_arg(args, "inline", false),
+            path: output,
+        };
+
+        let image = image::open(input)
+            .map_err(|e| PortError::msg(format!("cannot open {}: {}", input, e)))?
+            .into_rgba8();
+        let font = if imaging::needs_font(&ops) {
+            Some(imaging::load_font().map_err(PortError::msg)?)
+        } else {
+            None
+        };
+        let processed = imaging::apply(image, &ops, font.as_ref()).map_err(PortError::msg)?;
+        Ok(delivery.finish(&processed, None)?.to_value())
+    }
+
+    /// Poll a region until it changes from its first capture, or until it has
+    /// stopped changing for `stable_for` ms.
+    ///
+    /// Needs KWin ScreenShot2: a full spectacle run per poll would take longer
+    /// than the interval and flash the screen, so there is no fallback.
+    fn wait_change(&self, args: &HashMap<String, String>) -> PortResult {
+        let area = args.get("rect").map(|r| parse_area(r)).transpose()?;
+        let until_stable = match args.get("until").map(|s| s.as_str()).unwrap_or("change") {
+            "change" => false,
+            "stable" => true,
+            other => {
+                return Err(PortError::msg(format!("invalid 'until': {} (expected change or stable)", other)))
+            }
+        };
+        let threshold = float_arg(args, "threshold", 0.01)?;
+        let timeout = Duration::from_millis(ms_arg(args, "timeout", 10000)?);
+        let interval = Duration::from_millis(ms_arg(args, "interval", 250)?);
+        let stable_for = Duration::from_millis(ms_arg(args, "stable_for", 1000)?);
+
+        let grab = || {
+            self.grab_kwin(area).map_err(|e| {
+                PortError::msg(format!(
+                    "wait_change needs KWin ScreenShot2 (declare X-KDE-DBUS-Restricted-Interfaces=org.kde.KWin.ScreenShot2 \
+                     in appmesh's desktop file); KWin capture failed: {}",
+                    e
+                ))
+            })
+        };
+
+        let start = Instant::now();
+        let deadline = start + timeout;
+        let first = grab()?;
+        let mut previous = first.clone();
+        let mut still_since = Instant::now();
+
+        while Instant::now() < deadline {
+            std::thread::sleep(interval);
+            let frame = grab()?;
+            let fraction = if until_stable {
+                vision::changed_fraction(&previous, &frame)
+            } else {
+                vision::changed_fraction(&first, &frame)
+            };
+
+            let done = if until_stable {
+                if fraction > threshold {
+                    still_since = Instant::now();
+                }
+                still_since.elapsed() >= stable_for
+            } else {
+                fraction > threshold
+            };
+            if done {
+                let mut map = HashMap::new();
+                map.insert("until".into(), PortValue::String(if until_stable { "stable" } else { "change" }.into()));
+                map.insert("elapsed".into(), PortValue::Int(start.elapsed().as_millis() as i64));
+                map.insert("changed".into(), PortValue::Float(vision::changed_fraction(&first, &frame)));
+                return Ok(PortValue::Map(map));
+            }
+            previous = frame;
+        }
+
+        let what = if until_stable { "stabilize" } else { "change" };
+        Err(PortError::msg(format!("region did not {} within {}ms", what, timeout.as_millis())))
     }
 }
 
+// Safety: ScreenshotPort is only used from one thread at a time via Mutex or single-threaded FFI
+unsafe impl Send for ScreenshotPort {}
+
 impl AppMeshPort for ScreenshotPort {
     fn name(&self) -> &str {
         "screenshot"
@@ -31,13 +453,108 @@ impl AppMeshPort for ScreenshotPort {
         vec![
             CommandDef {
                 name: "take".into(),
-                description: "Take a screenshot and return the file path".into(),
+                description: "Take a screenshot and return its path (or inline data), size and format".into(),
                 params: vec![
                     ParamDef {
                         name: "mode".into(),
-                        description: "fullscreen, activewindow, or region".into(),
+                        description: "fullscreen, screen, activewindow, pick_window, pick_screen or region (default: fullscreen)".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "window".into(),
+                        description: "Capture this window (internal ID from 'windows list') instead of 'mode'".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "rect".into(),
+                        description: "Capture x,y,width,height in global coordinates instead of 'mode'".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "output".into(),
+                        description: "Capture this output (e.g. DP-1) instead of 'mode'".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "cursor".into(),
+                        description: "Include the mouse cursor (default: false)".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "decoration".into(),
+                        description: "Include window title bar and borders (default: true)".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "inline".into(),
+                        description: "Return the image as base64 'data' instead of writing a file (default: false)".into(),
                         required: false,
                     },
+                    ParamDef {
+                        name: "format".into(),
+                        description: "png, jpeg or webp (default: png)".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "quality".into(),
+                        description: "JPEG quality 1-100 (default: 90)".into(),
+                        required: false,
+                    },
+                    ParamDef {
+                        name: "backend".into(),
+                        description: "auto, kwin or spectacle (default: auto — KWin, falling back to spectacle)".into(),
+                        required: false,
+                    },
+                ],
+            },
+            CommandDef {
+                name: "pixel".into(),
+                description: "Get the color of a screen (or image) pixel".into(),
+                params: vec![
+                    ParamDef { name: "x".into(), description: "X coordinate".into(), required: true },
+                    ParamDef { name: "y".into(), description: "Y coordinate".into(), required: true },
+                    image_param(),
+                ],
+            },
+            CommandDef {
+                name: "find".into(),
+                description: "Find the best match of a template image on screen (or in an image)".into(),
+                params: vec![
+                    ParamDef { name: "template".into(), description: "Path to the PNG to look for".into(), required: true },
+                    rect_param(),
+                    ParamDef { name: "threshold".into(), description: "Minimum score (0-1) to report found (default: 0.9)".into(), required: false },
+                    image_param(),
+                ],
+            },
+            CommandDef {
+                name: "wait_change".into(),
+                description: "Wait until a screen region changes or stops changing".into(),
+                params: vec![
+                    rect_param(),
+                    ParamDef { name: "until".into(), description: "change or stable (default: change)".into(), required: false },
+                    ParamDef { name: "threshold".into(), description: "Fraction of pixels (0-1) that counts as a change (default: 0.01)".into(), required: false },
+                    ParamDef { name: "stable_for".into(), description: "Ms without change that counts as stable (default: 1000)".into(), required: false },
+                    ParamDef { name: "interval".into(), description: "Ms between captures (default: 250)".into(), required: false },
+                    ParamDef { name: "timeout".into(), description: "Timeout in ms (default: 10000)".into(), required: false },
+                ],
+            },
+            CommandDef {
+                name: "process".into(),
+                description: "Crop, resize, annotate or redact an image and write a new file".into(),
+                params: vec![
+                    ParamDef { name: "image".into(), description: "Input image path (e.g. from 'take')".into(), required: true },
+                    ParamDef {
+                        name: "ops".into(),
+                        description: format!(
+                            "Operations separated by |: {}",
+                            imaging::OPS.iter().map(|(_, syntax)| *syntax).collect::<Vec<_>>().join("; ")
+                        ),
+                        required: true,
+                    },
+                    ParamDef { name: "output".into(), description: "Output path (default: new file under /run/user/<uid>/appmesh)".into(), required: false },
+                    ParamDef { name: "format".into(), description: "png, jpeg or webp (default: from output or input extension)".into(), required: false },
+                    ParamDef { name: "quality".into(), description: "JPEG quality 1-100 (default: 90)".into(), required: false },
+                    ParamDef { name: "inline".into(), description: "Return base64 'data' instead of writing a file (default: false)".into(), required: false },
                 ],
             },
         ]
@@ -45,41 +562,11 @@ impl AppMeshPort for ScreenshotPort {
 
     fn execute(&self, cmd: &str, args: &HashMap<String, String>) -> PortResult {
         match cmd {
-            "take" => {
-                let mode = args.get("mode").map(|s| s.as_str()).unwrap_or("fullscreen");
-                let flag = match mode {
-                    "activewindow" => "-a",
-                    "region" => "-r",
-                    _ => "-f",
-                };
-
-                let path = Self::output_path();
-
-                let output = std::process::Command::new("spectacle")
-                    .args([flag, "-b", "-n", "-o", &path])
-                    .output()
-                    .map_err(|e| PortError {
-                        code: -1,
-                        message: format!("spectacle: {}", e),
-                    })?;
-
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    return Err(PortError {
-                        code: -1,
-                        message: format!("spectacle failed: {}", stderr),
-                    });
-                }
-
-                // Brief wait for file to be written
-                std::thread::sleep(std::time::Duration::from_millis(300));
-
-                if std::path::Path::new(&path).exists() {
-                    Ok(PortValue::String(path))
-                } else {
-                    Ok(PortValue::String(format!("screenshot may be at: {}", path)))
-                }
-            }
+            "take" => self.take(args),
+            "pixel" => self.pixel(args),
+            "find" => self.find(args),
+            "wait_change" => self.wait_change(args),
+            "process" => self.process(args),
             other => Err(PortError {
                 code: -1,
                 message: format!("unknown command: {}", other),
@@ -87,3 +574,91 @@ impl AppMeshPort for ScreenshotPort {
         }
     }
 }
+
+/// A rectangle in global (or image) coordinates.
+#[derive(Clone, Copy)]
+struct Area {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+/// Parse `x,y,width,height`.
+fn parse_area(value: &str) -> Result<Area, PortError> {
+    let invalid = || PortError::msg(format!("invalid 'rect': {} (expected x,y,width,height)", value));
+    let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+    let [x, y, width, height] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    let area = Area {
+        x: x.parse().map_err(|_| invalid())?,
+        y: y.parse().map_err(|_| invalid())?,
+        width: width.parse().map_err(|_| invalid())?,
+        height: height.parse().map_err(|_| invalid())?,
+    };
+    if area.width == 0 || area.height == 0 {
+        return Err(invalid());
+    }
+    Ok(area)
+}
+
+/// Cut `area` out of `image`; `None` keeps the whole image.
+fn crop(image: image::RgbaImage, area: Option<Area>) -> Result<image::RgbaImage, PortError> {
+    let Some(a) = area else {
+        return Ok(image);
+    };
+    let fits = a.x >= 0
+        && a.y >= 0
+        && a.x as u64 + a.width as u64 <= image.width() as u64
+        && a.y as u64 + a.height as u64 <= image.height() as u64;
+    if !fits {
+        return Err(PortError::msg(format!(
+            "rect {},{},{},{} is outside the {}x{} image",
+            a.x, a.y, a.width, a.height, image.width(), image.height()
+        )));
+    }
+    Ok(image::imageops::crop_imm(&image, a.x as u32, a.y as u32, a.width, a.height).to_image())
+}
+
+fn quality_arg(args: &HashMap<String, String>) -> Result<u8, PortError> {
+    match args.get("quality") {
+        Some(q) => q
+            .trim()
+            .parse::<u8>()
+            .ok()
+            .filter(|q| (1..=100).contains(q))
+            .ok_or_else(|| PortError::msg(format!("invalid 'quality': {} (expected 1-100)", q))),
+        None => Ok(90),
+    }
+}
+
+fn int_arg(args: &HashMap<String, String>, name: &str) -> Result<i32, PortError> {
+    let value = required_arg(args, name)?;
+    value
+        .trim()
+        .parse()
+        .map_err(|_| PortError::msg(format!("invalid '{}': {}", name, value)))
+}
+
+fn ms_arg(args: &HashMap<String, String>, name: &str, default: u64) -> Result<u64, PortError> {
+    match args.get(name) {
+        Some(v) => v.trim().parse().map_err(|_| PortError::msg(format!("invalid '{}': {}", name, v))),
+        None => Ok(default),
+    }
+}
+
+fn float_arg(args: &HashMap<String, String>, name: &str, default: f64) -> Result<f64, PortError> {
+    match args.get(name) {
+        Some(v) => v.trim().parse().map_err(|_| PortError::msg(format!("invalid '{}': {}", name, v))),
+        None => Ok(default),
+    }
+}
+
+fn rect_param() -> ParamDef {
+    ParamDef { name: "rect".into(), description: "Region x,y,width,height (default: whole screen or image)".into(), required: false }
+}
+
+fn image_param() -> ParamDef {
+    ParamDef { name: "image".into(), description: "Analyse this image file instead of the screen".into(), required: false }
+}
@@ -1,11 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
+use crate::kwin::KWinScripts;
+use crate::layout::{self, Layout};
 use crate::port::*;
 
+/// How long to wait for a KWin script to call back with its result.
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(2);
+
 /// Windows port — KWin window management via D-Bus scripting.
 pub struct WindowsPort {
     rt: tokio::runtime::Runtime,
-    connection: zbus::Connection,
+    scripts: KWinScripts,
 }
 
 impl WindowsPort {
@@ -14,127 +21,622 @@ impl WindowsPort {
             .enable_all()
             .build()?;
         let connection = rt.block_on(zbus::Connection::session())?;
-        Ok(Self { rt, connection })
-    }
-
-    /// Run a KWin script and return its load ID.
-    fn run_kwin_script(&self, script: &str) -> Result<i32, PortError> {
-        self.rt.block_on(async {
-            // Write script to temp file (unique name to avoid KWin script caching)
-            let tmp = format!("/tmp/appmesh_kwin_{}_{}.js", std::process::id(),
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .map(|d| d.as_nanos())
-                    .unwrap_or(0));
-            std::fs::write(&tmp, script)
-                .map_err(|e| PortError { code: -1, message: e.to_string() })?;
-
-            let proxy = zbus::Proxy::new(
-                &self.connection,
-                "org.kde.KWin",
-                "/Scripting",
-                "org.kde.kwin.Scripting",
-            )
-            .await
-            .map_err(|e| PortError { code: -1, message: e.to_string() })?;
-
-            // loadScript returns script ID
-            let reply: zbus::Message = proxy.call_method("loadScript", &(&tmp,)).await
-                .map_err(|e| PortError { code: -1, message: e.to_string() })?;
-            let body = reply.body();
-            let script_id: i32 = body.deserialize()
-                .map_err(|e| PortError { code: -1, message: e.to_string() })?;
-
-            // Run the script
-            let script_path = format!("/Scripting/Script{}", script_id);
-            let script_obj_path = zbus::zvariant::ObjectPath::try_from(script_path.as_str())
-                .map_err(|e| PortError { code: -1, message: format!("invalid path: {}", e) })?;
-            let script_proxy = zbus::Proxy::new(
-                &self.connection,
-                "org.kde.KWin",
-                script_obj_path,
-                "org.kde.kwin.Script",
-            )
-            .await
-            .map_err(|e| PortError { code: -1, message: e.to_string() })?;
-
-            let _: () = script_proxy.call("run", &()).await
-                .map_err(|e| PortError { code: -1, message: e.to_string() })?;
-
-            // Brief pause for script execution
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-
-            let _: () = script_proxy.call("stop", &()).await
-                .map_err(|e| PortError { code: -1, message: e.to_string() })?;
-
-            let _ = std::fs::remove_file(&tmp);
-
-            Ok(script_id)
-        })
+        let scripts = rt.block_on(KWinScripts::new(&connection))?;
+        Ok(Self { rt, scripts })
+    }
+
+    /// Run a KWin script body and return the JSON value it returns.
+    fn run_kwin_script(&self, body: &str) -> Result<serde_json::Value, PortError> {
+        self.rt
+            .block_on(self.scripts.run(body, SCRIPT_TIMEOUT))
+            .map_err(PortError::msg)
+    }
+
+    /// Fetch every captioned window as a JSON object.
+    fn window_rows(&self) -> Result<Vec<serde_json::Value>, PortError> {
+        match self.run_kwin_script(&list_windows_script())? {
+            serde_json::Value::Array(list) => Ok(list),
+            other => Err(PortError {
+                code: -1,
+                message: format!("unexpected script result: {}", other),
+            }),
+        }
     }
 
-    /// List windows via KWin script that stashes results in Klipper clipboard.
-    /// Uses qdbus6 subprocess for script lifecycle (proven reliable).
-    fn list_windows(&self) -> Result<String, PortError> {
-        // Save current clipboard so we can restore it
-        let saved_clipboard = self.rt.block_on(async {
-            let proxy = zbus::Proxy::new(
-                &self.connection,
-                "org.kde.klipper",
-                "/klipper",
-                "org.kde.klipper.klipper",
-            ).await.ok()?;
-            let reply: zbus::Message = proxy.call_method("getClipboardContents", &()).await.ok()?;
-            reply.body().deserialize::<String>().ok()
-        });
-
-        let output = std::process::Command::new("sh")
-            .args(["-c", r#"
-                SCRIPT=$(mktemp /tmp/appmesh_kwin_XXXXXX.js)
-                cat > "$SCRIPT" << 'JSEOF'
-const clients = workspace.windowList();
-let lines = [];
-for (const c of clients) {
-    if (c.caption && c.caption.length > 0) {
-        lines.push([c.internalId.toString(), c.resourceClass, c.active ? '*' : ' ', c.caption].join('\t'));
-    }
-}
-callDBus("org.kde.klipper", "/klipper", "org.kde.klipper.klipper", "setClipboardContents", lines.join('\n'));
-JSEOF
-                ID=$(qdbus6 org.kde.KWin /Scripting org.kde.kwin.Scripting.loadScript "$SCRIPT")
-                if echo "$ID" | grep -qE '^[0-9]+$'; then
-                    qdbus6 org.kde.KWin /Scripting/Script$ID org.kde.kwin.Script.run
-                    sleep 0.15
-                    qdbus6 org.kde.KWin /Scripting/Script$ID org.kde.kwin.Script.stop
-                fi
-                rm -f "$SCRIPT"
-                qdbus6 org.kde.klipper /klipper org.kde.klipper.klipper.getClipboardContents
-            "#])
-            .output()
-            .map_err(|e| PortError { code: -1, message: format!("script failed: {}", e) })?;
-
-        let result = if output.status.success() {
-            String::from_utf8_lossy(&output.stdout).trim().to_string()
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(PortError { code: -1, message: format!("window list failed: {}", stderr) });
+    /// List windows with geometry and state, optionally filtered.
+    fn list_windows(&self, filter: &WindowFilter) -> Result<Vec<PortValue>, PortError> {
+        Ok(self
+            .window_rows()?
+            .into_iter()
+            .filter(|w| filter.matches(w))
+            .map(PortValue::from)
+            .collect())
+    }
+
+    /// Block until a matching window exists, is active, or is gone.
+    fn wait_for_window(&self, args: &HashMap<String, String>) -> PortResult {
+        let filter = WindowFilter::from_args(args)?;
+        if filter.is_empty() {
+            return Err(PortError {
+                code: -1,
+                message: "wait needs at least one of 'class', 'title', 'title_regex' or 'pid'".into(),
+            });
+        }
+        let condition = match args.get("condition").map(|s| s.as_str()).unwrap_or("exists") {
+            "exists" => WaitCondition::Exists,
+            "active" => WaitCondition::Active,
+            "gone" => WaitCondition::Gone,
+            other => {
+                return Err(PortError {
+                    code: -1,
+                    message: format!("invalid 'condition': {} (expected exists, active or gone)", other),
+                })
+            }
+        };
+        let timeout_ms: u64 = args
+            .get("timeout")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10000);
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+
+        // Subscribe before taking the snapshot so no change slips in between
+        let events = self.subscribe()?;
+        let current: Vec<serde_json::Value> = self
+            .window_rows()?
+            .into_iter()
+            .filter(|w| filter.matches(w))
+            .collect();
+
+        let is_active = |w: &serde_json::Value| w.get("active").and_then(|v| v.as_bool()) == Some(true);
+        let window_id = |w: &serde_json::Value| {
+            w.get("internalId").and_then(|v| v.as_str()).unwrap_or("").to_string()
         };
 
-        // Restore clipboard
-        if let Some(saved) = saved_clipboard {
-            let _ = self.rt.block_on(async {
-                let proxy = zbus::Proxy::new(
-                    &self.connection,
-                    "org.kde.klipper",
-                    "/klipper",
-                    "org.kde.klipper.klipper",
-                ).await.ok()?;
-                let _: zbus::Message = proxy.call_method("setClipboardContents", &(&saved,)).await.ok()?;
-                Some(())
+        let mut remaining: HashSet<String> = HashSet::new();
+        match condition {
+            WaitCondition::Exists => {
+                if let Some(w) = current.into_iter().next() {
+                    return Ok(PortValue::from(w));
+                }
+            }
+            WaitCondition::Active => {
+                if let Some(w) = current.into_iter().find(|w| is_active(w)) {
+                    return Ok(PortValue::from(w));
+                }
+            }
+            WaitCondition::Gone => {
+                remaining = current.iter().map(window_id).collect();
+                if remaining.is_empty() {
+                    return Ok(PortValue::String("window gone".into()));
+                }
+            }
+        }
+
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            let Some(event) = events.next(deadline - now) else {
+                if Instant::now() < deadline {
+                    return Err(PortError {
+                        code: -1,
+                        message: "window event stream ended".into(),
+                    });
+                }
+                break;
+            };
+
+            let window = serde_json::to_value(&event.data).unwrap_or_default();
+            let removed = event.event == "window_removed";
+            let matches = !removed && filter.matches(&window);
+            match condition {
+                WaitCondition::Exists if matches => return Ok(event.data),
+                WaitCondition::Active if matches && is_active(&window) => return Ok(event.data),
+                WaitCondition::Gone => {
+                    if matches {
+                        remaining.insert(window_id(&window));
+                    } else {
+                        remaining.remove(&window_id(&window));
+                    }
+                    if remaining.is_empty() {
+                        return Ok(PortValue::String("window gone".into()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let what = match condition {
+            WaitCondition::Exists => "to appear",
+            WaitCondition::Active => "to become active",
+            WaitCondition::Gone => "to close",
+        };
+        Err(PortError {
+            code: -1,
+            message: format!("timed out after {}ms waiting for window {}", timeout_ms, what),
+        })
+    }
+
+    /// Save the current arrangement of normal windows as a named layout.
+    fn layout_save(&self, name: &str) -> PortResult {
+        let layout = Layout::from_windows(name, &self.window_rows()?);
+        let path = layout::save(&layout).map_err(PortError::msg)?;
+        Ok(PortValue::String(format!(
+            "saved {} windows to {}",
+            layout.windows.len(),
+            path.display()
+        )))
+    }
+
+    /// Re-apply a saved layout, optionally launching apps that have no window.
+    fn layout_restore(&self, args: &HashMap<String, String>) -> PortResult {
+        let name = required_arg(args, "name")?;
+        let launch = bool_arg(args, "launch", false);
+        let launch_timeout_ms: u64 = args
+            .get("launch_timeout")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10000);
+
+        let layout = layout::load(&name).map_err(PortError::msg)?;
+        let mut windows = self.window_rows()?;
+        let (mut matched, mut unmatched) = layout.assign(&windows);
+
+        let mut launched: Vec<String> = Vec::new();
+        if launch && !unmatched.is_empty() {
+            for &i in &unmatched {
+                let desktop_file = &layout.windows[i].desktop_file_name;
+                // One launch per app, however many of its windows were saved
+                if desktop_file.is_empty() || launched.contains(desktop_file) {
+                    continue;
+                }
+                let spawned = std::process::Command::new("kstart")
+                    .args(["--application", desktop_file])
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::null())
+                    .spawn();
+                if spawned.is_ok() {
+                    launched.push(desktop_file.clone());
+                }
+            }
+
+            // Poll until the launched apps have mapped their windows: every
+            // entry is matched, or each app has a window and no more appeared
+            // since the last poll (apps restoring several windows).
+            let deadline = Instant::now() + Duration::from_millis(launch_timeout_ms);
+            let mut last_matched = matched.len();
+            while !launched.is_empty() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(500));
+                windows = self.window_rows()?;
+                (matched, unmatched) = layout.assign(&windows);
+                let still_missing = unmatched
+                    .iter()
+                    .any(|&i| !layout.windows[i].desktop_file_name.is_empty());
+                let all_up = launched.iter().all(|file| {
+                    matched
+                        .iter()
+                        .any(|(i, _)| layout.windows[*i].desktop_file_name == *file)
+                });
+                if !still_missing || (all_up && matched.len() == last_matched) {
+                    break;
+                }
+                last_matched = matched.len();
+            }
+        }
+
+        let plan: Vec<serde_json::Value> = matched
+            .iter()
+            .filter_map(|(i, id)| {
+                let mut entry = serde_json::to_value(&layout.windows[*i]).ok()?;
+                entry["id"] = serde_json::Value::String(id.clone());
+                Some(entry)
+            })
+            .collect();
+        let restored = self.run_kwin_script(&restore_layout_script(&plan))?;
+
+        let unmatched: Vec<PortValue> = unmatched
+            .iter()
+            .map(|&i| {
+                let entry = &layout.windows[i];
+                let mut map = HashMap::new();
+                map.insert("resourceClass".into(), PortValue::String(entry.resource_class.clone()));
+                map.insert("caption".into(), PortValue::String(entry.caption.clone()));
+                PortValue::Map(map)
+            })
+            .collect();
+
+        let mut result = HashMap::new();
+        result.insert("restored".into(), PortValue::from(restored));
+        result.insert(
+            "launched".into(),
+            PortValue::List(launched.into_iter().map(PortValue::String).collect()),
+        );
+        result.insert("unmatched".into(), PortValue::List(unmatched));
+        Ok(PortValue::Map(result))
+    }
+
+    /// Apply an action to the window with the given internal ID.
+    fn apply(&self, id: &str, action: &WindowAction) -> Result<(), PortError> {
+        let escaped_id = serde_json::to_string(id).unwrap_or_default();
+        let script = format!(
+            r#"
+const w = workspace.windowList().find(w => w.internalId.toString() === {});
+if (!w) return false;
+{}
+return true;
+"#,
+            escaped_id,
+            action.script()
+        );
+        if self.run_kwin_script(&script)? != serde_json::Value::Bool(true) {
+            return Err(PortError {
+                code: -1,
+                message: format!("window not found: {}", id),
             });
         }
+        Ok(())
+    }
+}
+
+/// A typed window operation, rendered to a KWin script snippet acting on `w`.
+enum WindowAction {
+    Activate,
+    Move { x: i64, y: i64 },
+    Resize { width: i64, height: i64 },
+    SetGeometry { x: i64, y: i64, width: i64, height: i64 },
+    Minimize(bool),
+    Maximize(bool),
+    FullScreen(Toggle),
+    Close,
+    KeepAbove(Toggle),
+    KeepBelow(Toggle),
+    Shade(Toggle),
+    SetDesktop(String),
+    SetActivity(String),
+    MoveToOutput(String),
+}
+
+/// On/off/toggle state for boolean window properties.
+#[derive(Clone, Copy)]
+enum Toggle {
+    On,
+    Off,
+    Flip,
+}
 
-        Ok(result)
+impl Toggle {
+    fn from_args(args: &HashMap<String, String>) -> Result<Self, PortError> {
+        match args.get("state").map(|s| s.as_str()).unwrap_or("on") {
+            "on" | "true" | "1" => Ok(Toggle::On),
+            "off" | "false" | "0" => Ok(Toggle::Off),
+            "toggle" => Ok(Toggle::Flip),
+            other => Err(PortError {
+                code: -1,
+                message: format!("invalid 'state': {} (expected on, off or toggle)", other),
+            }),
+        }
+    }
+
+    /// JS expression assigning the new state to `w.<property>`.
+    fn assign(self, property: &str) -> String {
+        match self {
+            Toggle::On => format!("w.{} = true;", property),
+            Toggle::Off => format!("w.{} = false;", property),
+            Toggle::Flip => format!("w.{0} = !w.{0};", property),
+        }
+    }
+}
+
+impl WindowAction {
+    /// Parse a port command into an action. Returns `None` for non-action commands.
+    fn from_command(cmd: &str, args: &HashMap<String, String>) -> Result<Option<Self>, PortError> {
+        let action = match cmd {
+            "activate" => WindowAction::Activate,
+            "move" => WindowAction::Move {
+                x: int_arg(args, "x")?,
+                y: int_arg(args, "y")?,
+            },
+            "resize" => WindowAction::Resize {
+                width: int_arg(args, "width")?,
+                height: int_arg(args, "height")?,
+            },
+            "set_geometry" => WindowAction::SetGeometry {
+                x: int_arg(args, "x")?,
+                y: int_arg(args, "y")?,
+                width: int_arg(args, "width")?,
+                height: int_arg(args, "height")?,
+            },
+            "minimize" => WindowAction::Minimize(true),
+            "unminimize" => WindowAction::Minimize(false),
+            "maximize" => WindowAction::Maximize(true),
+            "restore" => WindowAction::Maximize(false),
+            "fullscreen" => WindowAction::FullScreen(Toggle::from_args(args)?),
+            "close" => WindowAction::Close,
+            "keep_above" => WindowAction::KeepAbove(Toggle::from_args(args)?),
+            "keep_below" => WindowAction::KeepBelow(Toggle::from_args(args)?),
+            "shade" => WindowAction::Shade(Toggle::from_args(args)?),
+            "set_desktop" => WindowAction::SetDesktop(required_arg(args, "desktop")?),
+            "set_activity" => WindowAction::SetActivity(required_arg(args, "activity")?),
+            "move_to_output" => WindowAction::MoveToOutput(required_arg(args, "output")?),
+            _ => return Ok(None),
+        };
+        Ok(Some(action))
+    }
+
+    fn verb(&self) -> &'static str {
+        match self {
+            WindowAction::Activate => "activated",
+            WindowAction::Move { .. } => "moved",
+            WindowAction::Resize { .. } => "resized",
+            WindowAction::SetGeometry { .. } => "set geometry of",
+            WindowAction::Minimize(true) => "minimized",
+            WindowAction::Minimize(false) => "unminimized",
+            WindowAction::Maximize(true) => "maximized",
+            WindowAction::Maximize(false) => "restored",
+            WindowAction::FullScreen(_) => "set fullscreen on",
+            WindowAction::Close => "closed",
+            WindowAction::KeepAbove(_) => "set keep-above on",
+            WindowAction::KeepBelow(_) => "set keep-below on",
+            WindowAction::Shade(_) => "set shade on",
+            WindowAction::SetDesktop(_) => "moved desktop of",
+            WindowAction::SetActivity(_) => "moved activity of",
+            WindowAction::MoveToOutput(_) => "moved output of",
+        }
+    }
+
+    fn script(&self) -> String {
+        let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+        match self {
+            WindowAction::Activate => "workspace.activeWindow = w;".into(),
+            WindowAction::Move { x, y } => format!(
+                "w.frameGeometry = {{ x: {}, y: {}, width: w.frameGeometry.width, height: w.frameGeometry.height }};",
+                x, y
+            ),
+            WindowAction::Resize { width, height } => format!(
+                "w.frameGeometry = {{ x: w.frameGeometry.x, y: w.frameGeometry.y, width: {}, height: {} }};",
+                width, height
+            ),
+            WindowAction::SetGeometry { x, y, width, height } => format!(
+                "w.frameGeometry = {{ x: {}, y: {}, width: {}, height: {} }};",
+                x, y, width, height
+            ),
+            WindowAction::Minimize(on) => format!("w.minimized = {};", on),
+            WindowAction::Maximize(on) => format!("w.setMaximize({0}, {0});", on),
+            WindowAction::FullScreen(t) => t.assign("fullScreen"),
+            WindowAction::Close => "w.closeWindow();".into(),
+            WindowAction::KeepAbove(t) => t.assign("keepAbove"),
+            WindowAction::KeepBelow(t) => t.assign("keepBelow"),
+            WindowAction::Shade(t) => t.assign("shade"),
+            // Desktops match by id, name, or 1-based position
+            WindowAction::SetDesktop(desktop) => format!(
+                r#"const key = {};
+const d = workspace.desktops.find((d, i) => d.id === key || d.name === key || String(i + 1) === key);
+if (!d) throw new Error("desktop not found: " + key);
+w.desktops = [d];"#,
+                quote(desktop)
+            ),
+            // An empty activity list means "on all activities"
+            WindowAction::SetActivity(activity) => format!(
+                r#"const key = {};
+w.activities = (key === "" || key === "all") ? [] : [key];"#,
+                quote(activity)
+            ),
+            WindowAction::MoveToOutput(output) => format!(
+                r#"const key = {};
+const o = workspace.screens.find(o => o.name === key);
+if (!o) throw new Error("output not found: " + key);
+workspace.sendClientToScreen(w, o);"#,
+                quote(output)
+            ),
+        }
+    }
+}
+
+fn int_arg(args: &HashMap<String, String>, name: &str) -> Result<i64, PortError> {
+    let value = required_arg(args, name)?;
+    value
+        .trim()
+        .parse()
+        .map_err(|_| PortError::msg(format!("invalid '{}': {}", name, value)))
+}
+
+/// JS helper describing a window, with properties named after KWin's own.
+const WINDOW_INFO_JS: &str = r#"
+function windowInfo(c) {
+    const g = c.frameGeometry;
+    return {
+        internalId: c.internalId.toString(),
+        caption: c.caption,
+        resourceClass: c.resourceClass,
+        resourceName: c.resourceName,
+        pid: c.pid,
+        desktopFileName: c.desktopFileName || "",
+        geometry: {
+            x: Math.round(g.x), y: Math.round(g.y),
+            width: Math.round(g.width), height: Math.round(g.height),
+        },
+        output: c.output ? c.output.name : "",
+        desktops: (c.desktops || []).map(d => d.id),
+        activities: c.activities || [],
+        minimized: c.minimized,
+        maximized: c.maximizeMode === 3,
+        fullScreen: c.fullScreen,
+        keepAbove: c.keepAbove,
+        skipTaskbar: c.skipTaskbar,
+        normalWindow: c.normalWindow,
+        active: c.active,
+    };
+}
+"#;
+
+/// Script body for `windows list`.
+fn list_windows_script() -> String {
+    format!(
+        r#"{}
+return workspace.windowList()
+    .filter(c => c.caption && c.caption.length > 0)
+    .map(windowInfo);
+"#,
+        WINDOW_INFO_JS
+    )
+}
+
+/// Watch script forwarding KWin workspace signals as `{ event, window }`.
+fn window_events_script() -> String {
+    format!(
+        r#"{}
+function track(c) {{
+    c.captionChanged.connect(() => emit({{ event: "caption_changed", window: windowInfo(c) }}));
+}}
+workspace.windowList().forEach(track);
+workspace.windowAdded.connect(c => {{
+    track(c);
+    emit({{ event: "window_added", window: windowInfo(c) }});
+}});
+workspace.windowRemoved.connect(c => emit({{ event: "window_removed", window: windowInfo(c) }}));
+workspace.windowActivated.connect(c => {{
+    if (c) emit({{ event: "window_activated", window: windowInfo(c) }});
+}});
+"#,
+        WINDOW_INFO_JS
+    )
+}
+
+/// Script body applying a layout plan (layout entries tagged with window `id`).
+///
+/// State is reset first so geometry lands on the restored (not maximized or
+/// fullscreen) frame, then re-applied. Geometry is skipped when the saved
+/// output is no longer connected.
+fn restore_layout_script(plan: &[serde_json::Value]) -> String {
+    format!(
+        r#"
+const plan = {};
+const restored = [];
+for (const p of plan) {{
+    const w = workspace.windowList().find(w => w.internalId.toString() === p.id);
+    if (!w) continue;
+    w.fullScreen = false;
+    w.minimized = false;
+    w.setMaximize(false, false);
+    const desktops = workspace.desktops.filter(d => p.desktops.includes(d.id));
+    if (desktops.length > 0) w.desktops = desktops;
+    w.activities = p.activities;
+    if (!p.output || workspace.screens.some(o => o.name === p.output)) {{
+        w.frameGeometry = p.geometry;
+    }}
+    if (p.maximized) w.setMaximize(true, true);
+    if (p.fullScreen) w.fullScreen = true;
+    w.keepAbove = p.keepAbove;
+    if (p.minimized) w.minimized = true;
+    restored.push(p.id);
+}}
+return restored;
+"#,
+        serde_json::Value::Array(plan.to_vec())
+    )
+}
+
+/// Event watcher: keeps the window events script loaded until the subscriber goes away.
+async fn watch_windows(sink: EventSink, ready: mpsc::Sender<Result<(), String>>) {
+    let setup = async {
+        let connection = zbus::Connection::session().await?;
+        let scripts = KWinScripts::new(&connection).await?;
+        let events = sink.clone();
+        let watch = scripts
+            .watch(&window_events_script(), SCRIPT_TIMEOUT, move |value| {
+                let name = value.get("event").and_then(|v| v.as_str()).unwrap_or("unknown");
+                let window = value.get("window").cloned().unwrap_or_default();
+                events.emit(name, PortValue::from(window));
+            })
+            .await?;
+        Ok::<_, Box<dyn std::error::Error>>((connection, scripts, watch))
+    };
+
+    let (_connection, scripts, watch) = match setup.await {
+        Ok(running) => {
+            let _ = ready.send(Ok(()));
+            running
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e.to_string()));
+            return;
+        }
+    };
+
+    sink.closed().await;
+    scripts.unwatch(watch).await;
+}
+
+/// What `windows wait` waits for.
+enum WaitCondition {
+    Exists,
+    Active,
+    Gone,
+}
+
+/// Optional filters for `windows list`, applied to the script's JSON rows.
+#[derive(Default)]
+struct WindowFilter {
+    class: Option<String>,
+    title: Option<String>,
+    title_regex: Option<regex::Regex>,
+    pid: Option<i64>,
+}
+
+impl WindowFilter {
+    fn from_args(args: &HashMap<String, String>) -> Result<Self, PortError> {
+        let title_regex = match args.get("title_regex") {
+            Some(pattern) => Some(regex::Regex::new(pattern).map_err(|e| PortError {
+                code: -1,
+                message: format!("invalid 'title_regex': {}", e),
+            })?),
+            None => None,
+        };
+        let pid = match args.get("pid") {
+            Some(pid) => Some(pid.parse().map_err(|_| PortError {
+                code: -1,
+                message: format!("invalid 'pid': {}", pid),
+            })?),
+            None => None,
+        };
+        Ok(Self {
+            class: args.get("class").map(|s| s.to_lowercase()),
+            title: args.get("title").map(|s| s.to_lowercase()),
+            title_regex,
+            pid,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.class.is_none() && self.title.is_none() && self.title_regex.is_none() && self.pid.is_none()
+    }
+
+    fn matches(&self, window: &serde_json::Value) -> bool {
+        let field = |name: &str| window.get(name).and_then(|v| v.as_str()).unwrap_or("");
+        if let Some(class) = &self.class {
+            if field("resourceClass").to_lowercase() != *class
+                && field("resourceName").to_lowercase() != *class
+            {
+                return false;
+            }
+        }
+        let caption = field("caption");
+        if let Some(title) = &self.title {
+            if !caption.to_lowercase().contains(title.as_str()) {
+                return false;
+            }
+        }
+        if let Some(re) = &self.title_regex {
+            if !re.is_match(caption) {
+                return false;
+            }
+        }
+        if let Some(pid) = self.pid {
+            if window.get("pid").and_then(|v| v.as_i64()) != Some(pid) {
+                return false;
+            }
+        }
+        true
     }
 }
 
@@ -150,56 +652,123 @@ impl AppMeshPort for WindowsPort {
         vec![
             CommandDef {
                 name: "list".into(),
-                description: "List all open windows with IDs, titles, and apps".into(),
-                params: vec![],
+                description: "List open windows with geometry, output, desktops and state".into(),
+                params: vec![
+                    ParamDef { name: "class".into(), description: "Match resource class or name (case-insensitive)".into(), required: false },
+                    ParamDef { name: "title".into(), description: "Match caption substring (case-insensitive)".into(), required: false },
+                    ParamDef { name: "title_regex".into(), description: "Match caption against a regex".into(), required: false },
+                    ParamDef { name: "pid".into(), description: "Match process ID".into(), required: false },
+                ],
             },
             CommandDef {
-                name: "activate".into(),
-                description: "Activate (focus) a window by its ID".into(),
+                name: "wait".into(),
+                description: "Wait until a matching window exists, is active, or is gone".into(),
                 params: vec![
-                    ParamDef { name: "id".into(), description: "Window ID (UUID or numeric)".into(), required: true },
+                    param("class", "Match resource class or name (case-insensitive)", false),
+                    param("title", "Match caption substring (case-insensitive)", false),
+                    param("title_regex", "Match caption against a regex", false),
+                    param("pid", "Match process ID", false),
+                    param("condition", "exists, active or gone (default: exists)", false),
+                    param("timeout", "Timeout in ms (default: 10000)", false),
                 ],
             },
+            CommandDef {
+                name: "layout_save".into(),
+                description: "Save the current window arrangement as a named layout".into(),
+                params: vec![param("name", "Layout name (e.g. coding)", true)],
+            },
+            CommandDef {
+                name: "layout_restore".into(),
+                description: "Restore a saved window layout".into(),
+                params: vec![
+                    param("name", "Layout name", true),
+                    param("launch", "Launch apps with no matching window (default: false)", false),
+                    param("launch_timeout", "Ms to wait for launched apps' windows (default: 10000)", false),
+                ],
+            },
+            CommandDef {
+                name: "layout_list".into(),
+                description: "List saved window layouts".into(),
+                params: vec![],
+            },
+            window_command("activate", "Activate (focus) a window", vec![]),
+            window_command("move", "Move a window to x,y", vec![
+                param("x", "Left edge in global coordinates", true),
+                param("y", "Top edge in global coordinates", true),
+            ]),
+            window_command("resize", "Resize a window", vec![
+                param("width", "Frame width", true),
+                param("height", "Frame height", true),
+            ]),
+            window_command("set_geometry", "Set a window's position and size", vec![
+                param("x", "Left edge in global coordinates", true),
+                param("y", "Top edge in global coordinates", true),
+                param("width", "Frame width", true),
+                param("height", "Frame height", true),
+            ]),
+            window_command("minimize", "Minimize a window", vec![]),
+            window_command("unminimize", "Unminimize a window", vec![]),
+            window_command("maximize", "Maximize a window", vec![]),
+            window_command("restore", "Restore a maximized window", vec![]),
+            window_command("fullscreen", "Set or toggle fullscreen", vec![state_param()]),
+            window_command("close", "Close a window", vec![]),
+            window_command("keep_above", "Set or toggle keep-above", vec![state_param()]),
+            window_command("keep_below", "Set or toggle keep-below", vec![state_param()]),
+            window_command("shade", "Set or toggle shading (X11 windows only)", vec![state_param()]),
+            window_command("set_desktop", "Move a window to a virtual desktop", vec![
+                param("desktop", "Desktop id, name, or 1-based number", true),
+            ]),
+            window_command("set_activity", "Move a window to an activity", vec![
+                param("activity", "Activity id, or 'all'", true),
+            ]),
+            window_command("move_to_output", "Move a window to another output", vec![
+                param("output", "Output name (e.g. DP-1)", true),
+            ]),
         ]
     }
 
     fn execute(&self, cmd: &str, args: &HashMap<String, String>) -> PortResult {
         match cmd {
             "list" => {
-                let output = self.list_windows()?;
-                Ok(PortValue::String(output))
+                let filter = WindowFilter::from_args(args)?;
+                Ok(PortValue::List(self.list_windows(&filter)?))
             }
-            "activate" => {
-                let id = args.get("id").ok_or_else(|| PortError {
-                    code: -1,
-                    message: "missing 'id' argument".into(),
-                })?;
-
-                // Try numeric ID with wmctrl first
-                if id.chars().all(|c| c.is_ascii_digit() || c == 'x' || c == 'X') {
-                    let output = std::process::Command::new("wmctrl")
-                        .args(["-i", "-a", id])
-                        .output();
-                    if let Ok(out) = output {
-                        if out.status.success() {
-                            return Ok(PortValue::String(format!("activated window: {}", id)));
-                        }
-                    }
+            "wait" => self.wait_for_window(args),
+            "layout_save" => self.layout_save(&required_arg(args, "name")?),
+            "layout_restore" => self.layout_restore(args),
+            "layout_list" => Ok(PortValue::List(
+                layout::list().into_iter().map(PortValue::String).collect(),
+            )),
+            _ => match WindowAction::from_command(cmd, args)? {
+                Some(action) => {
+                    let id = required_arg(args, "id")?;
+                    self.apply(&id, &action)?;
+                    Ok(PortValue::String(format!("{} window: {}", action.verb(), id)))
                 }
-
-                // Fall back to KWin script for UUID-style IDs
-                let escaped_id = serde_json::to_string(id).unwrap_or_default();
-                let script = format!(
-                    r#"workspace.windowList().find(w => w.internalId.toString() === {})?.activate();"#,
-                    escaped_id
-                );
-                self.run_kwin_script(&script)?;
-                Ok(PortValue::String(format!("activated window: {}", id)))
-            }
-            other => Err(PortError {
-                code: -1,
-                message: format!("unknown command: {}", other),
-            }),
+                None => Err(PortError {
+                    code: -1,
+                    message: format!("unknown command: {}", cmd),
+                }),
+            },
         }
     }
+
+    fn subscribe(&self) -> Result<EventStream, PortError> {
+        spawn_watcher("windows", watch_windows)
+    }
+}
+
+fn param(name: &str, description: &str, required: bool) -> ParamDef {
+    ParamDef { name: name.into(), description: description.into(), required }
+}
+
+fn state_param() -> ParamDef {
+    param("state", "on, off or toggle (default: on)", false)
+}
+
+/// Command taking a window ID as its first parameter, followed by `params`.
+fn window_command(name: &str, description: &str, params: Vec<ParamDef>) -> CommandDef {
+    let mut all = vec![param("id", "Window internal ID (from 'list')", true)];
+    all.extend(params);
+    CommandDef { name: name.into(), description: description.into(), params: all }
 }
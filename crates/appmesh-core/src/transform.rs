@@ -0,0 +1,305 @@
+//! Named text transforms for the clipboard port.
+//!
+//! Each transform is a pure `&str -> String` function registered in
+//! [`TRANSFORMS`]. Chains are written as `trim,lower,strip_tracking` (commas or
+//! pipes) and applied left to right.
+
+use base64::Engine;
+
+/// A named text transform.
+pub struct Transform {
+    pub name: &'static str,
+    pub description: &'static str,
+    apply: fn(&str) -> Result<String, String>,
+}
+
+impl Transform {
+    pub fn apply(&self, text: &str) -> Result<String, String> {
+        (self.apply)(text)
+    }
+}
+
+/// All registered transforms, in display order.
+pub const TRANSFORMS: &[Transform] = &[
+    Transform { name: "trim", description: "Strip leading/trailing whitespace", apply: trim },
+    Transform { name: "trim_lines", description: "Strip whitespace from every line and drop blank edges", apply: trim_lines },
+    Transform { name: "upper", description: "Convert to UPPER CASE", apply: upper },
+    Transform { name: "lower", description: "Convert to lower case", apply: lower },
+    Transform { name: "title", description: "Convert To Title Case", apply: title },
+    Transform { name: "strip_tracking", description: "Remove utm_*, fbclid, gclid etc. from URLs", apply: strip_tracking },
+    Transform { name: "markdown_to_text", description: "Render Markdown as plain text", apply: markdown_to_text },
+    Transform { name: "json_pretty", description: "Pretty-print JSON", apply: json_pretty },
+    Transform { name: "json_compact", description: "Minify JSON", apply: json_compact },
+    Transform { name: "base64_encode", description: "Base64-encode (standard alphabet)", apply: base64_encode },
+    Transform { name: "base64_decode", description: "Base64-decode to UTF-8 text", apply: base64_decode },
+    Transform { name: "url_encode", description: "Percent-encode for use in a URL component", apply: url_encode },
+    Transform { name: "url_decode", description: "Decode percent-encoding (and + as space)", apply: url_decode },
+];
+
+/// Look up a transform by name.
+pub fn find(name: &str) -> Option<&'static Transform> {
+    TRANSFORMS.iter().find(|t| t.name == name)
+}
+
+/// Apply a chain like `trim,lower` (or `trim|lower`) to `text`.
+pub fn apply_chain(text: &str, chain: &str) -> Result<String, String> {
+    let names: Vec<&str> = chain
+        .split([',', '|'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    if names.is_empty() {
+        return Err("empty transform chain".into());
+    }
+
+    let mut current = text.to_string();
+    for name in names {
+        let transform = find(name).ok_or_else(|| format!("unknown transform: {}", name))?;
+        current = transform
+            .apply(&current)
+            .map_err(|e| format!("{}: {}", name, e))?;
+    }
+    Ok(current)
+}
+
+fn trim(text: &str) -> Result<String, String> {
+    Ok(text.trim().to_string())
+}
+
+fn trim_lines(text: &str) -> Result<String, String> {
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    Ok(lines.join("\n").trim_matches('\n').to_string())
+}
+
+fn upper(text: &str) -> Result<String, String> {
+    Ok(text.to_uppercase())
+}
+
+fn lower(text: &str) -> Result<String, String> {
+    Ok(text.to_lowercase())
+}
+
+fn title(text: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut at_word_start = true;
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            if at_word_start {
+                out.extend(c.to_uppercase());
+            } else {
+                out.extend(c.to_lowercase());
+            }
+            at_word_start = false;
+        } else {
+            out.push(c);
+            at_word_start = c.is_whitespace() || c == '-' || c == '_';
+        }
+    }
+    Ok(out)
+}
+
+/// Query parameters that only exist to track clicks.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "gclsrc", "dclid", "msclkid", "yclid", "twclid", "igshid",
+    "mc_cid", "mc_eid", "_hsenc", "_hsmi", "mkt_tok", "ref_src", "ref_url", "si",
+];
+
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key)
+}
+
+/// Drop tracking parameters from a URL's query. The pairs that stay are kept
+/// byte for byte: re-encoding them (`%20` as `+`, normalised reserved
+/// characters) could change what they mean to the server.
+fn clean_url(raw: &str) -> Option<String> {
+    let mut url = url::Url::parse(raw).ok()?;
+    let kept = url
+        .query()?
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split_once('=').map_or(*pair, |(key, _)| key);
+            !is_tracking_param(&percent_encoding::percent_decode_str(key).decode_utf8_lossy())
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    url.set_query(if kept.is_empty() { None } else { Some(&kept) });
+    Some(url.to_string())
+}
+
+fn strip_tracking(text: &str) -> Result<String, String> {
+    // Rewrite every whitespace-delimited http(s) URL, keeping surrounding text intact
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("http://").into_iter().chain(rest.find("https://")).min() {
+        out.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        let end = tail
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | ')'))
+            .unwrap_or(tail.len());
+        // Sentence punctuation directly after a URL is almost never part of it
+        let end = tail[..end].trim_end_matches(['.', ',', ';', ':', '!', '?']).len();
+        let candidate = &tail[..end];
+        match clean_url(candidate) {
+            Some(cleaned) => out.push_str(&cleaned),
+            None => out.push_str(candidate),
+        }
+        rest = &tail[end..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn markdown_to_text(text: &str) -> Result<String, String> {
+    use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+    let mut out = String::new();
+    let mut list_depth: usize = 0;
+    for event in Parser::new(text) {
+        match event {
+            Event::Text(t) | Event::Code(t) => out.push_str(&t),
+            Event::SoftBreak | Event::HardBreak => out.push('\n'),
+            Event::Start(Tag::List(_)) => list_depth += 1,
+            Event::End(TagEnd::List(_)) => {
+                list_depth = list_depth.saturating_sub(1);
+                if list_depth == 0 {
+                    out.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str(&"  ".repeat(list_depth.saturating_sub(1)));
+                out.push_str("- ");
+            }
+            Event::End(TagEnd::Item) if !out.ends_with('\n') => out.push('\n'),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock | TagEnd::BlockQuote(_)) => {
+                if list_depth == 0 {
+                    out.push_str("\n\n");
+                } else if !out.ends_with('\n') {
+                    out.push('\n');
+                }
+            }
+            Event::Rule => out.push_str("\n\n"),
+            _ => {}
+        }
+    }
+
+    // Collapse runs of blank lines left behind by block ends
+    let mut collapsed = String::with_capacity(out.len());
+    let mut newlines = 0;
+    for c in out.trim().chars() {
+        if c == '\n' {
+            newlines += 1;
+            if newlines > 2 {
+                continue;
+            }
+        } else {
+            newlines = 0;
+        }
+        collapsed.push(c);
+    }
+    Ok(collapsed)
+}
+
+fn json_pretty(text: &str) -> Result<String, String> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    serde_json::to_string_pretty(&value).map_err(|e| e.to_string())
+}
+
+fn json_compact(text: &str) -> Result<String, String> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    serde_json::to_string(&value).map_err(|e| e.to_string())
+}
+
+fn base64_encode(text: &str) -> Result<String, String> {
+    Ok(base64::engine::general_purpose::STANDARD.encode(text))
+}
+
+fn base64_decode(text: &str) -> Result<String, String> {
+    // Accept wrapped input, optional padding and the URL-safe alphabet
+    let normalized: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            other => other,
+        })
+        .collect();
+    let bytes = base64::engine::general_purpose::STANDARD_NO_PAD
+        .decode(normalized.trim_end_matches('='))
+        .map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|_| "decoded data is not valid UTF-8".to_string())
+}
+
+/// RFC 3986 unreserved characters are left as-is; everything else is escaped.
+const URL_COMPONENT: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+fn url_encode(text: &str) -> Result<String, String> {
+    Ok(percent_encoding::utf8_percent_encode(text, URL_COMPONENT).to_string())
+}
+
+fn url_decode(text: &str) -> Result<String, String> {
+    let with_spaces = text.replace('+', " ");
+    percent_encoding::percent_decode_str(&with_spaces)
+        .decode_utf8()
+        .map(|s| s.into_owned())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_url_drops_tracking_params() {
+        assert_eq!(
+            clean_url("https://example.com/a?utm_source=x&id=7&fbclid=abc").as_deref(),
+            Some("https://example.com/a?id=7")
+        );
+        assert_eq!(
+            clean_url("https://example.com/a?utm_source=x&gclid=1").as_deref(),
+            Some("https://example.com/a")
+        );
+        assert_eq!(clean_url("https://example.com/a"), None);
+        assert_eq!(clean_url("not a url"), None);
+    }
+
+    #[test]
+    fn clean_url_keeps_pairs_untouched() {
+        assert_eq!(
+            clean_url("https://example.com/s?q=a%20b+c&path=%2Fx%3Fy&utm_medium=m&flag").as_deref(),
+            Some("https://example.com/s?q=a%20b+c&path=%2Fx%3Fy&flag")
+        );
+        // Encoded keys are still recognised
+        assert_eq!(
+            clean_url("https://example.com/?utm%5Fsource=x&a=1#top").as_deref(),
+            Some("https://example.com/?a=1#top")
+        );
+    }
+
+    #[test]
+    fn strip_tracking_rewrites_urls_in_text() {
+        assert_eq!(
+            strip_tracking("See https://example.com/p?id=1&utm_source=news. Or (https://x.org/?si=2)")
+                .unwrap(),
+            "See https://example.com/p?id=1. Or (https://x.org/)"
+        );
+        assert_eq!(strip_tracking("no links here").unwrap(), "no links here");
+    }
+
+    #[test]
+    fn apply_chain_runs_left_to_right() {
+        assert_eq!(apply_chain("  Hello World ", "trim|lower").unwrap(), "hello world");
+        assert_eq!(apply_chain("hello-there world", "title").unwrap(), "Hello-There World");
+        assert_eq!(apply_chain("a b", "url_encode, url_decode").unwrap(), "a b");
+        assert_eq!(apply_chain("x", "trim,nope").unwrap_err(), "unknown transform: nope");
+        assert_eq!(apply_chain("x", " , ").unwrap_err(), "empty transform chain");
+        assert!(apply_chain("%%%", "base64_decode").unwrap_err().starts_with("base64_decode: "));
+    }
+}
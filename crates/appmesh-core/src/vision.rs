@@ -0,0 +1,371 @@
+//! Image search and comparison for visual automation.
+//!
+//! Pure functions over [`RgbaImage`]s so they work the same on live captures
+//! and on PNG files. Matching is done on luma only: a template found on screen
+//! is expected to look the same, not to match under a different palette.
+
+use image::RgbaImage;
+
+/// Per-channel difference above which a pixel counts as changed.
+const CHANGE_TOLERANCE: u8 = 16;
+
+/// Most candidates kept from the coarse search for full-resolution refinement.
+const COARSE_CANDIDATES: usize = 256;
+
+/// Coarse scores this far below the best are not refined.
+const COARSE_MARGIN: f64 = 0.15;
+
+/// Smallest template side the coarse search shrinks to.
+const COARSE_MIN_SIDE: usize = 8;
+
+/// Best location of a template within an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Match {
+    pub x: u32,
+    pub y: u32,
+    /// Normalised cross-correlation, 1.0 for a perfect match.
+    pub score: f64,
+}
+
+/// RGBA of the pixel at `(x, y)`, if inside the image.
+pub fn pixel(image: &RgbaImage, x: u32, y: u32) -> Option<[u8; 4]> {
+    (x < image.width() && y < image.height()).then(|| image.get_pixel(x, y).0)
+}
+
+/// `#rrggbb` for a pixel, ignoring alpha.
+pub fn hex([r, g, b, _]: [u8; 4]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Fraction (0.0–1.0) of pixels that differ between two same-sized images.
+/// Images of different sizes are entirely different.
+pub fn changed_fraction(a: &RgbaImage, b: &RgbaImage) -> f64 {
+    if a.dimensions() != b.dimensions() {
+        return 1.0;
+    }
+    let total = (a.width() as usize * a.height() as usize).max(1);
+    let changed = a
+        .pixels()
+        .zip(b.pixels())
+        .filter(|(p, q)| {
+            p.0.iter()
+                .zip(q.0.iter())
+                .any(|(x, y)| x.abs_diff(*y) > CHANGE_TOLERANCE)
+        })
+        .count();
+    changed as f64 / total as f64
+}
+
+/// Find the location where `template` best matches `haystack`.
+///
+/// Scores are zero-mean normalised cross-correlation on luma. Large searches
+/// run coarse-to-fine: both images are box-downscaled, the strongest coarse
+/// positions are kept and then refined at full resolution around each.
+/// Returns `None` if the template does not fit inside the haystack.
+pub fn find_template(haystack: &RgbaImage, template: &RgbaImage) -> Option<Match> {
+    let hay = Gray::from_rgba(haystack);
+    let tpl = Gray::from_rgba(template);
+    if tpl.w == 0 || tpl.h == 0 || tpl.w > hay.w || tpl.h > hay.h {
+        return None;
+    }
+
+    let factor = (tpl.w.min(tpl.h) / COARSE_MIN_SIDE).max(1);
+    if factor == 1 {
+        return best_in(&hay, &tpl, 0..=hay.w - tpl.w, 0..=hay.h - tpl.h);
+    }
+
+    let small_hay = hay.downscale(factor);
+    let small_tpl = tpl.downscale(factor);
+    coarse_candidates(&small_hay, &small_tpl, COARSE_CANDIDATES)
+        .into_iter()
+        .map(|(x, y)| (x * factor, y * factor))
+        .filter_map(|(x, y)| {
+            let x0 = x.saturating_sub(factor);
+            let y0 = y.saturating_sub(factor);
+            let x1 = (x + factor).min(hay.w - tpl.w);
+            let y1 = (y + factor).min(hay.h - tpl.h);
+            best_in(&hay, &tpl, x0..=x1, y0..=y1)
+        })
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+}
+
+/// Grayscale image with prefix sums for O(1) window statistics.
+struct Gray {
+    w: usize,
+    h: usize,
+    px: Vec<f64>,
+    /// `(w + 1) * (h + 1)` summed-area tables of values and squared values.
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+}
+
+impl Gray {
+    fn from_rgba(image: &RgbaImage) -> Self {
+        let px = image
+            .pixels()
+            .map(|p| {
+                let [r, g, b, _] = p.0;
+                0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64
+            })
+            .collect();
+        Self::new(image.width() as usize, image.height() as usize, px)
+    }
+
+    fn new(w: usize, h: usize, px: Vec<f64>) -> Self {
+        let stride = w + 1;
+        let mut sum = vec![0.0; stride * (h + 1)];
+        let mut sum_sq = vec![0.0; stride * (h + 1)];
+        for y in 0..h {
+            for x in 0..w {
+                let v = px[y * w + x];
+                let i = (y + 1) * stride + x + 1;
+                sum[i] = v + sum[i - 1] + sum[i - stride] - sum[i - stride - 1];
+                sum_sq[i] = v * v + sum_sq[i - 1] + sum_sq[i - stride] - sum_sq[i - stride - 1];
+            }
+        }
+        Self { w, h, px, sum, sum_sq }
+    }
+
+    /// Average `factor`×`factor` blocks; partial blocks at the edges are dropped.
+    fn downscale(&self, factor: usize) -> Self {
+        let (w, h) = (self.w / factor, self.h / factor);
+        let area = (factor * factor) as f64;
+        let mut px = Vec::with_capacity(w * h);
+        for y in 0..h {
+            for x in 0..w {
+                px.push(self.window_sum(&self.sum, x * factor, y * factor, factor, factor) / area);
+            }
+        }
+        Self::new(w, h, px)
+    }
+
+    fn window_sum(&self, table: &[f64], x: usize, y: usize, w: usize, h: usize) -> f64 {
+        let stride = self.w + 1;
+        table[(y + h) * stride + x + w] - table[y * stride + x + w] - table[(y + h) * stride + x]
+            + table[y * stride + x]
+    }
+}
+
+/// Template statistics shared by every position of a search.
+struct Prepared<'a> {
+    tpl: &'a Gray,
+    /// Template values minus their mean.
+    centered: Vec<f64>,
+    mean: f64,
+    /// Sum of squared centered values.
+    energy: f64,
+}
+
+impl<'a> Prepared<'a> {
+    fn new(tpl: &'a Gray) -> Self {
+        let n = (tpl.w * tpl.h) as f64;
+        let mean = tpl.px.iter().sum::<f64>() / n;
+        let centered: Vec<f64> = tpl.px.iter().map(|v| v - mean).collect();
+        let energy = centered.iter().map(|v| v * v).sum();
+        Self { tpl, centered, mean, energy }
+    }
+
+    /// Zero-mean NCC of the template placed at `(x, y)` in `hay`.
+    fn score(&self, hay: &Gray, x: usize, y: usize) -> f64 {
+        let (tw, th) = (self.tpl.w, self.tpl.h);
+        let n = (tw * th) as f64;
+        let sum = hay.window_sum(&hay.sum, x, y, tw, th);
+        let window_mean = sum / n;
+        let window_energy = (hay.window_sum(&hay.sum_sq, x, y, tw, th) - sum * sum / n).max(0.0);
+
+        // Flat template or window: correlation is undefined, compare brightness instead
+        if self.energy < 1e-6 || window_energy < 1e-6 {
+            if self.energy < 1e-6 && window_energy < 1e-6 {
+                return 1.0 - (window_mean - self.mean).abs() / 255.0;
+            }
+            return 0.0;
+        }
+
+        let mut cross = 0.0;
+        for ty in 0..th {
+            let row = &hay.px[(y + ty) * hay.w + x..][..tw];
+            let tpl_row = &self.centered[ty * tw..][..tw];
+            cross += row.iter().zip(tpl_row).map(|(h, t)| h * t).sum::<f64>();
+        }
+        cross / (window_energy * self.energy).sqrt()
+    }
+}
+
+fn best_in(
+    hay: &Gray,
+    tpl: &Gray,
+    xs: std::ops::RangeInclusive<usize>,
+    ys: std::ops::RangeInclusive<usize>,
+) -> Option<Match> {
+    let prepared = Prepared::new(tpl);
+    let mut best: Option<Match> = None;
+    for y in ys {
+        for x in xs.clone() {
+            let score = prepared.score(hay, x, y);
+            if best.is_none_or(|b| score > b.score) {
+                best = Some(Match { x: x as u32, y: y as u32, score });
+            }
+        }
+    }
+    best
+}
+
+/// Coarse positions worth refining: local peaks scoring within
+/// [`COARSE_MARGIN`] of the best, strongest first, at most `limit`.
+///
+/// Downscaling blurs away the detail that tells similar spots apart (rows of
+/// identical icons, text), so the true match is often not the coarse best.
+fn coarse_candidates(hay: &Gray, tpl: &Gray, limit: usize) -> Vec<(usize, usize)> {
+    if tpl.w == 0 || tpl.h == 0 || tpl.w > hay.w || tpl.h > hay.h {
+        return Vec::new();
+    }
+    let prepared = Prepared::new(tpl);
+    let (cols, rows) = (hay.w - tpl.w + 1, hay.h - tpl.h + 1);
+    let mut scores = Vec::with_capacity(cols * rows);
+    for y in 0..rows {
+        for x in 0..cols {
+            scores.push(prepared.score(hay, x, y));
+        }
+    }
+
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+    let Some(&best) = order.first().map(|i| &scores[*i]) else {
+        return Vec::new();
+    };
+
+    // Greedy non-maximum suppression: skip neighbours of an accepted peak
+    let mut taken = vec![false; scores.len()];
+    let mut picked = Vec::new();
+    for i in order {
+        if scores[i] < best - COARSE_MARGIN || picked.len() >= limit {
+            break;
+        }
+        if taken[i] {
+            continue;
+        }
+        let (x, y) = (i % cols, i / cols);
+        picked.push((x, y));
+        for ny in y.saturating_sub(1)..=(y + 1).min(rows - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(cols - 1) {
+                taken[ny * cols + nx] = true;
+            }
+        }
+    }
+    picked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// Deterministic noise, so every window of it is distinct.
+    fn noise(w: u32, h: u32, seed: u32) -> RgbaImage {
+        let mut state = seed;
+        RgbaImage::from_fn(w, h, |_, _| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let v = (state >> 24) as u8;
+            Rgba([v, v.wrapping_mul(3), v.wrapping_add(90), 255])
+        })
+    }
+
+    fn solid(w: u32, h: u32, v: u8) -> RgbaImage {
+        RgbaImage::from_pixel(w, h, Rgba([v, v, v, 255]))
+    }
+
+    fn crop(image: &RgbaImage, x: u32, y: u32, w: u32, h: u32) -> RgbaImage {
+        image::imageops::crop_imm(image, x, y, w, h).to_image()
+    }
+
+    #[test]
+    fn finds_exact_match_coarse_to_fine() {
+        let hay = noise(240, 160, 7);
+        // 40×32 takes the downscaled path (factor 4)
+        let found = find_template(&hay, &crop(&hay, 131, 57, 40, 32)).unwrap();
+        assert_eq!((found.x, found.y), (131, 57));
+        assert!((found.score - 1.0).abs() < 1e-9, "score {}", found.score);
+    }
+
+    #[test]
+    fn finds_exact_match_small_template() {
+        let hay = noise(64, 48, 3);
+        // Below 16px a side the search is exhaustive at full resolution
+        let found = find_template(&hay, &crop(&hay, 0, 41, 9, 7)).unwrap();
+        assert_eq!((found.x, found.y), (0, 41));
+        assert!((found.score - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn template_that_does_not_fit() {
+        let hay = noise(30, 20, 1);
+        assert_eq!(find_template(&hay, &noise(31, 10, 2)), None);
+        assert_eq!(find_template(&hay, &noise(10, 21, 2)), None);
+        assert_eq!(find_template(&hay, &RgbaImage::new(0, 0)), None);
+        // Same size fits exactly once
+        assert_eq!(find_template(&hay, &hay).map(|m| (m.x, m.y)), Some((0, 0)));
+    }
+
+    #[test]
+    fn picks_the_right_one_of_repeated_icons() {
+        // A row of identical icons; one has a small mark the coarse pass blurs away
+        let icon = noise(32, 32, 11);
+        let mut marked = icon.clone();
+        for y in 20..24 {
+            for x in 4..8 {
+                marked.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            }
+        }
+        let mut hay = solid(400, 60, 40);
+        for (i, x) in [10, 90, 170, 250, 330].into_iter().enumerate() {
+            let tile = if i == 3 { &marked } else { &icon };
+            image::imageops::replace(&mut hay, tile, x, 14);
+        }
+
+        let found = find_template(&hay, &marked).unwrap();
+        assert_eq!((found.x, found.y), (250, 14));
+        assert!((found.score - 1.0).abs() < 1e-9);
+
+        let found = find_template(&hay, &icon).unwrap();
+        assert_ne!(found.x, 250);
+        assert!((found.score - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn flat_template_matches_by_brightness() {
+        let mut hay = noise(120, 80, 5);
+        image::imageops::replace(&mut hay, &solid(40, 30, 200), 60, 40);
+        image::imageops::replace(&mut hay, &solid(40, 30, 90), 5, 5);
+
+        let found = find_template(&hay, &solid(24, 16, 200)).unwrap();
+        assert!((60..=76).contains(&found.x) && (40..=54).contains(&found.y), "{:?}", found);
+        assert!((found.score - 1.0).abs() < 1e-9);
+
+        let found = find_template(&hay, &solid(24, 16, 95)).unwrap();
+        assert!((5..=21).contains(&found.x) && (5..=19).contains(&found.y), "{:?}", found);
+        assert!((found.score - (1.0 - 5.0 / 255.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn changed_fraction_tolerance() {
+        let a = solid(10, 10, 100);
+        assert_eq!(changed_fraction(&a, &a), 0.0);
+        // Differences up to the tolerance are noise
+        assert_eq!(changed_fraction(&a, &solid(10, 10, 100 + CHANGE_TOLERANCE)), 0.0);
+        assert_eq!(changed_fraction(&a, &solid(10, 10, 100 - CHANGE_TOLERANCE)), 0.0);
+        assert_eq!(changed_fraction(&a, &solid(10, 10, 101 + CHANGE_TOLERANCE)), 1.0);
+
+        // One channel is enough; alpha counts too
+        let mut b = a.clone();
+        for x in 0..10 {
+            for y in 0..5 {
+                b.put_pixel(x, y, Rgba([100, 100, 200, 255]));
+            }
+        }
+        b.put_pixel(0, 9, Rgba([100, 100, 100, 0]));
+        assert_eq!(changed_fraction(&a, &b), 0.51);
+
+        assert_eq!(changed_fraction(&a, &solid(10, 11, 100)), 1.0);
+        assert_eq!(changed_fraction(&RgbaImage::new(0, 0), &RgbaImage::new(0, 0)), 0.0);
+    }
+}
@@ -0,0 +1,261 @@
+//! Mail port push events against a stand-in JMAP server on 127.0.0.1.
+//!
+//! The server's first EventSource connection announces one Email state and
+//! closes; the changes after that are only reachable by the watcher's
+//! catch-up once it has reconnected.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use appmesh_core::port::{AppMeshPort, EventStream, PortEvent, PortValue};
+use appmesh_core::ports::mail::MailPort;
+use axum::extract::State;
+use axum::response::sse::{Event, Sse};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::stream::{self, BoxStream, StreamExt};
+use serde_json::{json, Value};
+
+const ACCOUNT: &str = "a1";
+
+#[derive(Default)]
+struct Server {
+    base: String,
+    /// EventSource connections opened so far.
+    pushes: usize,
+}
+
+type Shared = Arc<Mutex<Server>>;
+
+async fn session(State(server): State<Shared>) -> Json<Value> {
+    let base = server.lock().unwrap().base.clone();
+    Json(json!({
+        "capabilities": {
+            "urn:ietf:params:jmap:core": {
+                "maxSizeUpload": 50000000,
+                "maxConcurrentUpload": 4,
+                "maxSizeRequest": 10000000,
+                "maxConcurrentRequests": 4,
+                "maxCallsInRequest": 16,
+                "maxObjectsInGet": 500,
+                "maxObjectsInSet": 500,
+                "collationAlgorithms": []
+            },
+            "urn:ietf:params:jmap:mail": {}
+        },
+        "accounts": {
+            ACCOUNT: {
+                "name": "ann@example.com",
+                "isPersonal": true,
+                "isReadOnly": false,
+                "accountCapabilities": { "urn:ietf:params:jmap:mail": {} }
+            }
+        },
+        "primaryAccounts": { "urn:ietf:params:jmap:mail": ACCOUNT },
+        "username": "ann@example.com",
+        "apiUrl": format!("{}/api", base),
+        "downloadUrl": format!("{}/download/{{accountId}}/{{blobId}}/{{name}}?type={{type}}", base),
+        "uploadUrl": format!("{}/upload/{{accountId}}", base),
+        "eventSourceUrl": format!("{}/events?types={{types}}&closeafter={{closeafter}}&ping={{ping}}", base),
+        "state": "s0"
+    }))
+}
+
+/// First connection: one state change, then the server hangs up. Later
+/// connections stay open and quiet.
+async fn events(State(server): State<Shared>) -> Sse<BoxStream<'static, Result<Event, Infallible>>> {
+    let first = {
+        let mut server = server.lock().unwrap();
+        server.pushes += 1;
+        server.pushes == 1
+    };
+    let stream = if first {
+        let change = json!({
+            "@type": "StateChange",
+            "changed": { ACCOUNT: { "Email": "e1", "Mailbox": "m0" } }
+        });
+        stream::iter([Ok(Event::default().event("state").data(change.to_string()))]).boxed()
+    } else {
+        stream::pending().boxed()
+    };
+    Sse::new(stream)
+}
+
+async fn api(Json(request): Json<Value>) -> Json<Value> {
+    let responses: Vec<Value> = request["methodCalls"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|call| {
+            let (method, args, id) = (call[0].as_str().unwrap(), &call[1], &call[2]);
+            let result = match method {
+                "Email/get" => get_response("e0", emails(), &args["ids"]),
+                "Mailbox/get" => get_response("m0", mailboxes(), &args["ids"]),
+                "Email/changes" => match args["sinceState"].as_str().unwrap() {
+                    "e0" => changes("e0", "e1", &["m1", "m2"], &["m3"], &["m4"]),
+                    "e1" => changes("e1", "e2", &["m5"], &[], &[]),
+                    other => changes(other, other, &[], &[], &[]),
+                },
+                "Mailbox/changes" => match args["sinceState"].as_str().unwrap() {
+                    "m0" => changes("m0", "m1", &[], &["inbox"], &[]),
+                    other => changes(other, other, &[], &[], &[]),
+                },
+                other => panic!("unexpected method {}", other),
+            };
+            json!([method, result, id])
+        })
+        .collect();
+    Json(json!({ "methodResponses": responses, "sessionState": "s0" }))
+}
+
+fn get_response(state: &str, all: Vec<Value>, ids: &Value) -> Value {
+    let list: Vec<Value> = match ids.as_array() {
+        Some(ids) => ids
+            .iter()
+            .filter_map(|id| all.iter().find(|item| item["id"] == *id).cloned())
+            .collect(),
+        None => all,
+    };
+    json!({ "accountId": ACCOUNT, "state": state, "list": list, "notFound": [] })
+}
+
+fn changes(old: &str, new: &str, created: &[&str], updated: &[&str], destroyed: &[&str]) -> Value {
+    json!({
+        "accountId": ACCOUNT,
+        "oldState": old,
+        "newState": new,
+        "hasMoreChanges": false,
+        "created": created,
+        "updated": updated,
+        "destroyed": destroyed,
+        "updatedProperties": null
+    })
+}
+
+fn mailboxes() -> Vec<Value> {
+    vec![
+        json!({ "id": "inbox", "name": "Inbox", "role": "inbox", "totalEmails": 3, "unreadEmails": 2 }),
+        json!({ "id": "sent", "name": "Sent", "role": "sent", "totalEmails": 1, "unreadEmails": 0 }),
+    ]
+}
+
+fn emails() -> Vec<Value> {
+    let email = |id: &str, subject: &str, mailbox: &str, keywords: Value| {
+        json!({
+            "id": id,
+            "threadId": format!("t-{}", id),
+            "subject": subject,
+            "from": [{ "name": "Bob", "email": "bob@example.com" }],
+            "to": [{ "name": null, "email": "ann@example.com" }],
+            "receivedAt": "2026-10-18T09:00:00Z",
+            "preview": "",
+            "mailboxIds": { mailbox: true },
+            "keywords": keywords
+        })
+    };
+    vec![
+        email("m1", "Lunch?", "inbox", json!({})),
+        email("m2", "Re: Lunch?", "sent", json!({ "$seen": true })),
+        email("m3", "Minutes", "inbox", json!({ "$seen": true, "$flagged": true })),
+        email("m5", "Sent while offline", "inbox", json!({})),
+    ]
+}
+
+/// Serve on an ephemeral port from a thread of its own; returns the base URL.
+fn start_server(server: Shared) -> String {
+    let (ready_tx, ready_rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let base = format!("http://{}", listener.local_addr().unwrap());
+            server.lock().unwrap().base = base.clone();
+            let app = Router::new()
+                .route("/.well-known/jmap", get(session))
+                .route("/events", get(events))
+                .route("/api", post(api))
+                .with_state(server);
+            ready_tx.send(base).unwrap();
+            axum::serve(listener, app).await.unwrap();
+        });
+    });
+    ready_rx.recv_timeout(Duration::from_secs(10)).expect("JMAP server did not start")
+}
+
+fn field<'a>(value: &'a PortValue, name: &str) -> &'a PortValue {
+    match value {
+        PortValue::Map(map) => map.get(name).unwrap_or_else(|| panic!("no '{}' in {:?}", name, value)),
+        other => panic!("expected a map, got {:?}", other),
+    }
+}
+
+fn string(value: &PortValue) -> &str {
+    match value {
+        PortValue::String(s) => s,
+        other => panic!("expected a string, got {:?}", other),
+    }
+}
+
+/// The next event, failing on watcher errors. Reconnecting takes a few seconds.
+fn next(events: &EventStream) -> PortEvent {
+    let event = events.next(Duration::from_secs(20)).expect("no event from the mail watcher");
+    assert_ne!(event.event, "error", "watcher error: {:?}", event.data);
+    event
+}
+
+#[test]
+fn watch_reports_pushed_changes_and_catches_up_after_reconnect() {
+    // Keep saved accounts and caches out of the picture
+    let home = std::env::temp_dir().join(format!("appmesh-mail-watch-{}", std::process::id()));
+    std::env::set_var("XDG_CONFIG_HOME", home.join("config"));
+    std::env::set_var("XDG_CACHE_HOME", home.join("cache"));
+    for var in ["JMAP_URL", "JMAP_USER", "JMAP_PASS", "JMAP_ACCOUNT_ID"] {
+        std::env::remove_var(var);
+    }
+
+    let server = Shared::default();
+    let base = start_server(server.clone());
+    let port = MailPort::new().unwrap();
+    let args: HashMap<String, String> = [("url", base.as_str()), ("user", "ann"), ("pass", "secret")]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    port.execute("connect", &args).unwrap();
+    let events = port.subscribe().unwrap();
+
+    // Pushed: arrived in the inbox, stored by us in Sent, changed, removed
+    let event = next(&events);
+    assert_eq!((event.event.as_str(), string(field(&event.data, "id"))), ("new_mail", "m1"));
+    assert!(matches!(field(&event.data, "unread"), PortValue::Bool(true)));
+    assert_eq!(string(field(&event.data, "from")), "Bob <bob@example.com>");
+    match field(&event.data, "mailboxes") {
+        PortValue::List(names) => assert_eq!(names.iter().map(string).collect::<Vec<_>>(), ["Inbox"]),
+        other => panic!("expected mailbox names, got {:?}", other),
+    }
+
+    let event = next(&events);
+    assert_eq!((event.event.as_str(), string(field(&event.data, "id"))), ("mail_created", "m2"));
+
+    let event = next(&events);
+    assert_eq!((event.event.as_str(), string(field(&event.data, "id"))), ("mail_updated", "m3"));
+    assert!(matches!(field(&event.data, "unread"), PortValue::Bool(false)));
+
+    let event = next(&events);
+    assert_eq!((event.event.as_str(), string(field(&event.data, "id"))), ("mail_destroyed", "m4"));
+
+    // The push connection closed; what changed since comes from the catch-up,
+    // Email before Mailbox, with the unchanged Mailbox state not refetched before
+    let event = next(&events);
+    assert_eq!((event.event.as_str(), string(field(&event.data, "id"))), ("new_mail", "m5"));
+    let event = next(&events);
+    assert_eq!(event.event, "mailbox_updated");
+    assert_eq!(string(field(&event.data, "name")), "Inbox");
+    assert!(matches!(field(&event.data, "total"), PortValue::Int(3)));
+    assert!(matches!(field(&event.data, "unread"), PortValue::Int(2)));
+
+    assert_eq!(server.lock().unwrap().pushes, 2, "watcher did not reopen the push connection");
+    assert!(events.next(Duration::from_millis(500)).is_none());
+    let _ = std::fs::remove_dir_all(home);
+}
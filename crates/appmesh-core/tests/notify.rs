@@ -0,0 +1,273 @@
+//! Notify port against a stand-in notification server on a private session bus.
+//!
+//! Needs `dbus-daemon` on PATH; skipped without it.
+
+use std::collections::HashMap;
+use std::process::{Child, Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use appmesh_core::port::{AppMeshPort, PortValue};
+use appmesh_core::ports::notify::NotifyPort;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::OwnedValue;
+
+/// A Notify call as the server received it.
+struct Received {
+    app: String,
+    title: String,
+    actions: Vec<String>,
+    hints: HashMap<String, OwnedValue>,
+}
+
+#[derive(Default)]
+struct Seen {
+    notified: Vec<Received>,
+    closed: Vec<u32>,
+}
+
+/// Minimal org.freedesktop.Notifications: numbers notifications from 1,
+/// answers anything with actions by invoking its first action, and reports
+/// every close as "closed by call".
+struct Server {
+    next_id: u32,
+    seen: Arc<Mutex<Seen>>,
+}
+
+#[zbus::interface(name = "org.freedesktop.Notifications")]
+impl Server {
+    #[allow(clippy::too_many_arguments)]
+    async fn notify(
+        &mut self,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        app_name: String,
+        _replaces_id: u32,
+        _icon: String,
+        summary: String,
+        _body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        _timeout: i32,
+    ) -> u32 {
+        self.next_id += 1;
+        let id = self.next_id;
+        if let Some(key) = actions.first().cloned() {
+            // Click after the reply has gone out, like a user would
+            let emitter = emitter.to_owned();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                let _ = Server::action_invoked(&emitter, id, &key).await;
+            });
+        }
+        self.seen.lock().unwrap().notified.push(Received { app: app_name, title: summary, actions, hints });
+        id
+    }
+
+    async fn close_notification(&self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>, id: u32) {
+        self.seen.lock().unwrap().closed.push(id);
+        let _ = Server::notification_closed(&emitter, id, 3).await;
+    }
+
+    fn get_capabilities(&self) -> Vec<String> {
+        vec!["actions".into(), "body".into()]
+    }
+
+    fn get_server_information(&self) -> (String, String, String, String) {
+        ("stand-in".into(), "appmesh".into(), "1.0".into(), "1.2".into())
+    }
+
+    #[zbus(signal)]
+    async fn action_invoked(emitter: &SignalEmitter<'_>, id: u32, action_key: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn notification_closed(emitter: &SignalEmitter<'_>, id: u32, reason: u32) -> zbus::Result<()>;
+}
+
+/// A private session bus, killed on drop.
+struct Bus(Child);
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn start_bus() -> Option<(Bus, String)> {
+    let mut child = Command::new("dbus-daemon")
+        .args(["--session", "--nofork", "--print-address"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let mut line = String::new();
+    let stdout = child.stdout.take()?;
+    std::io::BufRead::read_line(&mut std::io::BufReader::new(stdout), &mut line).ok()?;
+    Some((Bus(child), line.trim().to_string()))
+}
+
+/// Serve the stand-in on its own thread and runtime for the rest of the test.
+fn start_server(address: &str, seen: Arc<Mutex<Seen>>) {
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let address = address.to_string();
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        rt.block_on(async move {
+            let server = Server { next_id: 0, seen };
+            let connection = zbus::connection::Builder::address(address.as_str())
+                .unwrap()
+                .name("org.freedesktop.Notifications")
+                .unwrap()
+                .serve_at("/org/freedesktop/Notifications", server)
+                .unwrap()
+                .build()
+                .await
+                .unwrap();
+            ready_tx.send(()).unwrap();
+            std::future::pending::<()>().await;
+            drop(connection);
+        });
+    });
+    ready_rx.recv_timeout(Duration::from_secs(10)).expect("notification server did not start");
+}
+
+fn args(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+fn field<'a>(value: &'a PortValue, name: &str) -> &'a PortValue {
+    match value {
+        PortValue::Map(map) => map.get(name).unwrap_or_else(|| panic!("no '{}' in {:?}", name, value)),
+        other => panic!("expected a map, got {:?}", other),
+    }
+}
+
+fn int(value: &PortValue) -> i64 {
+    match value {
+        PortValue::Int(i) => *i,
+        other => panic!("expected an int, got {:?}", other),
+    }
+}
+
+fn string(value: &PortValue) -> &str {
+    match value {
+        PortValue::String(s) => s,
+        other => panic!("expected a string, got {:?}", other),
+    }
+}
+
+fn list(value: PortValue) -> Vec<PortValue> {
+    match value {
+        PortValue::List(items) => items,
+        other => panic!("expected a list, got {:?}", other),
+    }
+}
+
+// One test: the port finds its bus through the process environment.
+#[test]
+fn notify_port_against_stand_in_server() {
+    let Some((_bus, address)) = start_bus() else {
+        eprintln!("skipping: dbus-daemon not available");
+        return;
+    };
+    std::env::set_var("DBUS_SESSION_BUS_ADDRESS", &address);
+    let seen = Arc::new(Mutex::new(Seen::default()));
+    start_server(&address, seen.clone());
+
+    let port = NotifyPort::new().unwrap();
+
+    // Recording starts with the first history call, so nothing is there yet
+    assert!(list(port.execute("history", &HashMap::new()).unwrap()).is_empty());
+
+    let sent = port
+        .execute(
+            "send",
+            &args(&[
+                ("title", "Build finished"),
+                ("body", "all green"),
+                ("urgency", "critical"),
+                ("category", "transfer.complete"),
+                ("desktop_entry", "org.kde.konsole.desktop"),
+                ("progress", "40"),
+            ]),
+        )
+        .unwrap();
+    assert_eq!(int(field(&sent, "id")), 1);
+    {
+        let seen = seen.lock().unwrap();
+        let call = &seen.notified[0];
+        assert_eq!(call.app, "AppMesh");
+        assert_eq!(call.title, "Build finished");
+        assert!(call.actions.is_empty());
+        assert_eq!(u8::try_from(&call.hints["urgency"]).unwrap(), 2);
+        assert_eq!(<&str>::try_from(&call.hints["category"]).unwrap(), "transfer.complete");
+        assert_eq!(<&str>::try_from(&call.hints["desktop-entry"]).unwrap(), "org.kde.konsole");
+        assert_eq!(i32::try_from(&call.hints["value"]).unwrap(), 40);
+    }
+
+    let answer = port
+        .execute("ask", &args(&[("title", "Deploy?"), ("actions", "yes:Deploy,no:Cancel"), ("wait", "5000")]))
+        .unwrap();
+    assert_eq!(int(field(&answer, "id")), 2);
+    assert_eq!(string(field(&answer, "action")), "yes");
+    assert_eq!(string(field(&answer, "reason")), "action");
+    assert_eq!(seen.lock().unwrap().notified[1].actions, ["yes", "Deploy", "no", "Cancel"]);
+
+    let closed = port.execute("close", &args(&[("id", "1")])).unwrap();
+    assert_eq!(int(field(&closed, "id")), 1);
+    // ask closes its notification once answered, then close closes the first
+    assert_eq!(seen.lock().unwrap().closed, [2, 1]);
+
+    // The monitor sees both Notify calls once their replies carry IDs
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let history = loop {
+        let history = list(port.execute("history", &HashMap::new()).unwrap());
+        if history.len() >= 2 || Instant::now() > deadline {
+            break history;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    assert_eq!(history.len(), 2, "history: {:?}", history);
+    assert_eq!(int(field(&history[0], "id")), 1);
+    assert_eq!(string(field(&history[0], "title")), "Build finished");
+    assert_eq!(string(field(&history[0], "urgency")), "critical");
+    assert_eq!(string(field(&history[0], "desktop_entry")), "org.kde.konsole");
+    assert_eq!(int(field(&history[1], "id")), 2);
+    assert_eq!(list(field(&history[1], "actions").clone()).len(), 2);
+
+    let limited = list(port.execute("history", &args(&[("limit", "1")])).unwrap());
+    assert_eq!(string(field(&limited[0], "title")), "Deploy?");
+
+    // Dropping the port takes its connection and its monitor's off the bus.
+    // Monitors are not listed by ListNames, so count via the daemon's stats,
+    // which not every dbus-daemon build has.
+    let Some(with_port) = connections(&address) else { return };
+    drop(port);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while connections(&address) > Some(with_port - 2) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(connections(&address), Some(with_port - 2), "connections outlived the port");
+}
+
+/// Active connections on the bus, from org.freedesktop.DBus.Debug.Stats.
+fn connections(address: &str) -> Option<u32> {
+    let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    rt.block_on(async {
+        let connection = zbus::connection::Builder::address(address).ok()?.build().await.ok()?;
+        let stats: HashMap<String, OwnedValue> = connection
+            .call_method(
+                Some("org.freedesktop.DBus"),
+                "/org/freedesktop/DBus",
+                Some("org.freedesktop.DBus.Debug.Stats"),
+                "GetStats",
+                &(),
+            )
+            .await
+            .ok()?
+            .body()
+            .deserialize()
+            .ok()?;
+        u32::try_from(stats.get("ActiveConnections")?).ok()
+    })
+}
@@ -54,8 +54,10 @@ QStringList AppMeshBridge::ports() const
 {
     return {
         QStringLiteral("clipboard"),
+        QStringLiteral("desktops"),
         QStringLiteral("input"),
         QStringLiteral("notify"),
+        QStringLiteral("outputs"),
         QStringLiteral("screenshot"),
         QStringLiteral("windows")
     };
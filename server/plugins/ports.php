@@ -11,10 +11,73 @@ require_once __DIR__ . '/../appmesh-ffi.php';
 $portTools = [];
 
 // Port definitions: name => [commands => [cmd => [description, params, required]]]
+// Hint arguments accepted by every notify command that shows a notification
+$notifyHints = [
+    'urgency' => prop('string', 'low, normal or critical'),
+    'category' => prop('string', 'Notification category (e.g. transfer.complete, im.received)'),
+    'desktop_entry' => prop('string', 'Desktop file name of the sending app, without .desktop'),
+    'transient' => prop('string', 'true to skip the notification history'),
+    'resident' => prop('string', 'true to keep it open after an action is invoked'),
+    'image_path' => prop('string', 'Image shown in the notification, as a file path, file:// URI or icon name'),
+    'image' => prop('string', 'Image file sent inline as pixel data'),
+    'progress' => prop('string', 'Progress percentage 0-100 (KDE)'),
+];
+
+// Filter, sort and paging arguments shared by mail query and search
+$mailQuery = [
+    'from' => prop('string', 'Sender contains'),
+    'to' => prop('string', 'Recipient contains'),
+    'subject' => prop('string', 'Subject contains'),
+    'before' => prop('string', 'Received before (YYYY-MM-DD, YYYY-MM-DDTHH:MM:SS or unix time, UTC)'),
+    'after' => prop('string', 'Received at or after (YYYY-MM-DD, YYYY-MM-DDTHH:MM:SS or unix time, UTC)'),
+    'has_attachment' => prop('string', 'true/false'),
+    'keyword' => prop('string', 'Has keyword (e.g. $flagged, $seen)'),
+    'not_keyword' => prop('string', 'Lacks keyword (e.g. $seen for unread)'),
+    'min_size' => prop('string', 'Minimum size in bytes'),
+    'max_size' => prop('string', 'Maximum size in bytes'),
+    'not_in' => prop('string', 'Comma-separated mailboxes to exclude (e.g. Trash,Junk)'),
+    'sort' => prop('string', 'Comma-separated received, sent, size, from, to, subject, each with optional :asc/:desc (default: received:desc)'),
+    'limit' => prop('string', 'Max results (default: 20)'),
+    'position' => prop('string', '0-based index of the first result (use next_position from the previous page)'),
+    'anchor' => prop('string', 'Email ID to page from instead of position'),
+    'anchor_offset' => prop('string', 'Offset from the anchor (may be negative)'),
+    'collapse_threads' => prop('string', 'true for one row per conversation, with count and participants'),
+];
+
+// Compose options shared by mail send, reply, forward and drafts
+$mailCompose = [
+    'from' => prop('string', 'From address, optionally with a name (default: first identity)'),
+    'cc' => prop('string', 'Cc recipients, comma-separated'),
+    'bcc' => prop('string', 'Bcc recipients, comma-separated'),
+    'reply_to' => prop('string', 'Reply-To addresses, comma-separated'),
+    'html' => prop('string', 'HTML body; sent as multipart/alternative with the text body'),
+    'attach' => prop('string', 'Comma-separated local file paths to attach'),
+    'headers' => prop('string', 'Extra header lines, newline-separated (e.g. X-Priority: 1)'),
+];
+
+// Delayed sending, shared by everything in mail that submits
+$mailSchedule = [
+    'send_at' => prop('string', 'Send at this time (YYYY-MM-DD, YYYY-MM-DDTHH:MM:SS or unix time, UTC)'),
+    'delay' => prop('string', 'Send after this many seconds; cancel_send until then'),
+];
+
+// Parameters shared by mail reply and reply_all
+$mailReply = [
+    'id' => prop('string', 'Email ID to reply to'),
+    'body' => prop('string', 'Reply body text (required unless html is given)'),
+    'to' => prop('string', 'Extra recipients besides the original sender'),
+    'quote' => prop('string', 'Quote the original below the reply (default: true)'),
+] + $mailCompose + $mailSchedule;
+
 $ports = [
     'clipboard' => [
         'get' => ['Get clipboard contents', [], []],
         'set' => ['Set clipboard contents', ['text' => prop('string', 'Text to copy')], ['text']],
+        'transform' => ['Transform clipboard contents through a chain of named transforms', [
+            'ops' => prop('string', 'Transform chain, e.g. trim,lower,strip_tracking'),
+            'dry_run' => prop('string', 'Return the result without setting the clipboard (true/false, default: false)'),
+        ], ['ops']],
+        'transforms' => ['List available clipboard transforms', [], []],
     ],
     'notify' => [
         'send' => [
@@ -23,47 +86,157 @@ $ports = [
                 'title' => prop('string', 'Notification title'),
                 'body' => prop('string', 'Notification body text'),
                 'icon' => prop('string', 'Icon name (default: dialog-information)'),
-            ],
+                'timeout' => prop('string', 'Timeout in ms (-1=server default, 0=never)'),
+                'actions' => prop('string', 'Action buttons as key:Label pairs, e.g. yes:Approve,no:Reject'),
+            ] + $notifyHints,
             ['title'],
         ],
+        'update' => [
+            'Replace a shown notification in place (e.g. to advance progress)',
+            [
+                'id' => prop('string', 'Notification ID from send'),
+                'title' => prop('string', 'Notification title'),
+                'body' => prop('string', 'Notification body text'),
+                'icon' => prop('string', 'Icon name (default: dialog-information)'),
+                'timeout' => prop('string', 'Timeout in ms (-1=server default, 0=never)'),
+                'actions' => prop('string', 'Action buttons as key:Label pairs, e.g. yes:Approve,no:Reject'),
+            ] + $notifyHints,
+            ['id', 'title'],
+        ],
+        'close' => ['Close a notification', ['id' => prop('string', 'Notification ID from send')], ['id']],
+        'capabilities' => ['List features the notification server supports', [], []],
+        'server_info' => ['Notification server name, vendor, version and spec version', [], []],
+        'history' => ['Notifications shown by any application since history or watch was first used, oldest first', [
+            'limit' => prop('string', 'Return only the most recent N'),
+            'app' => prop('string', 'Only notifications from this application name'),
+        ], []],
+        'ask' => [
+            'Show a notification with action buttons and wait for the user\'s choice',
+            [
+                'title' => prop('string', 'Notification title'),
+                'actions' => prop('string', 'Action buttons as key:Label pairs, e.g. yes:Approve,no:Reject'),
+                'body' => prop('string', 'Notification body text'),
+                'icon' => prop('string', 'Icon name (default: dialog-information)'),
+                'wait' => prop('string', 'Ms to wait for an answer before closing it (default: 60000)'),
+            ] + $notifyHints,
+            ['title', 'actions'],
+        ],
+    ],
+    'outputs' => [
+        'list' => ['List outputs with geometry, scale, rotation, refresh rate and state', [], []],
+        'enable' => ['Enable an output', ['output' => prop('string', 'Output name (e.g. DP-1)')], ['output']],
+        'disable' => ['Disable an output', ['output' => prop('string', 'Output name (e.g. DP-1)')], ['output']],
+        'config_save' => ['Save the current output configuration under a name', [
+            'name' => prop('string', 'Configuration name (e.g. docked)'),
+        ], ['name']],
+        'config_apply' => ['Switch to a saved output configuration', ['name' => prop('string', 'Configuration name')], ['name']],
+        'config_list' => ['List saved output configurations', [], []],
     ],
     'screenshot' => [
         'take' => [
-            'Take a screenshot',
-            ['mode' => prop('string', 'fullscreen, activewindow, or region', ['enum' => ['fullscreen', 'activewindow', 'region']])],
+            'Take a screenshot; returns path (or inline base64 data), width, height, format, size and backend',
+            [
+                'mode' => prop('string', 'fullscreen, screen, activewindow, pick_window, pick_screen or region', ['enum' => ['fullscreen', 'screen', 'activewindow', 'pick_window', 'pick_screen', 'region']]),
+                'window' => prop('string', 'Capture this window (internal ID from windows list) instead of mode'),
+                'rect' => prop('string', 'Capture x,y,width,height in global coordinates instead of mode'),
+                'output' => prop('string', 'Capture this output (e.g. DP-1) instead of mode'),
+                'cursor' => prop('string', 'Include the mouse cursor (true/false, default: false)'),
+                'decoration' => prop('string', 'Include window title bar and borders (true/false, default: true)'),
+                'inline' => prop('string', 'Return base64 data instead of writing a file (true/false, default: false)'),
+                'format' => prop('string', 'png, jpeg or webp (default: png)', ['enum' => ['png', 'jpeg', 'webp']]),
+                'quality' => prop('string', 'JPEG quality 1-100 (default: 90)'),
+                'backend' => prop('string', 'auto, kwin or spectacle (default: auto)', ['enum' => ['auto', 'kwin', 'spectacle']]),
+            ],
             [],
         ],
+        'pixel' => ['Get the color of a screen (or image) pixel', [
+            'x' => prop('string', 'X coordinate'),
+            'y' => prop('string', 'Y coordinate'),
+            'image' => prop('string', 'Analyse this image file instead of the screen'),
+        ], ['x', 'y']],
+        'find' => ['Find the best match of a template image on screen (or in an image)', [
+            'template' => prop('string', 'Path to the PNG to look for'),
+            'rect' => prop('string', 'Region x,y,width,height (default: whole screen or image)'),
+            'threshold' => prop('string', 'Minimum score 0-1 to report found (default: 0.9)'),
+            'image' => prop('string', 'Analyse this image file instead of the screen'),
+        ], ['template']],
+        'wait_change' => ['Wait until a screen region changes or stops changing', [
+            'rect' => prop('string', 'Region x,y,width,height (default: whole screen)'),
+            'until' => prop('string', 'change or stable (default: change)', ['enum' => ['change', 'stable']]),
+            'threshold' => prop('string', 'Fraction of pixels 0-1 that counts as a change (default: 0.01)'),
+            'stable_for' => prop('string', 'Ms without change that counts as stable (default: 1000)'),
+            'interval' => prop('string', 'Ms between captures (default: 250)'),
+            'timeout' => prop('string', 'Timeout in ms (default: 10000)'),
+        ], []],
+        'process' => ['Crop, resize, annotate or redact an image and write a new file', [
+            'image' => prop('string', 'Input image path (e.g. from take)'),
+            'ops' => prop('string', 'Operations separated by |: crop:x,y,w,h; resize:w[,h] or resize:50%; rect:x,y,w,h[,color[,thickness]]; fill:x,y,w,h[,color]; arrow:x1,y1,x2,y2[,color[,thickness]]; text:x,y,size,color,label; blur:x,y,w,h[,sigma]; pixelate:x,y,w,h[,block]'),
+            'output' => prop('string', 'Output path (default: new file under /run/user/<uid>/appmesh)'),
+            'format' => prop('string', 'png, jpeg or webp (default: from output or input extension)', ['enum' => ['png', 'jpeg', 'webp']]),
+            'quality' => prop('string', 'JPEG quality 1-100 (default: 90)'),
+            'inline' => prop('string', 'Return base64 data instead of writing a file (true/false, default: false)'),
+        ], ['image', 'ops']],
     ],
     'mail' => [
         'connect' => ['Connect to JMAP mail server', [
             'url' => prop('string', 'JMAP server URL'),
             'user' => prop('string', 'Email/username'),
             'pass' => prop('string', 'Password'),
+            'account_id' => prop('string', 'JMAP accountId, e.g. of a shared mailbox (default: primary)'),
+            'save' => prop('string', 'true to save the login as this named account'),
         ], []],
         'status' => ['Check mail connection status', [], []],
         'mailboxes' => ['List all mailboxes', [], []],
-        'query' => ['Query emails in a mailbox', [
-            'mailbox' => prop('string', 'Mailbox name or ID (default: Inbox)'),
-            'limit' => prop('string', 'Max results (default: 20)'),
-        ], []],
+        'query' => ['Query emails in a mailbox, one page at a time', [
+            'mailbox' => prop('string', 'Mailbox name or ID, or * for all (default: Inbox)'),
+            'live' => prop('string', 'true to skip the offline cache and ask the server'),
+        ] + $mailQuery, []],
         'read' => ['Read an email by ID', [
             'id' => prop('string', 'Email ID'),
         ], ['id']],
+        'thread' => ['All messages in an email\'s conversation, oldest first', [
+            'id' => prop('string', 'Email ID'),
+        ], ['id']],
         'mark_read' => ['Mark email as read', [
             'id' => prop('string', 'Email ID'),
         ], ['id']],
         // Phase 2: Send & Compose
         'identities' => ['List sender identities', [], []],
         'send' => ['Send an email', [
-            'to' => prop('string', 'Recipient email address'),
+            'to' => prop('string', 'Recipients, comma-separated (e.g. Ann <ann@example.com>, bob@example.com)'),
+            'subject' => prop('string', 'Email subject'),
+            'body' => prop('string', 'Email body text (required unless html is given)'),
+        ] + $mailCompose + $mailSchedule, ['to', 'subject']],
+        'reply' => ['Reply to the sender (Reply-To if set), quoting the original', $mailReply, ['id']],
+        'reply_all' => ['Reply to the sender and all other recipients', $mailReply, ['id']],
+        'forward' => ['Forward an email inline or as an attachment', [
+            'id' => prop('string', 'Email ID to forward'),
+            'to' => prop('string', 'Recipients, comma-separated'),
+            'body' => prop('string', 'Note above the forwarded message'),
+            'mode' => prop('string', 'inline (default) or attachment (original as .eml)'),
+        ] + $mailCompose + $mailSchedule, ['id', 'to']],
+        // Drafts
+        'draft_save' => ['Save a message to Drafts for review instead of sending', [
+            'to' => prop('string', 'Recipients, comma-separated'),
+            'subject' => prop('string', 'Email subject'),
+            'body' => prop('string', 'Email body text'),
+        ] + $mailCompose, []],
+        'draft_update' => ['Replace fields of a draft; returns the new draft ID', [
+            'id' => prop('string', 'Draft email ID'),
+            'to' => prop('string', 'Recipients, comma-separated'),
             'subject' => prop('string', 'Email subject'),
             'body' => prop('string', 'Email body text'),
-            'from' => prop('string', 'From address (default: first identity)'),
-        ], ['to', 'subject', 'body']],
-        'reply' => ['Reply to an email', [
-            'id' => prop('string', 'Email ID to reply to'),
-            'body' => prop('string', 'Reply body text'),
-        ], ['id', 'body']],
+        ] + $mailCompose, ['id']],
+        'drafts' => ['List drafts', $mailQuery, []],
+        'draft_send' => ['Send a draft; it moves to Sent once submitted', [
+            'id' => prop('string', 'Draft email ID'),
+        ] + $mailSchedule, ['id']],
+        // Submissions
+        'submissions' => ['List recent submissions with undo and delivery status', [
+            'status' => prop('string', 'pending, final or canceled'),
+            'limit' => prop('string', 'Max results (default: 20)'),
+        ], []],
+        'cancel_send' => ['Cancel a scheduled send; the message returns to Drafts', ['id' => prop('string', 'Submission ID')], ['id']],
         // Phase 3: Mail Management
         'move' => ['Move email to another mailbox', [
             'id' => prop('string', 'Email ID'),
@@ -84,8 +257,8 @@ $ports = [
         ], ['id']],
         'search' => ['Full-text search across all mailboxes', [
             'text' => prop('string', 'Search text'),
-            'limit' => prop('string', 'Max results (default: 20)'),
-        ], ['text']],
+            'live' => prop('string', 'true to skip the offline cache and ask the server'),
+        ] + $mailQuery, ['text']],
         'attachment_list' => ['List attachments on an email', [
             'id' => prop('string', 'Email ID'),
         ], ['id']],
@@ -93,13 +266,115 @@ $ports = [
             'id' => prop('string', 'Blob ID'),
             'name' => prop('string', 'Filename to save as'),
         ], ['id']],
+        'sync' => ['Update the offline cache used by query, search and read', [
+            'limit' => prop('string', 'Newest emails to load on a full sync (default: 5000)'),
+            'reset' => prop('string', 'true to discard the cache and load it again'),
+        ], []],
+        'sync_status' => ['Offline cache location, counts, states and age', [], []],
+    ],
+    'desktops' => [
+        'list' => ['List virtual desktops (id, name, number, current)', [], []],
+        'current' => ['Get the current virtual desktop', [], []],
+        'switch' => ['Switch to a virtual desktop', ['desktop' => prop('string', 'Desktop ID, name or 1-based number')], ['desktop']],
+        'create' => ['Create a virtual desktop', [
+            'name' => prop('string', 'Desktop name'),
+            'position' => prop('string', '1-based position (default: last)'),
+        ], ['name']],
+        'remove' => ['Remove a virtual desktop', ['desktop' => prop('string', 'Desktop ID, name or 1-based number')], ['desktop']],
+        'rename' => ['Rename a virtual desktop', [
+            'desktop' => prop('string', 'Desktop ID, name or 1-based number'),
+            'name' => prop('string', 'New name'),
+        ], ['desktop', 'name']],
+        'activity_list' => ['List activities (id, name, description, icon, state, current)', [], []],
+        'activity_current' => ['Get the current activity', [], []],
+        'activity_switch' => ['Switch to an activity', ['activity' => prop('string', 'Activity ID or name')], ['activity']],
+        'activity_start' => ['Start a stopped activity', ['activity' => prop('string', 'Activity ID or name')], ['activity']],
+        'activity_stop' => ['Stop a running activity', ['activity' => prop('string', 'Activity ID or name')], ['activity']],
     ],
     'windows' => [
-        'list' => ['List all open windows', [], []],
+        'list' => ['List open windows with geometry, output, desktops and state', [
+            'class' => prop('string', 'Match resource class or name (case-insensitive)'),
+            'title' => prop('string', 'Match caption substring (case-insensitive)'),
+            'title_regex' => prop('string', 'Match caption against a regex'),
+            'pid' => prop('string', 'Match process ID'),
+        ], []],
+        'wait' => ['Wait until a matching window exists, is active, or is gone', [
+            'class' => prop('string', 'Match resource class or name (case-insensitive)'),
+            'title' => prop('string', 'Match caption substring (case-insensitive)'),
+            'title_regex' => prop('string', 'Match caption against a regex'),
+            'pid' => prop('string', 'Match process ID'),
+            'condition' => prop('string', 'exists, active or gone (default: exists)', ['enum' => ['exists', 'active', 'gone']]),
+            'timeout' => prop('string', 'Timeout in ms (default: 10000)'),
+        ], []],
+        'layout_save' => ['Save the current window arrangement as a named layout', [
+            'name' => prop('string', 'Layout name (e.g. coding)'),
+        ], ['name']],
+        'layout_restore' => ['Restore a saved window layout', [
+            'name' => prop('string', 'Layout name'),
+            'launch' => prop('string', 'Launch apps with no matching window (true/false, default: false)'),
+            'launch_timeout' => prop('string', 'Ms to wait for launched apps\' windows (default: 10000)'),
+        ], ['name']],
+        'layout_list' => ['List saved window layouts', [], []],
         'activate' => ['Activate a window by ID', ['id' => prop('string', 'Window ID (UUID)')], ['id']],
+        'move' => ['Move a window to x,y', [
+            'id' => prop('string', 'Window ID (UUID)'),
+            'x' => prop('string', 'Left edge in global coordinates'),
+            'y' => prop('string', 'Top edge in global coordinates'),
+        ], ['id', 'x', 'y']],
+        'resize' => ['Resize a window', [
+            'id' => prop('string', 'Window ID (UUID)'),
+            'width' => prop('string', 'Frame width'),
+            'height' => prop('string', 'Frame height'),
+        ], ['id', 'width', 'height']],
+        'set_geometry' => ['Set a window\'s position and size', [
+            'id' => prop('string', 'Window ID (UUID)'),
+            'x' => prop('string', 'Left edge in global coordinates'),
+            'y' => prop('string', 'Top edge in global coordinates'),
+            'width' => prop('string', 'Frame width'),
+            'height' => prop('string', 'Frame height'),
+        ], ['id', 'x', 'y', 'width', 'height']],
+        'minimize' => ['Minimize a window', ['id' => prop('string', 'Window ID (UUID)')], ['id']],
+        'unminimize' => ['Unminimize a window', ['id' => prop('string', 'Window ID (UUID)')], ['id']],
+        'maximize' => ['Maximize a window', ['id' => prop('string', 'Window ID (UUID)')], ['id']],
+        'restore' => ['Restore a maximized window', ['id' => prop('string', 'Window ID (UUID)')], ['id']],
+        'fullscreen' => ['Set or toggle fullscreen', [
+            'id' => prop('string', 'Window ID (UUID)'),
+            'state' => prop('string', 'on, off or toggle (default: on)', ['enum' => ['on', 'off', 'toggle']]),
+        ], ['id']],
+        'close' => ['Close a window', ['id' => prop('string', 'Window ID (UUID)')], ['id']],
+        'keep_above' => ['Set or toggle keep-above', [
+            'id' => prop('string', 'Window ID (UUID)'),
+            'state' => prop('string', 'on, off or toggle (default: on)', ['enum' => ['on', 'off', 'toggle']]),
+        ], ['id']],
+        'keep_below' => ['Set or toggle keep-below', [
+            'id' => prop('string', 'Window ID (UUID)'),
+            'state' => prop('string', 'on, off or toggle (default: on)', ['enum' => ['on', 'off', 'toggle']]),
+        ], ['id']],
+        'shade' => ['Set or toggle shading (X11 windows only)', [
+            'id' => prop('string', 'Window ID (UUID)'),
+            'state' => prop('string', 'on, off or toggle (default: on)', ['enum' => ['on', 'off', 'toggle']]),
+        ], ['id']],
+        'set_desktop' => ['Move a window to a virtual desktop', [
+            'id' => prop('string', 'Window ID (UUID)'),
+            'desktop' => prop('string', 'Desktop id, name, or 1-based number'),
+        ], ['id', 'desktop']],
+        'set_activity' => ['Move a window to an activity', [
+            'id' => prop('string', 'Window ID (UUID)'),
+            'activity' => prop('string', 'Activity id, or "all"'),
+        ], ['id', 'activity']],
+        'move_to_output' => ['Move a window to another output', [
+            'id' => prop('string', 'Window ID (UUID)'),
+            'output' => prop('string', 'Output name (e.g. DP-1)'),
+        ], ['id', 'output']],
     ],
 ];
 
+// Every mail command acts on a named account from ~/.config/appmesh/mail/
+foreach ($ports['mail'] as $cmdName => $cmdDef) {
+    $ports['mail'][$cmdName][1]['account'] = prop('string', 'Named account (default: default)');
+}
+$ports['mail']['accounts'] = ['List configured mail accounts and their connection state', [], []];
+
 foreach ($ports as $portName => $commands) {
     foreach ($commands as $cmdName => $cmdDef) {
         [$description, $properties, $required] = $cmdDef;